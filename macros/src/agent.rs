@@ -0,0 +1,74 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{FnArg, ItemFn};
+
+pub fn expand(attr: TokenStream, item: ItemFn) -> syn::Result<TokenStream> {
+    if !attr.is_empty() {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "#[agent] does not take arguments",
+        ));
+    }
+
+    let sig = &item.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(syn::Error::new_spanned(
+            asyncness,
+            "#[agent] functions can not be async",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "#[agent] functions can not be generic",
+        ));
+    }
+
+    let sdk = quote!(::mamoru_rust_sdk);
+    let name = &sig.ident;
+    let mut check = quote!();
+    let call = match sig.inputs.len() {
+        0 => quote!(|(): ()| #name()),
+        1 => match &sig.inputs[0] {
+            FnArg::Typed(arg) => {
+                let ty = &arg.ty;
+                // Reports an unsupported context on the argument type rather than on the macro.
+                check = quote_spanned! {ty.span()=>
+                    fn __mamoru_check_context<C: #sdk::mamoru::AgentContext>() {}
+                    let _ = __mamoru_check_context::<#ty>;
+                };
+                quote!(|ctx: #ty| #name(ctx))
+            }
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "#[agent] functions can not take `self`",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig.inputs,
+                "#[agent] functions take at most one context argument",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        #item
+
+        const _: () = {
+            struct __MamoruAgent;
+
+            impl #sdk::Guest for __MamoruAgent {
+                fn run() {
+                    #check
+                    #sdk::mamoru::run_agent(#call);
+                }
+            }
+
+            #sdk::export!(__MamoruAgent with_types_in #sdk);
+        };
+    })
+}
@@ -0,0 +1,346 @@
+use mamoru_rust_sdk_parse::U256DigitsError;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    Data, DeriveInput, Expr, ExprLit, ExprUnary, Field, Fields, GenericArgument, Lit,
+    PathArguments, Type, UnOp,
+};
+
+/// Attributes of a field, with every value kept as the raw parameter string.
+struct ParamAttrs {
+    key: Option<String>,
+    default: Option<String>,
+    min: Option<String>,
+    max: Option<String>,
+}
+
+pub fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "AgentParameters can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "AgentParameters can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut specs = Vec::new();
+    let mut loads = Vec::new();
+    let mut idents = Vec::new();
+    let mut bindings = Vec::new();
+
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let attrs = parse_attrs(field)?;
+        let key = attrs.key.clone().unwrap_or_else(|| ident.to_string());
+        let binding = format_ident!("__param_{}", ident);
+
+        specs.push(spec(field, &key, &attrs));
+        loads.push(load(field, &key, &attrs, &binding));
+        idents.push(ident);
+        bindings.push(binding);
+    }
+
+    let sdk = quote!(::mamoru_rust_sdk::mamoru);
+    let build = if idents.is_empty() {
+        quote! {
+            if !errors.is_empty() {
+                return ::std::result::Result::Err(errors);
+            }
+
+            ::std::result::Result::Ok(Self {})
+        }
+    } else {
+        quote! {
+            match (#(#bindings,)*) {
+                (#(::std::option::Option::Some(#bindings),)*) if errors.is_empty() => {
+                    ::std::result::Result::Ok(Self { #(#idents: #bindings,)* })
+                }
+                _ => ::std::result::Result::Err(errors),
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics #sdk::AgentParameters for #name #ty_generics #where_clause {
+            fn schema() -> ::std::vec::Vec<#sdk::ParameterSpec> {
+                ::std::vec![#(#specs,)*]
+            }
+
+            fn load_from(
+                lookup: &dyn ::std::ops::Fn(&str) -> ::std::option::Option<::std::string::String>,
+            ) -> ::std::result::Result<Self, ::std::vec::Vec<#sdk::ParameterError>> {
+                let mut errors = ::std::vec::Vec::new();
+                #(#loads)*
+                #build
+            }
+        }
+    })
+}
+
+fn parse_attrs(field: &Field) -> syn::Result<ParamAttrs> {
+    let mut attrs = ParamAttrs {
+        key: None,
+        default: None,
+        min: None,
+        max: None,
+    };
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("param"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let (slot, name) = if meta.path.is_ident("key") {
+                (&mut attrs.key, "key")
+            } else if meta.path.is_ident("default") {
+                (&mut attrs.default, "default")
+            } else if meta.path.is_ident("min") {
+                (&mut attrs.min, "min")
+            } else if meta.path.is_ident("max") {
+                (&mut attrs.max, "max")
+            } else {
+                return Err(meta.error("expected one of `key`, `default`, `min` or `max`"));
+            };
+
+            let expr: Expr = meta.value()?.parse()?;
+            let value = literal_to_string(&expr)?;
+            if name != "key" {
+                check_value(&field.ty, &value).map_err(|reason| {
+                    let ty = field.ty.to_token_stream().to_string().replace(' ', "");
+                    syn::Error::new_spanned(&expr, format!("invalid `{name}` for `{ty}`: {reason}"))
+                })?;
+            }
+            *slot = Some(value);
+
+            Ok(())
+        })?;
+    }
+
+    if (attrs.min.is_some() || attrs.max.is_some()) && is_option(&field.ty) {
+        return Err(syn::Error::new_spanned(
+            &field.ty,
+            "`min` and `max` are not supported on optional parameters",
+        ));
+    }
+
+    Ok(attrs)
+}
+
+/// Turns `10`, `-1`, `1.5`, `true` or `"10m"` into the string the parameter would hold.
+fn literal_to_string(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Str(lit) => Ok(lit.value()),
+            Lit::Int(lit) => Ok(lit.base10_digits().to_string()),
+            Lit::Float(lit) => Ok(lit.base10_digits().to_string()),
+            Lit::Bool(lit) => Ok(lit.value.to_string()),
+            Lit::Char(lit) => Ok(lit.value().to_string()),
+            _ => Err(syn::Error::new_spanned(lit, "unsupported literal")),
+        },
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => Ok(format!("-{}", literal_to_string(expr)?)),
+        _ => Err(syn::Error::new_spanned(expr, "expected a literal")),
+    }
+}
+
+/// Parses `value` the way `FromParameter` would for the known types, so that a malformed default
+/// or bound fails the build instead of every parameter load. Other types are checked when loaded.
+fn check_value(ty: &Type, value: &str) -> Result<(), String> {
+    let Some((name, inner)) = last_segment(ty) else {
+        return Ok(());
+    };
+
+    macro_rules! check_integer {
+        ($($ty:ident),*) => {
+            match name.as_str() {
+                $(stringify!($ty) => {
+                    let (digits, radix) = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+                        Some(hex) => (hex, 16),
+                        None => (value, 10),
+                    };
+                    return $ty::from_str_radix(digits, radix).map(|_| ()).map_err(|e| e.to_string());
+                })*
+                _ => {}
+            }
+        };
+    }
+
+    check_integer!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+    match (name.as_str(), inner) {
+        ("f32", _) => value.parse::<f32>().map(|_| ()).map_err(|e| e.to_string()),
+        ("f64", _) => value.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()),
+        ("bool", _) => value.parse::<bool>().map(|_| ()).map_err(|e| e.to_string()),
+        ("char", _) => value.parse::<char>().map(|_| ()).map_err(|e| e.to_string()),
+        ("Duration", _) => mamoru_rust_sdk_parse::parse_duration(value)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        ("U256", _) => check_u256(value),
+        ("Option", Some(inner)) => check_value(inner, value),
+        ("Vec", Some(inner)) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .try_for_each(|entry| check_value(inner, entry)),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the name of the last path segment of a type and its first type argument.
+fn last_segment(ty: &Type) -> Option<(String, Option<&Type>)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    let inner = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    };
+
+    Some((segment.ident.to_string(), inner))
+}
+
+/// Checks a decimal or `0x` prefixed hexadecimal 256-bit integer, as `U256::from_str` reads it.
+fn check_u256(value: &str) -> Result<(), String> {
+    match mamoru_rust_sdk_parse::strip_hex_prefix(value) {
+        Some(digits) => mamoru_rust_sdk_parse::check_hex_u256(digits),
+        None => mamoru_rust_sdk_parse::check_decimal_u256(value),
+    }
+    .map_err(|e| match e {
+        U256DigitsError::InvalidDigit => format!("invalid digit in `{value}`"),
+        other => other.to_string(),
+    })
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn option_tokens(value: &Option<String>) -> TokenStream {
+    match value {
+        Some(value) => quote!(::std::option::Option::Some(#value)),
+        None => quote!(::std::option::Option::None),
+    }
+}
+
+fn spec(field: &Field, key: &str, attrs: &ParamAttrs) -> TokenStream {
+    let type_name = field.ty.to_token_stream().to_string().replace(' ', "");
+    let required = attrs.default.is_none() && !is_option(&field.ty);
+    let default = option_tokens(&attrs.default);
+    let min = option_tokens(&attrs.min);
+    let max = option_tokens(&attrs.max);
+
+    quote! {
+        ::mamoru_rust_sdk::mamoru::ParameterSpec {
+            key: #key,
+            type_name: #type_name,
+            required: #required,
+            default: #default,
+            min: #min,
+            max: #max,
+        }
+    }
+}
+
+fn load(field: &Field, key: &str, attrs: &ParamAttrs, binding: &syn::Ident) -> TokenStream {
+    let ty = &field.ty;
+    let sdk = quote!(::mamoru_rust_sdk::mamoru);
+    let raw = match &attrs.default {
+        Some(default) => quote! {
+            match lookup(#key) {
+                ::std::option::Option::Some(raw) => (::std::option::Option::Some(raw), false),
+                ::std::option::Option::None => {
+                    (::std::option::Option::Some(#default.to_string()), true)
+                }
+            }
+        },
+        None => quote!((lookup(#key), false)),
+    };
+
+    let check = if attrs.min.is_some() || attrs.max.is_some() {
+        let bound = |value: &Option<String>| match value {
+            Some(value) => quote! {
+                match <#ty as #sdk::FromParameter>::from_parameter(#key, ::std::option::Option::Some(#value)) {
+                    ::std::result::Result::Ok(bound) => ::std::option::Option::Some(bound),
+                    ::std::result::Result::Err(error) => {
+                        errors.push(error.into_invalid_declaration());
+                        ::std::option::Option::None
+                    }
+                }
+            },
+            None => quote!(::std::option::Option::None),
+        };
+        let min = bound(&attrs.min);
+        let max = bound(&attrs.max);
+        let bounds = match (&attrs.min, &attrs.max) {
+            (Some(min), Some(max)) => format!("a value between {min} and {max}"),
+            (Some(min), None) => format!("a value of at least {min}"),
+            (None, Some(max)) => format!("a value of at most {max}"),
+            (None, None) => unreachable!(),
+        };
+
+        quote! {
+            let min: ::std::option::Option<#ty> = #min;
+            let max: ::std::option::Option<#ty> = #max;
+            if let ::std::result::Result::Err(error) = #sdk::check_parameter_bounds(
+                #key,
+                &value,
+                raw.as_deref().unwrap_or_default(),
+                min.as_ref(),
+                max.as_ref(),
+                #bounds,
+            ) {
+                errors.push(error);
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    quote! {
+        let #binding: ::std::option::Option<#ty> = {
+            let (raw, defaulted) = #raw;
+            match <#ty as #sdk::FromParameter>::from_parameter(#key, raw.as_deref()) {
+                ::std::result::Result::Ok(value) => {
+                    #check
+                    ::std::option::Option::Some(value)
+                }
+                ::std::result::Result::Err(error) if defaulted => {
+                    errors.push(error.into_invalid_declaration());
+                    ::std::option::Option::None
+                }
+                ::std::result::Result::Err(error) => {
+                    errors.push(error);
+                    ::std::option::Option::None
+                }
+            }
+        };
+    }
+}
@@ -0,0 +1,88 @@
+use proc_macro::TokenStream;
+
+mod agent;
+mod agent_parameters;
+mod schema;
+mod value_data;
+
+/// Derives `mamoru_rust_sdk::mamoru::AgentParameters` for a struct with named fields.
+///
+/// Each field is read from the parameter with the same name, parsed with `FromParameter`.
+/// Supported field attributes:
+/// - `#[param(key = "...")]`: reads the field from another parameter key.
+/// - `#[param(default = ...)]`: value used when the parameter is missing.
+/// - `#[param(min = ..., max = ...)]`: inclusive bounds checked after parsing.
+///
+/// Defaults and bounds of integer, float, `bool`, `char`, `Duration` and `U256` fields, or of
+/// `Option` and `Vec` of them, are checked at build time. Other types report a
+/// `ParameterError::InvalidDeclaration` when loaded.
+#[proc_macro_derive(AgentParameters, attributes(param))]
+pub fn derive_agent_parameters(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    agent_parameters::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Exports a function as the `run` entrypoint of the `mamoru-core` world.
+///
+/// The function takes no argument or a single chain context implementing
+/// `mamoru_rust_sdk::mamoru::AgentContext`, and returns `Result<(), AgentError>`. The panic and
+/// log hooks are installed before it runs, and a returned error is reported as an `Error`
+/// incident.
+///
+/// `SuiCtx` is the only chain context type. Agents of other chains take no argument and read the
+/// chain through their context imports or `mamoru::query`; other argument types fail to build.
+#[proc_macro_attribute]
+pub fn agent(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = syn::parse_macro_input!(item as syn::ItemFn);
+
+    agent::expand(attr.into(), item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `mamoru_rust_sdk::mamoru_serialize::HasSchema` for a struct with named fields.
+///
+/// Each field becomes a record field with the schema of its type, required unless it is an
+/// `Option`. The `#[serde(rename_all = "...")]` type attribute and the `#[serde(rename = "...")]`,
+/// `#[serde(default)]` and `#[serde(skip)]` field attributes are taken into account, as is the
+/// `#[value(rename = "...")]` field attribute of the `IntoValueData` and `FromValueData` derives,
+/// which takes precedence.
+#[proc_macro_derive(HasSchema, attributes(serde, value))]
+pub fn derive_has_schema(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    schema::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `mamoru_rust_sdk::mamoru_serialize::IntoValueData` and `TryFrom<T> for ValueData`.
+///
+/// Structs become a `STRUCT` named after the type and enums a `STRUCT` named after the enum with
+/// a `variant` field. Supported attributes:
+/// - `#[value(rename = "...")]` on the type, a variant or a field: the name to use instead.
+/// - `#[value(tag = "...")]` on an enum: the name of the variant field.
+/// - `#[value(u256)]` on a field: stores an integer as a `U256`.
+/// - `#[value(bytes)]` on a field: stores a `Vec<u8>` or a byte array as `BYTES`.
+#[proc_macro_derive(IntoValueData, attributes(value))]
+pub fn derive_into_value_data(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    value_data::expand_into(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `mamoru_rust_sdk::mamoru_serialize::FromValueData`, reading what `IntoValueData`
+/// writes. It accepts the same attributes.
+#[proc_macro_derive(FromValueData, attributes(value))]
+pub fn derive_from_value_data(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    value_data::expand_from(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
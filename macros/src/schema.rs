@@ -0,0 +1,220 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_quote, Attribute, Data, DeriveInput, Expr, ExprLit, Fields, Lit, LitStr, Meta, MetaList,
+    Token,
+};
+
+/// The `serde` and `value` attributes of a field or of the type that change its value-data shape.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    default: bool,
+    skip: bool,
+}
+
+/// The case conventions of `#[serde(rename_all = "...")]`.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(lit: &LitStr) -> syn::Result<Self> {
+        Ok(match lit.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return Err(syn::Error::new_spanned(lit, "unknown rename rule")),
+        })
+    }
+
+    /// Renames a `snake_case` field, as serde does.
+    fn apply(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => field
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                        .unwrap_or_default()
+                })
+                .collect(),
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply(field);
+                let mut chars = pascal.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+}
+
+pub fn expand(mut input: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "HasSchema can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "HasSchema can only be derived for structs",
+            ))
+        }
+    };
+
+    let sdk = quote!(::mamoru_rust_sdk::mamoru_serialize);
+    let rename_all = serde_attrs(&input.attrs)?.rename_all;
+    let mut schema_fields = Vec::new();
+
+    for field in fields {
+        let attrs = serde_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().expect("named field");
+        let ident = ident.to_string();
+        let ident = ident.strip_prefix("r#").unwrap_or(&ident);
+        let name = attrs.rename.unwrap_or_else(|| match rename_all {
+            Some(rule) => rule.apply(ident),
+            None => ident.to_string(),
+        });
+        let ty = &field.ty;
+        let default = attrs.default;
+
+        schema_fields.push(quote! {
+            .field(
+                #name,
+                <#ty as #sdk::HasSchema>::schema(),
+                !#default && !<#ty as #sdk::HasSchema>::is_optional(),
+            )
+        });
+    }
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#sdk::HasSchema));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #sdk::HasSchema for #name #ty_generics #where_clause {
+            fn schema() -> #sdk::ValueSchema {
+                #sdk::ValueSchema::record()
+                    #(#schema_fields)*
+                    .into()
+            }
+        }
+    })
+}
+
+/// Reads the `serde` attributes, then the `rename` of the `value` attributes, which takes
+/// precedence as it is what `IntoValueData` and `FromValueData` derives write and read.
+fn serde_attrs(attrs: &[Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut result = SerdeAttrs::default();
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+
+        for meta in metas {
+            match meta {
+                Meta::NameValue(meta) if meta.path.is_ident("rename") => {
+                    if let Some(lit) = string_value(&meta.value) {
+                        result.rename = Some(lit.value());
+                    }
+                }
+                Meta::List(list) if list.path.is_ident("rename") => {
+                    if let Some(lit) = deserialize_value(&list)? {
+                        result.rename = Some(lit.value());
+                    }
+                }
+                Meta::NameValue(meta) if meta.path.is_ident("rename_all") => {
+                    if let Some(lit) = string_value(&meta.value) {
+                        result.rename_all = Some(RenameRule::parse(lit)?);
+                    }
+                }
+                Meta::List(list) if list.path.is_ident("rename_all") => {
+                    if let Some(lit) = deserialize_value(&list)? {
+                        result.rename_all = Some(RenameRule::parse(&lit)?);
+                    }
+                }
+                Meta::Path(path)
+                    if path.is_ident("skip") || path.is_ident("skip_deserializing") =>
+                {
+                    result.skip = true;
+                }
+                Meta::Path(path) if path.is_ident("default") => result.default = true,
+                Meta::NameValue(meta) if meta.path.is_ident("default") => result.default = true,
+                _ => {}
+            }
+        }
+    }
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("value")) {
+        let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+
+        for meta in metas {
+            if let Meta::NameValue(meta) = meta {
+                if meta.path.is_ident("rename") {
+                    if let Some(lit) = string_value(&meta.value) {
+                        result.rename = Some(lit.value());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+fn string_value(expr: &Expr) -> Option<&LitStr> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => Some(lit),
+        _ => None,
+    }
+}
+
+/// Reads the `deserialize` name of `rename(serialize = "...", deserialize = "...")`, the one
+/// `from_value_data` follows.
+fn deserialize_value(list: &MetaList) -> syn::Result<Option<LitStr>> {
+    let metas = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+
+    Ok(metas.into_iter().find_map(|meta| match meta {
+        Meta::NameValue(meta) if meta.path.is_ident("deserialize") => {
+            string_value(&meta.value).cloned()
+        }
+        _ => None,
+    }))
+}
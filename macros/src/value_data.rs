@@ -0,0 +1,345 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, Attribute, Data, DeriveInput, Expr, ExprLit, Field, Fields, GenericArgument,
+    Ident, Index, Lit, Member, PathArguments, Type,
+};
+
+/// Attributes of the type, a variant or a field.
+#[derive(Default)]
+struct ValueAttrs {
+    rename: Option<String>,
+    tag: Option<String>,
+    u256: bool,
+    bytes: bool,
+}
+
+/// How a field is stored.
+enum Hint {
+    None,
+    U256,
+    Bytes,
+}
+
+/// A field of a struct or a variant, with the binding it is read into or written from.
+struct FieldInfo<'a> {
+    member: Member,
+    binding: Ident,
+    name: String,
+    ty: &'a Type,
+    optional: bool,
+    hint: Hint,
+}
+
+pub fn expand_into(mut input: DeriveInput) -> syn::Result<TokenStream> {
+    let sdk = quote!(::mamoru_rust_sdk::mamoru_serialize);
+    let types = quote!(::mamoru_rust_sdk::component::guest::types);
+    let attrs = parse_attrs(&input.attrs)?;
+    let type_name = attrs.rename.unwrap_or_else(|| input.ident.to_string());
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = fields(&data.fields)?;
+            let bindings = fields.iter().map(|field| &field.binding);
+            let members = fields.iter().map(|field| &field.member);
+            let writes = fields.iter().map(write_field);
+            let mutability = (!fields.is_empty()).then(|| quote!(mut));
+
+            quote! {
+                let Self { #(#members: #bindings,)* } = self;
+                let #mutability writer = #sdk::StructWriter::new(data, #type_name);
+                #(#writes)*
+                writer.finish()
+            }
+        }
+        Data::Enum(data) => {
+            let tag = attrs.tag.unwrap_or_else(|| "variant".to_string());
+            let mut arms = Vec::new();
+
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let variant_name = parse_attrs(&variant.attrs)?
+                    .rename
+                    .unwrap_or_else(|| ident.to_string());
+                let fields = fields(&variant.fields)?;
+                check_tag(&tag, &fields, ident)?;
+
+                let bindings = fields.iter().map(|field| &field.binding);
+                let members = fields.iter().map(|field| &field.member);
+                let writes = fields.iter().map(write_field);
+
+                arms.push(quote! {
+                    Self::#ident { #(#members: #bindings,)* } => {
+                        let mut writer = #sdk::StructWriter::new(data, #type_name);
+                        writer.field(#tag, #variant_name);
+                        #(#writes)*
+                        writer.finish()
+                    }
+                });
+            }
+
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "IntoValueData can not be derived for unions",
+            ))
+        }
+    };
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#sdk::IntoValueData));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #sdk::IntoValueData for #name #ty_generics #where_clause {
+            fn write_value_data(
+                self,
+                data: &mut ::std::vec::Vec<#types::ValueType>,
+            ) -> ::std::result::Result<#types::ValueType, #sdk::IndexOverflow> {
+                #body
+            }
+        }
+
+        impl #impl_generics ::std::convert::TryFrom<#name #ty_generics> for #types::ValueData
+            #where_clause
+        {
+            type Error = #sdk::IndexOverflow;
+
+            fn try_from(value: #name #ty_generics) -> ::std::result::Result<Self, Self::Error> {
+                #sdk::IntoValueData::try_into_value_data(value)
+            }
+        }
+    })
+}
+
+pub fn expand_from(mut input: DeriveInput) -> syn::Result<TokenStream> {
+    let sdk = quote!(::mamoru_rust_sdk::mamoru_serialize);
+    let attrs = parse_attrs(&input.attrs)?;
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = fields(&data.fields)?;
+            let members = fields.iter().map(|field| &field.member);
+            let reads = fields.iter().map(read_field);
+
+            quote! {
+                ::std::result::Result::Ok(Self { #(#members: #reads,)* })
+            }
+        }
+        Data::Enum(data) => {
+            let tag = attrs.tag.unwrap_or_else(|| "variant".to_string());
+            let mut arms = Vec::new();
+
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let variant_name = parse_attrs(&variant.attrs)?
+                    .rename
+                    .unwrap_or_else(|| ident.to_string());
+                let fields = fields(&variant.fields)?;
+                check_tag(&tag, &fields, ident)?;
+
+                let members = fields.iter().map(|field| &field.member);
+                let reads = fields.iter().map(read_field);
+
+                arms.push(quote! {
+                    #variant_name => ::std::result::Result::Ok(Self::#ident { #(#members: #reads,)* }),
+                });
+            }
+
+            quote! {
+                let tag = view.field(#tag)?;
+                match tag.as_str()? {
+                    #(#arms)*
+                    other => ::std::result::Result::Err(
+                        tag.invalid(::std::format!("unknown variant `{other}`")),
+                    ),
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromValueData can not be derived for unions",
+            ))
+        }
+    };
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#sdk::FromValueData));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #sdk::FromValueData for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn from_view(
+                view: &#sdk::ValueDataView,
+            ) -> ::std::result::Result<Self, #sdk::ViewError> {
+                #body
+            }
+        }
+    })
+}
+
+fn write_field(field: &FieldInfo) -> TokenStream {
+    let name = &field.name;
+    let binding = &field.binding;
+    let method = match field.hint {
+        Hint::None => quote!(field),
+        Hint::U256 => quote!(u256),
+        Hint::Bytes => quote!(bytes),
+    };
+
+    if field.optional {
+        quote! {
+            if let ::std::option::Option::Some(value) = #binding {
+                writer.#method(#name, value);
+            }
+        }
+    } else {
+        quote!(writer.#method(#name, #binding);)
+    }
+}
+
+fn read_field(field: &FieldInfo) -> TokenStream {
+    let sdk = quote!(::mamoru_rust_sdk::mamoru_serialize);
+    let name = &field.name;
+    let ty = if field.optional {
+        option_inner(field.ty).unwrap_or(field.ty)
+    } else {
+        field.ty
+    };
+    let read = match field.hint {
+        Hint::None => quote!(<#ty as #sdk::FromValueData>::from_view),
+        Hint::U256 => quote!(#sdk::read_u256::<#ty>),
+        Hint::Bytes => quote!(#sdk::read_bytes::<#ty>),
+    };
+
+    if field.optional {
+        quote!(#sdk::read_optional_field(view, #name, #read)?)
+    } else {
+        quote!(#read(&view.field(#name)?)?)
+    }
+}
+
+fn fields(fields: &Fields) -> syn::Result<Vec<FieldInfo<'_>>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(position, field)| field_info(position, field))
+        .collect()
+}
+
+fn field_info(position: usize, field: &Field) -> syn::Result<FieldInfo<'_>> {
+    let attrs = parse_attrs(&field.attrs)?;
+    if attrs.tag.is_some() {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`tag` is only supported on enums",
+        ));
+    }
+    if attrs.u256 && attrs.bytes {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`u256` and `bytes` can not be used together",
+        ));
+    }
+
+    let (member, default_name) = match &field.ident {
+        Some(ident) => (Member::Named(ident.clone()), ident.to_string()),
+        None => (Member::Unnamed(Index::from(position)), position.to_string()),
+    };
+    let hint = if attrs.u256 {
+        Hint::U256
+    } else if attrs.bytes {
+        Hint::Bytes
+    } else {
+        Hint::None
+    };
+
+    Ok(FieldInfo {
+        member,
+        binding: format_ident!("__field{}", position),
+        name: attrs.rename.unwrap_or(default_name),
+        ty: &field.ty,
+        optional: option_inner(&field.ty).is_some(),
+        hint,
+    })
+}
+
+fn check_tag(tag: &str, fields: &[FieldInfo], variant: &Ident) -> syn::Result<()> {
+    if fields.iter().any(|field| field.name == tag) {
+        return Err(syn::Error::new_spanned(
+            variant,
+            format!("field `{tag}` collides with the variant tag, rename one of them"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns `T` for an `Option<T>` type.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn parse_attrs(attrs: &[Attribute]) -> syn::Result<ValueAttrs> {
+    let mut result = ValueAttrs::default();
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("value")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("u256") {
+                result.u256 = true;
+                return Ok(());
+            }
+            if meta.path.is_ident("bytes") {
+                result.bytes = true;
+                return Ok(());
+            }
+
+            let slot = if meta.path.is_ident("rename") {
+                &mut result.rename
+            } else if meta.path.is_ident("tag") {
+                &mut result.tag
+            } else {
+                return Err(meta.error("expected one of `rename`, `tag`, `u256` or `bytes`"));
+            };
+
+            match meta.value()?.parse()? {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(lit), ..
+                }) => *slot = Some(lit.value()),
+                expr => return Err(syn::Error::new_spanned(expr, "expected a string literal")),
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(result)
+}
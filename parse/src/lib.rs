@@ -0,0 +1,191 @@
+//! Parsers shared by `mamoru-rust-sdk` and `mamoru-rust-sdk-macros`, so that the defaults the
+//! macros check at build time follow the rules the SDK reads parameters with at run time.
+
+use std::time::Duration;
+
+/// Errors returned by [`parse_duration`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DurationError {
+    #[error("expected a number before each unit")]
+    MissingAmount,
+    #[error("unknown duration unit `{0}`, expected one of ms, s, m, h, d")]
+    UnknownUnit(String),
+    #[error("duration overflows")]
+    Overflow,
+}
+
+/// Errors returned by [`check_decimal_u256`] and [`check_hex_u256`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum U256DigitsError {
+    #[error("empty number")]
+    Empty,
+    #[error("invalid digit")]
+    InvalidDigit,
+    #[error("number does not fit in 256 bits")]
+    Overflow,
+}
+
+/// Parses a duration written as seconds, e.g. `90`, or as amounts with a unit, e.g. `1h30m`.
+///
+/// Parameters:
+/// - `value`: The duration, with `ms`, `s`, `m`, `h` or `d` units.
+///
+/// Returns:
+/// The duration, or a `DurationError` if it is malformed or does not fit in a `Duration`.
+pub fn parse_duration(value: &str) -> Result<Duration, DurationError> {
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(DurationError::MissingAmount);
+        }
+        let amount: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| DurationError::Overflow)?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "ms" => Duration::from_millis(1),
+            "s" => Duration::from_secs(1),
+            "m" => Duration::from_secs(60),
+            "h" => Duration::from_secs(60 * 60),
+            "d" => Duration::from_secs(24 * 60 * 60),
+            other => return Err(DurationError::UnknownUnit(other.to_string())),
+        };
+        rest = &rest[unit_len..];
+
+        total = u128::from(amount)
+            .checked_mul(unit.as_nanos())
+            .and_then(duration_from_nanos)
+            .and_then(|part| total.checked_add(part))
+            .ok_or(DurationError::Overflow)?;
+    }
+
+    Ok(total)
+}
+
+/// Returns the `Duration` of `nanos` nanoseconds, `None` if it does not fit.
+fn duration_from_nanos(nanos: u128) -> Option<Duration> {
+    const NANOS_PER_SEC: u128 = 1_000_000_000;
+    let secs = u64::try_from(nanos / NANOS_PER_SEC).ok()?;
+
+    Some(Duration::new(secs, (nanos % NANOS_PER_SEC) as u32))
+}
+
+/// Checks that decimal digits, without sign or prefix, fit in 256 bits.
+pub fn check_decimal_u256(digits: &str) -> Result<(), U256DigitsError> {
+    const MAX: &str =
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+
+    check_digits(digits, |b| b.is_ascii_digit())?;
+
+    let significant = digits.trim_start_matches('0');
+    if (significant.len(), significant) > (MAX.len(), MAX) {
+        return Err(U256DigitsError::Overflow);
+    }
+
+    Ok(())
+}
+
+/// Checks that hexadecimal digits, without the `0x` prefix, fit in 256 bits.
+pub fn check_hex_u256(digits: &str) -> Result<(), U256DigitsError> {
+    check_digits(digits, |b| b.is_ascii_hexdigit())?;
+
+    if digits.trim_start_matches('0').len() > 64 {
+        return Err(U256DigitsError::Overflow);
+    }
+
+    Ok(())
+}
+
+fn check_digits(digits: &str, valid: impl Fn(u8) -> bool) -> Result<(), U256DigitsError> {
+    if digits.is_empty() {
+        return Err(U256DigitsError::Empty);
+    }
+    if !digits.bytes().all(valid) {
+        return Err(U256DigitsError::InvalidDigit);
+    }
+
+    Ok(())
+}
+
+/// Strips the `0x` or `0X` prefix of a hexadecimal number.
+pub fn strip_hex_prefix(value: &str) -> Option<&str> {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_duration("1s500ms"), Ok(Duration::from_millis(1500)));
+        assert_eq!(
+            parse_duration("2d"),
+            Ok(Duration::from_secs(2 * 24 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        assert_eq!(parse_duration("h"), Err(DurationError::MissingAmount));
+        assert_eq!(
+            parse_duration("1h-1m"),
+            Err(DurationError::UnknownUnit("h-".to_string()))
+        );
+        assert_eq!(
+            parse_duration("10w"),
+            Err(DurationError::UnknownUnit("w".to_string()))
+        );
+        assert_eq!(
+            parse_duration("99999999999999999999d"),
+            Err(DurationError::Overflow)
+        );
+        assert_eq!(
+            parse_duration(&format!("{}s1s", u64::MAX)),
+            Err(DurationError::Overflow)
+        );
+    }
+
+    #[test]
+    fn checks_u256_digits() {
+        const MAX: &str =
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+
+        assert_eq!(check_decimal_u256(MAX), Ok(()));
+        assert_eq!(check_decimal_u256(&format!("000{MAX}")), Ok(()));
+        assert_eq!(
+            check_decimal_u256(
+                "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+            ),
+            Err(U256DigitsError::Overflow)
+        );
+        assert_eq!(check_decimal_u256(""), Err(U256DigitsError::Empty));
+        assert_eq!(check_decimal_u256("1f"), Err(U256DigitsError::InvalidDigit));
+
+        assert_eq!(check_hex_u256(&"f".repeat(64)), Ok(()));
+        assert_eq!(check_hex_u256(&format!("00{}", "f".repeat(64))), Ok(()));
+        assert_eq!(
+            check_hex_u256(&format!("1{}", "0".repeat(64))),
+            Err(U256DigitsError::Overflow)
+        );
+        assert_eq!(check_hex_u256("zz"), Err(U256DigitsError::InvalidDigit));
+        assert_eq!(strip_hex_prefix("0Xff"), Some("ff"));
+        assert_eq!(strip_hex_prefix("ff"), None);
+    }
+}
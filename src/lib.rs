@@ -1,11 +1,30 @@
+extern crate self as mamoru_rust_sdk;
+
 pub mod mamoru;
+pub mod mamoru_bcs;
+pub mod mamoru_budget;
+pub mod mamoru_env;
+pub mod mamoru_log;
+pub mod mamoru_query;
 pub mod mamoru_serialize;
 pub mod mamoru_storage;
+// The Sui types mirror the host layout, including fields that are not read in this crate.
+#[allow(dead_code)]
 pub mod sui_ctx;
+pub mod u256;
 
-use wit_bindgen::generate;
+// Generated in a module of its own: with `pub_export_macro` the exported macros land at the crate
+// root, where they would clash with the `use` items `generate!` emits next to them. The generated
+// code decodes WIT bools with a transmute.
+#[allow(clippy::transmute_int_to_bool)]
+mod bindings {
+    wit_bindgen::generate!({
+        world: "mamoru-core",
+        path: "wit/components.wit",
+        // Lets agent crates export `run` through `#[mamoru::agent]`.
+        pub_export_macro: true,
+        default_bindings_module: "mamoru_rust_sdk",
+    });
+}
 
-generate!({
-    world: "mamoru-core",
-    path: "wit/components.wit",
-});
+pub use bindings::*;
@@ -1,11 +1,29 @@
-use crate::component::guest::types::{Incident, ValueData};
+use crate::component::guest::types::{
+    HttpMethod as WitHttpMethod, HttpRequest as WitHttpRequest, HttpResponse as WitHttpResponse,
+    Incident, ValueData, ValueType,
+};
+use crate::mamoru_budget::{apply_budget, encoded_size, payload_budget, BudgetError};
+use crate::mamoru_query::QueryError;
+use crate::mamoru_storage::StorageError;
+use crate::u256::{U256Error, U256};
+use mamoru_rust_sdk_parse::DurationError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::num::IntErrorKind;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub use mamoru_rust_sdk_macros::{agent, AgentParameters};
 
 /// Type alias for TransactionId, using String instead of u64 for more flexibility
 type TransactionId = String;
 
-// TODO remove this severity and apply wit components
 /// Represents the severity level of an incident, where higher numbers indicate greater severity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum IncidentSeverity {
     Info = 0,
     Warning = 1,
@@ -13,12 +31,56 @@ pub enum IncidentSeverity {
     Alert = 3,
 }
 
+impl From<IncidentSeverity> for u64 {
+    fn from(severity: IncidentSeverity) -> Self {
+        severity as u64
+    }
+}
+
+impl TryFrom<u64> for IncidentSeverity {
+    type Error = IncidentError;
+
+    fn try_from(value: u64) -> Result<Self, IncidentError> {
+        match value {
+            0 => Ok(IncidentSeverity::Info),
+            1 => Ok(IncidentSeverity::Warning),
+            2 => Ok(IncidentSeverity::Error),
+            3 => Ok(IncidentSeverity::Alert),
+            other => Err(IncidentError::UnknownSeverity(other)),
+        }
+    }
+}
+
+/// Errors returned when building an incident.
+#[derive(Debug, thiserror::Error)]
+pub enum IncidentError {
+    #[error("incident message can not be empty")]
+    EmptyMessage,
+    #[error("unknown incident severity {0}")]
+    UnknownSeverity(u64),
+    #[error(transparent)]
+    OverBudget(#[from] BudgetError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
 impl Incident {
+    /// Creates a new `IncidentBuilder` for the given transaction.
+    ///
+    /// Parameters:
+    /// - `tx_hash`: The transaction identifier.
+    ///
+    /// Returns:
+    /// An `IncidentBuilder` with an `Info` severity and no message.
+    pub fn builder(tx_hash: impl Into<TransactionId>) -> IncidentBuilder {
+        IncidentBuilder::new(tx_hash.into())
+    }
+
     /// Creates a new `Incident` with an `Info` severity.
     ///
     /// Parameters:
     /// - `tx_id`: The transaction identifier.
-    /// - `message`: Descriptive message about the incident.
+    /// - `message`: Descriptive message about the incident, [`report`] rejects an empty one.
     /// - `data`: Optional data related to the incident.
     /// - `address`: Optional address involved in the incident.
     ///
@@ -29,9 +91,55 @@ impl Incident {
         message: &str,
         data: Option<ValueData>,
         address: Option<String>,
+    ) -> Self {
+        Self::with_severity(IncidentSeverity::Info, tx_id, message, data, address)
+    }
+
+    /// Creates a new `Incident` with a `Warning` severity.
+    ///
+    /// See [`Incident::new_info`] for the parameters.
+    pub fn new_warning(
+        tx_id: TransactionId,
+        message: &str,
+        data: Option<ValueData>,
+        address: Option<String>,
+    ) -> Self {
+        Self::with_severity(IncidentSeverity::Warning, tx_id, message, data, address)
+    }
+
+    /// Creates a new `Incident` with an `Error` severity.
+    ///
+    /// See [`Incident::new_info`] for the parameters.
+    pub fn new_error(
+        tx_id: TransactionId,
+        message: &str,
+        data: Option<ValueData>,
+        address: Option<String>,
+    ) -> Self {
+        Self::with_severity(IncidentSeverity::Error, tx_id, message, data, address)
+    }
+
+    /// Creates a new `Incident` with an `Alert` severity.
+    ///
+    /// See [`Incident::new_info`] for the parameters.
+    pub fn new_alert(
+        tx_id: TransactionId,
+        message: &str,
+        data: Option<ValueData>,
+        address: Option<String>,
+    ) -> Self {
+        Self::with_severity(IncidentSeverity::Alert, tx_id, message, data, address)
+    }
+
+    fn with_severity(
+        severity: IncidentSeverity,
+        tx_id: TransactionId,
+        message: &str,
+        data: Option<ValueData>,
+        address: Option<String>,
     ) -> Self {
         Incident {
-            severity: IncidentSeverity::Info as u64,
+            severity: severity.into(),
             message: message.to_string(),
             tx_hash: tx_id,
             address: address.unwrap_or_default(),
@@ -40,9 +148,315 @@ impl Incident {
     }
 }
 
+/// A builder for incidents, validating the fields before they are sent to the host.
+#[derive(Clone, Debug)]
+pub struct IncidentBuilder {
+    tx_hash: TransactionId,
+    severity: IncidentSeverity,
+    message: String,
+    address: Option<String>,
+    data: Option<ValueData>,
+}
+
+impl IncidentBuilder {
+    fn new(tx_hash: TransactionId) -> Self {
+        IncidentBuilder {
+            tx_hash,
+            severity: IncidentSeverity::Info,
+            message: String::new(),
+            address: None,
+            data: None,
+        }
+    }
+
+    /// Sets the severity of the incident, `Info` by default.
+    pub fn severity(mut self, severity: IncidentSeverity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Sets the descriptive message of the incident. It is required and can not be empty.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    /// Sets the address involved in the incident.
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    /// Attaches data related to the incident.
+    pub fn data(mut self, data: impl Into<ValueData>) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// Validates the fields and converts the builder into a WIT `Incident`.
+    ///
+    /// Returns:
+    /// The `Incident`, or an `IncidentError` if the message is empty.
+    pub fn build(self) -> Result<Incident, IncidentError> {
+        if self.message.trim().is_empty() {
+            return Err(IncidentError::EmptyMessage);
+        }
+
+        Ok(Incident {
+            severity: self.severity.into(),
+            message: self.message,
+            tx_hash: self.tx_hash,
+            address: self.address.unwrap_or_default(),
+            data: self.data,
+        })
+    }
+
+    /// Builds the incident and reports it.
+    ///
+    /// Returns:
+    /// An `IncidentError` if the incident is not valid or does not fit in the payload budget, in
+    /// which case nothing is reported.
+    pub fn report(self) -> Result<(), IncidentError> {
+        report(&self.build()?)
+    }
+}
+
 /// Reports an incident by calling a function from the guest component.
-pub fn report(incident: &Incident) {
-    crate::component::guest::mamoru::report(incident);
+///
+/// The message and data are first shortened to fit in the current
+/// [`crate::mamoru_budget::PayloadBudget`].
+///
+/// Returns:
+/// An `IncidentError::EmptyMessage` if the message is empty or only whitespace, or an
+/// `IncidentError::OverBudget` if the incident can not be brought within the budget, in which
+/// case nothing is reported.
+pub fn report(incident: &Incident) -> Result<(), IncidentError> {
+    if incident.message.trim().is_empty() {
+        return Err(IncidentError::EmptyMessage);
+    }
+
+    let budget = payload_budget();
+    let mut incident = Cow::Borrowed(incident);
+
+    if incident.message.len() > budget.max_message_bytes
+        || incident
+            .data
+            .as_ref()
+            .is_some_and(|data| encoded_size(data) > budget.max_data_bytes)
+    {
+        apply_budget(incident.to_mut(), &budget)?;
+    }
+
+    crate::component::guest::mamoru::report(&incident);
+
+    Ok(())
+}
+
+/// Prefix of the storage keys used by [`report_deduplicated`].
+const DEDUP_KEY_PREFIX: &str = "mamoru:dedup:";
+
+/// Name of the data entry carrying the number of suppressed duplicates.
+const SUPPRESSED_DUPLICATES_KEY: &str = "suppressed_duplicates";
+
+/// Reports an incident unless another one with the same `fingerprint` was reported
+/// during the last `cooldown`.
+///
+/// The fingerprint is saved in the agent's own `KvStorage` with `cooldown` as TTL. Repeats
+/// inside the window are counted instead of reported, and the next incident that goes out
+/// carries a "suppressed N duplicates" note in its message and a `suppressed_duplicates`
+/// entry in its data. The counter never expires, so the note is not lost however late that
+/// incident comes, and it is deleted once attached.
+///
+/// Parameters:
+/// - `incident`: The incident to report.
+/// - `fingerprint`: Identifies incidents that are duplicates of each other.
+/// - `cooldown`: How long repeats of a reported incident are suppressed, rounded up to whole
+///   seconds. A zero cooldown suppresses nothing: the incident is reported without touching the
+///   storage.
+///
+/// Returns:
+/// `true` if the incident was reported, `false` if it was suppressed, or an `IncidentError` if it
+/// does not fit in the payload budget.
+pub fn report_deduplicated(
+    incident: &Incident,
+    fingerprint: &str,
+    cooldown: Duration,
+) -> Result<bool, IncidentError> {
+    if cooldown.is_zero() {
+        report(incident)?;
+        return Ok(true);
+    }
+
+    let storage = crate::mamoru_storage::open(Some(cooldown));
+    let window_key = format!("{DEDUP_KEY_PREFIX}{fingerprint}");
+    let counter_key = format!("{window_key}:suppressed");
+    let suppressed = storage
+        .get(&counter_key)
+        .and_then(|counter| match counter.value {
+            ValueType::U64(count) => Some(count),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    match deduplicate(incident, storage.contains(&window_key), suppressed) {
+        Dedup::Suppress(count) => {
+            storage.set_persistent(&counter_key, u64_value(count))?;
+            Ok(false)
+        }
+        Dedup::Report(incident) => {
+            report(&incident)?;
+
+            if suppressed > 0 {
+                storage.delete(&counter_key)?;
+            }
+            storage.set(
+                &window_key,
+                ValueData {
+                    data: None,
+                    value: ValueType::Bool(true),
+                },
+            )?;
+
+            Ok(true)
+        }
+    }
+}
+
+/// What [`report_deduplicated`] does with an incident.
+enum Dedup {
+    /// The incident is a duplicate, the stored counter becomes the given count.
+    Suppress(u64),
+    /// The incident goes out, carrying the duplicates suppressed before it.
+    Report(Incident),
+}
+
+/// Decides between suppressing and reporting an incident, given whether its fingerprint is in
+/// a cooldown window and the number of duplicates suppressed so far.
+fn deduplicate(incident: &Incident, in_window: bool, suppressed: u64) -> Dedup {
+    if in_window {
+        return Dedup::Suppress(suppressed.saturating_add(1));
+    }
+
+    let mut incident = incident.clone();
+    if suppressed > 0 {
+        attach_suppressed_duplicates(&mut incident, suppressed);
+    }
+
+    Dedup::Report(incident)
+}
+
+fn u64_value(value: u64) -> ValueData {
+    ValueData {
+        data: None,
+        value: ValueType::U64(value),
+    }
+}
+
+/// Adds the suppressed duplicates counter to the message and, when the data is a `MAP`
+/// or missing, as a `suppressed_duplicates` entry.
+fn attach_suppressed_duplicates(incident: &mut Incident, suppressed: u64) {
+    incident.message = format!("{} (suppressed {suppressed} duplicates)", incident.message);
+
+    let data = incident.data.get_or_insert_with(|| ValueData {
+        data: Some(Vec::new()),
+        value: ValueType::Map(Vec::new()),
+    });
+
+    if let ValueType::Map(elems) = &mut data.value {
+        let values = data.data.get_or_insert_with(Vec::new);
+        if let Ok(index) = u8::try_from(values.len()) {
+            values.push(ValueType::U64(suppressed));
+            elems.push((SUPPRESSED_DUPLICATES_KEY.to_string(), index));
+        }
+    }
+}
+
+/// Error returned by an agent entrypoint, reported as an `Error` incident by [`agent`].
+///
+/// Any error type converts into it with `?`, and [`AgentError::msg`] creates one from a message.
+pub struct AgentError(anyhow::Error);
+
+impl AgentError {
+    /// Creates an error from a message.
+    pub fn msg(message: impl Display + std::fmt::Debug + Send + Sync + 'static) -> Self {
+        AgentError(anyhow::Error::msg(message))
+    }
+}
+
+impl<E: std::error::Error + Send + Sync + 'static> From<E> for AgentError {
+    fn from(error: E) -> Self {
+        AgentError(error.into())
+    }
+}
+
+impl Display for AgentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
+
+impl std::fmt::Debug for AgentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+/// A chain context an [`agent`] entrypoint can take as argument.
+///
+/// Only Sui has a context type, [`crate::sui_ctx::SuiCtx`]. Agents of the other chains take `()`
+/// and read the chain through the `evm_ctx`, `aptos_ctx` and `cosmos_ctx` imports of
+/// [`crate::component::guest`], or through [`query`]. Any other argument type fails to build.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a chain context an agent can take",
+    label = "unsupported agent context",
+    note = "agents take `()` or `SuiCtx`, other chains are read through their context imports or `mamoru::query`"
+)]
+pub trait AgentContext: Sized {
+    /// Loads the context from the host.
+    fn load() -> Self;
+
+    /// The transaction the agent runs on, used for the incident of a returned `AgentError`.
+    fn tx_hash(&self) -> TransactionId;
+}
+
+/// For agents that read the chain through the import functions or `query`.
+impl AgentContext for () {
+    fn load() -> Self {}
+
+    fn tx_hash(&self) -> TransactionId {
+        TransactionId::new()
+    }
+}
+
+impl AgentContext for crate::sui_ctx::SuiCtx {
+    fn load() -> Self {
+        crate::sui_ctx::load()
+    }
+
+    fn tx_hash(&self) -> TransactionId {
+        self.transaction_digest().unwrap_or_default().to_string()
+    }
+}
+
+/// Runs an agent entrypoint, used by the code generated by [`agent`].
+///
+/// Installs the panic hook and the logger, loads the context and reports a returned error as an
+/// `Error` incident.
+#[doc(hidden)]
+pub fn run_agent<C: AgentContext>(run: impl FnOnce(C) -> Result<(), AgentError>) {
+    crate::mamoru_env::install_panic_hook();
+    // The agent may have installed its own logger.
+    let _ = crate::mamoru_log::init();
+
+    let ctx = C::load();
+    let tx_hash = ctx.tx_hash();
+
+    if let Err(error) = run(ctx) {
+        let incident = Incident::new_error(tx_hash, &error.to_string(), None, None);
+        if let Err(report_error) = report(&incident) {
+            log::error!("failed to report the agent error `{error}`: {report_error}");
+        }
+    }
 }
 
 /// Enumerates types of logs that can be generated.
@@ -53,12 +467,25 @@ pub enum LogType {
     ERROR,
 }
 
-/// Logs an incident with optional data and address.
+impl From<LogType> for log::Level {
+    fn from(typ_: LogType) -> Self {
+        match typ_ {
+            LogType::DEBUG => log::Level::Debug,
+            LogType::INFO => log::Level::Info,
+            LogType::WARN => log::Level::Warn,
+            LogType::ERROR => log::Level::Error,
+        }
+    }
+}
+
+/// Sends a log line to the host `debug` import, it is not reported as an incident.
+///
+/// Prefer the `log` macros with [`crate::mamoru_log::init`], which go through the same import.
 ///
 /// Parameters:
 /// - `tx_id`: Transaction identifier.
 /// - `message`: Log message.
-/// - `typ_`: Type of the log.
+/// - `typ_`: Type of the log, `INFO` if not set.
 /// - `data`: Optional data related to the log.
 /// - `address`: Optional address involved in the log.
 pub fn log(
@@ -69,44 +496,458 @@ pub fn log(
     address: Option<String>,
 ) {
     let level = typ_.unwrap_or(LogType::INFO);
-    let incident = Incident {
-        severity: level as u64,
-        message: message.to_string(),
-        tx_hash: tx_id,
-        address: address.unwrap_or_default(),
-        data,
-    };
+    let mut line = format!("[tx {tx_id}] {message}");
+
+    if let Some(address) = address {
+        line.push_str(&format!(" address={address}"));
+    }
+    if let Some(data) = data {
+        line.push_str(&format!(" data={data:?}"));
+    }
+
+    crate::mamoru_log::send(level.into(), &line);
+}
+
+/// Errors returned when reading an agent parameter.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParameterError {
+    /// The parameter is not set for this agent.
+    #[error("parameter `{key}` is missing")]
+    Missing { key: String },
+    /// The parameter value can not be parsed as the requested type.
+    #[error("parameter `{key}` has a malformed value `{value}`: {reason}")]
+    Malformed {
+        key: String,
+        value: String,
+        reason: String,
+    },
+    /// The parameter value is valid but outside of the accepted range.
+    #[error("parameter `{key}` is out of range with value `{value}`: {reason}")]
+    OutOfRange {
+        key: String,
+        value: String,
+        reason: String,
+    },
+    /// A default or bound declared by the agent for the parameter can not be parsed.
+    #[error("parameter `{key}` declares an invalid value `{value}`: {reason}")]
+    InvalidDeclaration {
+        key: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl ParameterError {
+    /// Returns the key of the parameter that failed.
+    pub fn key(&self) -> &str {
+        match self {
+            ParameterError::Missing { key }
+            | ParameterError::Malformed { key, .. }
+            | ParameterError::OutOfRange { key, .. }
+            | ParameterError::InvalidDeclaration { key, .. } => key,
+        }
+    }
+
+    /// Turns the error of parsing a value declared by the agent into an `InvalidDeclaration`.
+    ///
+    /// Used by the code generated by [`AgentParameters`] derives.
+    #[doc(hidden)]
+    pub fn into_invalid_declaration(self) -> Self {
+        match self {
+            ParameterError::Malformed { key, value, reason }
+            | ParameterError::OutOfRange { key, value, reason } => {
+                ParameterError::InvalidDeclaration { key, value, reason }
+            }
+            other => other,
+        }
+    }
 
-    report(&incident);
+    fn malformed(key: &str, value: &str, reason: impl Display) -> Self {
+        ParameterError::Malformed {
+            key: key.to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    fn out_of_range(key: &str, value: &str, reason: impl Display) -> Self {
+        ParameterError::OutOfRange {
+            key: key.to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
 }
 
-//TODO pending to modify error
-/// Generic function to try converting a static string input into a type `T`.
+/// Fetches the raw value of a parameter, treating an empty value as missing.
+fn raw_parameter(key: &str) -> Result<String, ParameterError> {
+    let value = crate::component::guest::mamoru::parameter(key);
+    let value = value.trim();
+
+    if value.is_empty() {
+        return Err(ParameterError::Missing {
+            key: key.to_string(),
+        });
+    }
+
+    Ok(value.to_string())
+}
+
+/// Fetches a parameter and parses it with `FromStr`.
+///
+/// Parameters:
+/// - `key`: The parameter identifier.
+///
+/// Returns:
+/// The parsed value, or a `ParameterError` if the parameter is missing or malformed.
+pub fn parameter<T>(key: &str) -> Result<T, ParameterError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = raw_parameter(key)?;
+    value
+        .parse()
+        .map_err(|e| ParameterError::malformed(key, &value, e))
+}
+
+/// Fetches a parameter and parses it with `FromStr`, falling back to `default` if it is missing.
+///
+/// Parameters:
+/// - `key`: The parameter identifier.
+/// - `default`: The value used when the parameter is not set.
+///
+/// Returns:
+/// The parsed value, or a `ParameterError` if the parameter is malformed.
+pub fn parameter_or<T>(key: &str, default: T) -> Result<T, ParameterError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match parameter(key) {
+        Err(ParameterError::Missing { .. }) => Ok(default),
+        other => other,
+    }
+}
+
+/// Fetches a parameter and checks that it is inside `range`.
+///
+/// Parameters:
+/// - `key`: The parameter identifier.
+/// - `range`: The accepted values, bounds included.
+///
+/// Returns:
+/// The parsed value, or a `ParameterError` if the parameter is missing, malformed or out of range.
+pub fn parameter_in_range<T>(key: &str, range: RangeInclusive<T>) -> Result<T, ParameterError>
+where
+    T: FromStr + PartialOrd + Display,
+    T::Err: Display,
+{
+    let value: T = parameter(key)?;
+
+    if !range.contains(&value) {
+        return Err(ParameterError::out_of_range(
+            key,
+            &value.to_string(),
+            format!(
+                "expected a value between {} and {}",
+                range.start(),
+                range.end()
+            ),
+        ));
+    }
+
+    Ok(value)
+}
+
+/// Fetches a comma-separated list parameter, e.g. `0x1, 0x2,0x3`.
+///
+/// Entries are trimmed and empty entries are ignored.
+///
+/// Parameters:
+/// - `key`: The parameter identifier.
+///
+/// Returns:
+/// The parsed entries, or a `ParameterError` naming the first malformed entry.
+pub fn parameter_list<T>(key: &str) -> Result<Vec<T>, ParameterError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = raw_parameter(key)?;
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse()
+                .map_err(|e| ParameterError::malformed(key, entry, e))
+        })
+        .collect()
+}
+
+/// Fetches a parameter holding a JSON document and deserializes it.
+///
+/// Parameters:
+/// - `key`: The parameter identifier.
+///
+/// Returns:
+/// The deserialized value, or a `ParameterError` if the parameter is missing or not valid JSON for `T`.
+pub fn parameter_json<T: DeserializeOwned>(key: &str) -> Result<T, ParameterError> {
+    let value = raw_parameter(key)?;
+    serde_json::from_str(&value).map_err(|e| ParameterError::malformed(key, &value, e))
+}
+
+/// Fetches a duration parameter such as `500ms`, `30s`, `10m`, `1h30m` or `7d`.
+///
+/// A number without unit is read as seconds.
+///
+/// Parameters:
+/// - `key`: The parameter identifier.
+///
+/// Returns:
+/// The parsed `Duration`, or a `ParameterError` if the parameter is missing, malformed or overflows.
+pub fn parameter_duration(key: &str) -> Result<Duration, ParameterError> {
+    let value = raw_parameter(key)?;
+    parse_duration(key, &value)
+}
+
+fn parse_duration(key: &str, value: &str) -> Result<Duration, ParameterError> {
+    mamoru_rust_sdk_parse::parse_duration(value).map_err(|e| match e {
+        DurationError::Overflow => ParameterError::out_of_range(key, value, e),
+        DurationError::MissingAmount | DurationError::UnknownUnit(_) => {
+            ParameterError::malformed(key, value, e)
+        }
+    })
+}
+
+/// Fetches an unsigned integer parameter written in decimal or `0x` prefixed hexadecimal.
 ///
-/// The function fetches the parameter and attempts to convert it from a `String`.
-/// If the conversion fails, it returns an error with a descriptive message.
+/// Parameters:
+/// - `key`: The parameter identifier.
+///
+/// Returns:
+/// The parsed value, or a `ParameterError` if the parameter is missing, malformed or does not fit in `u128`.
+pub fn parameter_u128(key: &str) -> Result<u128, ParameterError> {
+    let value = raw_parameter(key)?;
+    u128::from_parameter(key, Some(&value))
+}
+
+/// Fetches a 256-bit unsigned integer parameter written in decimal or `0x` prefixed hexadecimal.
 ///
 /// Parameters:
-/// - `input`: The static string identifier for the parameter.
+/// - `key`: The parameter identifier.
 ///
 /// Returns:
-/// A result containing either the converted value or an error string.
-pub fn parameter<T: TryFrom<String, Error = String>>(input: &'static str) -> Result<T, String> {
-    let inner_data = crate::component::guest::mamoru::parameter(input);
-    T::try_from(inner_data).map_err(|e| format!("Error trying to parse from string {e}"))
+/// The parsed value, or a `ParameterError` if the parameter is missing, malformed or does not fit in 256 bits.
+pub fn parameter_u256(key: &str) -> Result<U256, ParameterError> {
+    let value = raw_parameter(key)?;
+    U256::from_parameter(key, Some(&value))
+}
+
+/// Types that can be read from an agent parameter.
+///
+/// This is what [`AgentParameters`] derives use to parse each field. Integers accept decimal and
+/// `0x` prefixed hexadecimal values, `Duration` uses the format of [`parameter_duration`], `Vec<T>`
+/// is a comma-separated list and `Option<T>` is `None` when the parameter is missing.
+pub trait FromParameter: Sized {
+    /// Parses the raw `value` of the parameter `key`, `None` if the parameter is missing.
+    fn from_parameter(key: &str, value: Option<&str>) -> Result<Self, ParameterError>;
+}
+
+fn required<'a>(key: &str, value: Option<&'a str>) -> Result<&'a str, ParameterError> {
+    value.ok_or_else(|| ParameterError::Missing {
+        key: key.to_string(),
+    })
+}
+
+macro_rules! impl_from_parameter_for_integer {
+    ($($ty:ty),*) => {$(
+        impl FromParameter for $ty {
+            fn from_parameter(key: &str, value: Option<&str>) -> Result<Self, ParameterError> {
+                let value = required(key, value)?;
+                let (digits, radix) = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+                    Some(hex) => (hex, 16),
+                    None => (value, 10),
+                };
+
+                <$ty>::from_str_radix(digits, radix).map_err(|e| match e.kind() {
+                    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                        ParameterError::out_of_range(key, value, e)
+                    }
+                    _ => ParameterError::malformed(key, value, e),
+                })
+            }
+        }
+    )*};
+}
+
+impl_from_parameter_for_integer!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+macro_rules! impl_from_parameter_for_from_str {
+    ($($ty:ty),*) => {$(
+        impl FromParameter for $ty {
+            fn from_parameter(key: &str, value: Option<&str>) -> Result<Self, ParameterError> {
+                let value = required(key, value)?;
+                value.parse().map_err(|e| ParameterError::malformed(key, value, e))
+            }
+        }
+    )*};
+}
+
+impl_from_parameter_for_from_str!(f32, f64, bool, char, String);
+
+impl FromParameter for Duration {
+    fn from_parameter(key: &str, value: Option<&str>) -> Result<Self, ParameterError> {
+        parse_duration(key, required(key, value)?)
+    }
+}
+
+impl FromParameter for U256 {
+    fn from_parameter(key: &str, value: Option<&str>) -> Result<Self, ParameterError> {
+        let value = required(key, value)?;
+        value.parse().map_err(|e| match e {
+            U256Error::Overflow => ParameterError::out_of_range(key, value, e),
+            _ => ParameterError::malformed(key, value, e),
+        })
+    }
+}
+
+impl<T: FromParameter> FromParameter for Vec<T> {
+    fn from_parameter(key: &str, value: Option<&str>) -> Result<Self, ParameterError> {
+        required(key, value)?
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| T::from_parameter(key, Some(entry)))
+            .collect()
+    }
+}
+
+impl<T: FromParameter> FromParameter for Option<T> {
+    fn from_parameter(key: &str, value: Option<&str>) -> Result<Self, ParameterError> {
+        value
+            .map(|value| T::from_parameter(key, Some(value)))
+            .transpose()
+    }
+}
+
+/// Checks that a parsed parameter is inside the optional `min`/`max` bounds.
+///
+/// Used by the code generated by [`AgentParameters`] derives.
+#[doc(hidden)]
+pub fn check_parameter_bounds<T: PartialOrd>(
+    key: &str,
+    value: &T,
+    raw: &str,
+    min: Option<&T>,
+    max: Option<&T>,
+    bounds: &str,
+) -> Result<(), ParameterError> {
+    let below = min.is_some_and(|min| value < min);
+    let above = max.is_some_and(|max| value > max);
+
+    if below || above {
+        return Err(ParameterError::out_of_range(
+            key,
+            raw,
+            format!("expected {bounds}"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Machine-readable description of a parameter expected by an agent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ParameterSpec {
+    /// The key of the parameter.
+    pub key: &'static str,
+    /// The Rust type the parameter is parsed into.
+    #[serde(rename = "type")]
+    pub type_name: &'static str,
+    /// Whether the agent can run without this parameter.
+    pub required: bool,
+    /// The value used when the parameter is missing.
+    pub default: Option<&'static str>,
+    /// The minimum accepted value, inclusive.
+    pub min: Option<&'static str>,
+    /// The maximum accepted value, inclusive.
+    pub max: Option<&'static str>,
+}
+
+/// A set of parameters loaded together, usually implemented with `#[derive(AgentParameters)]`.
+///
+/// ```ignore
+/// #[derive(AgentParameters)]
+/// struct Params {
+///     #[param(default = 10, min = 1, max = 100)]
+///     max_withdrawals: u64,
+///     #[param(key = "pool", default = "0x2")]
+///     pool_address: String,
+///     #[param(default = "10m")]
+///     window: Duration,
+///     watched_tokens: Vec<String>,
+///     threshold: Option<f64>,
+/// }
+/// ```
+pub trait AgentParameters: Sized {
+    /// Describes the expected parameters: keys, types, defaults and bounds.
+    fn schema() -> Vec<ParameterSpec>;
+
+    /// Loads the parameters from `lookup`, which returns the raw value of a key if it is set.
+    ///
+    /// Returns:
+    /// The parameters, or every `ParameterError` found instead of only the first one.
+    fn load_from(lookup: &dyn Fn(&str) -> Option<String>) -> Result<Self, Vec<ParameterError>>;
+
+    /// Loads the parameters through the host `parameter` import.
+    fn load() -> Result<Self, Vec<ParameterError>> {
+        Self::load_from(&|key| raw_parameter(key).ok())
+    }
+
+    /// Validates a daemon configuration against the parameters without running the agent.
+    ///
+    /// Parameters:
+    /// - `config`: The parameters of the daemon, by key.
+    ///
+    /// Returns:
+    /// Every `ParameterError` found in the configuration.
+    fn validate(config: &HashMap<String, String>) -> Result<(), Vec<ParameterError>> {
+        Self::load_from(&|key| {
+            config
+                .get(key)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        })
+        .map(|_| ())
+    }
+
+    /// Serializes the schema as JSON.
+    fn schema_json() -> String {
+        serde_json::to_string(&Self::schema()).expect("parameter schema is always serializable")
+    }
 }
 
 /// Apply a SQL query via string for extracting mamoru info
 ///
+/// The query runs inside the agent against the chain context of the current run, see
+/// [`crate::mamoru_query`] for the supported SQL subset and tables.
 ///
-///Parameters:
-///- `query`: SQL query
-//TODO Add not serialized types
-pub fn query(_query: String) {}
+/// Parameters:
+/// - `query`: SQL query
+///
+/// Returns:
+/// One `MAP` value-data per result row, keyed by column name, or a `QueryError`.
+pub fn query(query: &str) -> Result<Vec<ValueData>, QueryError> {
+    crate::mamoru_query::query(query)
+}
 
 /// Http method types
-#[allow(dead_code)]
-enum HttpMethod {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HttpMethod {
     Post,
     Get,
     Put,
@@ -114,47 +955,286 @@ enum HttpMethod {
     Delete,
 }
 
+impl From<HttpMethod> for WitHttpMethod {
+    fn from(method: HttpMethod) -> Self {
+        match method {
+            HttpMethod::Post => WitHttpMethod::Post,
+            HttpMethod::Get => WitHttpMethod::Get,
+            HttpMethod::Put => WitHttpMethod::Put,
+            HttpMethod::Patch => WitHttpMethod::Patch,
+            HttpMethod::Delete => WitHttpMethod::Delete,
+        }
+    }
+}
+
 /// Http requests
-#[allow(dead_code)]
+#[derive(Clone, Debug)]
 pub struct HttpRequest {
-    method: HttpMethod,
-    url: String,
-    headers: HashMap<String, String>,
-    body: Option<String>,
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+}
+
+impl HttpRequest {
+    /// Creates a new `HttpRequest` without headers and body.
+    ///
+    /// Parameters:
+    /// - `method`: The HTTP method to use.
+    /// - `url`: The target URL.
+    pub fn new(method: HttpMethod, url: impl Into<String>) -> Self {
+        HttpRequest {
+            method,
+            url: url.into(),
+            headers: HashMap::new(),
+            body: None,
+        }
+    }
+
+    /// Creates a new `GET` request for the given `url`.
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new(HttpMethod::Get, url)
+    }
+
+    /// Creates a new `POST` request for the given `url`.
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new(HttpMethod::Post, url)
+    }
+
+    /// Adds a header to the request, replacing any previous value with the same name.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets the body of the request.
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+}
+
+impl TryFrom<&HttpRequest> for WitHttpRequest {
+    type Error = HttpError;
+
+    fn try_from(request: &HttpRequest) -> Result<Self, Self::Error> {
+        Ok(WitHttpRequest {
+            method: request.method.into(),
+            url: request.url.clone(),
+            headers: headers_to_value_data(&request.headers)?,
+            body: request.body.clone(),
+        })
+    }
 }
 
 /// Http response
-#[allow(dead_code)]
+#[derive(Clone, Debug)]
 pub struct HttpResponse {
-    status: u16,
-    error: Option<String>,
-    headers: HashMap<String, String>,
-    body: Option<Vec<u8>>,
+    pub status: u16,
+    pub error: Option<String>,
+    pub headers: HashMap<String, String>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpResponse {
+    /// Returns `true` if the status code is in the `2xx` range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Returns the body decoded as UTF-8, replacing invalid sequences.
+    pub fn text(&self) -> String {
+        self.body
+            .as_deref()
+            .map(|body| String::from_utf8_lossy(body).into_owned())
+            .unwrap_or_default()
+    }
+}
+
+impl From<WitHttpResponse> for HttpResponse {
+    fn from(response: WitHttpResponse) -> Self {
+        HttpResponse {
+            status: response.status,
+            error: response.error,
+            headers: headers_from_value_data(&response.headers),
+            body: response.body,
+        }
+    }
+}
+
+/// Errors returned by [`http`].
+#[derive(Debug, thiserror::Error)]
+pub enum HttpError {
+    /// The host could not perform the request (DNS, connection, timeout...).
+    #[error("http request failed: {0}")]
+    Request(String),
+    /// The server answered with a status outside of the `2xx` range.
+    #[error("http request returned status {}", .0.status)]
+    Status(HttpResponse),
+    /// The request headers can not be encoded as `value-data`.
+    #[error("too many http headers: {0}, at most {max} are supported", max = u8::MAX as usize + 1)]
+    TooManyHeaders(usize),
+}
+
+/// Encodes headers as a `MAP` value-data, each value stored as a `STRING` entry of `data`.
+fn headers_to_value_data(headers: &HashMap<String, String>) -> Result<ValueData, HttpError> {
+    let mut data = Vec::with_capacity(headers.len());
+    let mut elems = Vec::with_capacity(headers.len());
+
+    for (index, (name, value)) in headers.iter().enumerate() {
+        let index = u8::try_from(index).map_err(|_| HttpError::TooManyHeaders(headers.len()))?;
+        data.push(ValueType::String(value.clone()));
+        elems.push((name.clone(), index));
+    }
+
+    Ok(ValueData {
+        data: Some(data),
+        value: ValueType::Map(elems),
+    })
+}
+
+/// Decodes a `MAP` value-data into headers, ignoring entries that are not strings.
+fn headers_from_value_data(headers: &ValueData) -> HashMap<String, String> {
+    let (ValueType::Map(elems), Some(data)) = (&headers.value, &headers.data) else {
+        return HashMap::new();
+    };
+
+    elems
+        .iter()
+        .filter_map(|(name, index)| match data.get(*index as usize) {
+            Some(ValueType::String(value)) => Some((name.clone(), value.clone())),
+            _ => None,
+        })
+        .collect()
 }
 
 /// Basic HTTP requests function
 ///
 /// Parameters:
-/// `http_request`: HTTP request
+/// - `http_request`: HTTP request
 ///
 /// Returns:
-/// A `HttpResponse` response.
-#[allow(dead_code)]
-pub fn http(_http_request: HttpRequest) -> HttpResponse {
-    HttpResponse {
-        status: 200,
-        error: None,
-        headers: HashMap::new(),
-        body: None,
+/// The `HttpResponse` if the server answered with a `2xx` status, otherwise an `HttpError`
+/// describing the host error or carrying the non-successful response.
+pub fn http(http_request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+    let request = WitHttpRequest::try_from(http_request)?;
+    let response: HttpResponse = crate::component::guest::mamoru::http(&request).into();
+
+    if let Some(error) = response.error {
+        return Err(HttpError::Request(error));
+    }
+
+    if !response.is_success() {
+        return Err(HttpError::Status(response));
     }
+
+    Ok(response)
 }
 
-/// Convert from string to u256
+/// Convert from string to u256 through the host
 ///
 /// Parameters:
-/// - `payload`: String to convert in u256 via vector
+/// - `payload`: Decimal or `0x` prefixed hexadecimal string to convert
+///
 /// Returns:
-/// A vector of bytes that represents a u256
-pub fn u256_from_str(_payload: String) -> Vec<u8> {
-    Vec::new()
+/// The parsed `U256`, or a `U256Error` if the host returned no bytes, which is how it reports a
+/// payload it can not parse, or more than 32 bytes.
+/// Use `payload.parse::<U256>()` to parse the value inside the agent instead.
+pub fn u256_from_str(payload: &str) -> Result<U256, U256Error> {
+    let bytes = crate::component::guest::mamoru::u256_from_str(payload);
+    if bytes.is_empty() {
+        return Err(U256Error::Empty);
+    }
+
+    U256::from_be_slice(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incident(data: Option<ValueData>) -> Incident {
+        Incident::new_warning("0x1".to_string(), "price moved", data, None)
+    }
+
+    #[test]
+    fn counts_duplicates_in_the_window() {
+        assert!(matches!(
+            deduplicate(&incident(None), true, 0),
+            Dedup::Suppress(1)
+        ));
+        assert!(matches!(
+            deduplicate(&incident(None), true, 4),
+            Dedup::Suppress(5)
+        ));
+        assert!(matches!(
+            deduplicate(&incident(None), true, u64::MAX),
+            Dedup::Suppress(u64::MAX)
+        ));
+    }
+
+    #[test]
+    fn attaches_suppressed_duplicates_to_the_next_incident() {
+        let Dedup::Report(reported) = deduplicate(&incident(None), false, 0) else {
+            panic!("expected the incident to be reported");
+        };
+        assert_eq!(reported.message, "price moved");
+        assert!(reported.data.is_none());
+
+        let Dedup::Report(reported) = deduplicate(&incident(None), false, 3) else {
+            panic!("expected the incident to be reported");
+        };
+        assert_eq!(reported.message, "price moved (suppressed 3 duplicates)");
+        let data = reported.data.unwrap();
+        assert_eq!(
+            data.view()
+                .field(SUPPRESSED_DUPLICATES_KEY)
+                .unwrap()
+                .as_u64()
+                .unwrap(),
+            3
+        );
+
+        let mut builder = crate::mamoru_serialize::DataBuilder::new();
+        builder.text("pool", "0xa");
+        let Dedup::Report(reported) =
+            deduplicate(&incident(Some(builder.build().unwrap())), false, 2)
+        else {
+            panic!("expected the incident to be reported");
+        };
+        let data = reported.data.unwrap();
+        let view = data.view();
+        assert_eq!(view.field("pool").unwrap().as_str().unwrap(), "0xa");
+        assert_eq!(
+            view.field(SUPPRESSED_DUPLICATES_KEY)
+                .unwrap()
+                .as_u64()
+                .unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn rejects_empty_messages() {
+        let tx = "0x1".to_string();
+
+        for message in ["", "  \n"] {
+            assert!(matches!(
+                report(&Incident::new_warning(tx.clone(), message, None, None)),
+                Err(IncidentError::EmptyMessage)
+            ));
+            assert!(matches!(
+                Incident::builder(tx.clone()).message(message).build(),
+                Err(IncidentError::EmptyMessage)
+            ));
+        }
+    }
+
+    #[test]
+    fn reads_the_tx_hash_from_the_loaded_context() {
+        let ctx = crate::sui_ctx::SuiCtx::new_empty_sui_ctx();
+
+        assert_eq!(ctx.tx_hash(), "");
+        assert_eq!(().tx_hash(), "");
+    }
 }
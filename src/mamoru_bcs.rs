@@ -0,0 +1,732 @@
+//! Decoding of BCS encoded Move values, such as Sui event contents, object contents and pure
+//! transaction arguments, driven by the layout of their Move type.
+//!
+//! BCS is not self-describing, so the layout has to be known up front:
+//!
+//! ```ignore
+//! let layout = MoveTypeLayout::parse("u64")?;
+//! let amount: u64 = mamoru_bcs::decode_as(&pure_arg, &layout)?;
+//!
+//! let swap = MoveStructLayout::new("0xabc::pool::SwapEvent")
+//!     .field("pool", MoveTypeLayout::Address)
+//!     .field("amount_in", MoveTypeLayout::U64)
+//!     .field("coin", MoveTypeLayout::parse("0x1::ascii::String")?);
+//! let contents = mamoru_bcs::decode(&event.contents, &swap.into())?;
+//! ```
+
+use crate::component::guest::types::{ValueData, ValueType};
+use crate::mamoru_serialize::{from_value_data, ValueDataError};
+use crate::u256::U256;
+use serde::de::DeserializeOwned;
+
+/// Length of Sui and Aptos account and object addresses.
+const ADDRESS_LENGTH: usize = 32;
+
+/// Errors returned when parsing a Move type or decoding a BCS value.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum BcsError {
+    #[error("unexpected end of input at byte {0}")]
+    UnexpectedEnd(usize),
+    #[error("{0} bytes left after the value")]
+    TrailingBytes(usize),
+    #[error("invalid bool {value} at byte {offset}")]
+    InvalidBool { offset: usize, value: u8 },
+    #[error("invalid option of length {len} at byte {offset}")]
+    InvalidOption { offset: usize, len: usize },
+    #[error("invalid length at byte {0}")]
+    InvalidLength(usize),
+    #[error("invalid UTF-8 string at byte {0}")]
+    InvalidUtf8(usize),
+    /// The type string can not be parsed.
+    #[error("invalid Move type `{type_name}`: {reason}")]
+    InvalidType { type_name: String, reason: String },
+    /// The layout of a struct type is not known, it has to be described with a [`MoveStructLayout`].
+    #[error("unknown layout for struct `{0}`")]
+    UnknownStruct(String),
+    #[error(transparent)]
+    ValueData(#[from] ValueDataError),
+}
+
+/// The layout of a Move type, describing how its BCS bytes are decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MoveTypeLayout {
+    /// Decoded as a `BOOL`.
+    Bool,
+    /// `u8` to `u64` are decoded as `U64`s.
+    U8,
+    U16,
+    U32,
+    U64,
+    /// `u128` and `u256` are decoded as `U256`s.
+    U128,
+    U256,
+    /// Addresses and signers are decoded as `0x` prefixed hex `STRING`s.
+    Address,
+    Signer,
+    /// Decoded as a `LIST`, or as `BYTES` for `vector<u8>`.
+    Vector(Box<MoveTypeLayout>),
+    /// `0x1::string::String` and `0x1::ascii::String`, decoded as a `STRING`.
+    String,
+    /// `0x1::option::Option<T>`. A struct field is left out of the `STRUCT` when `None` and holds
+    /// the value otherwise. Anywhere else, such as a pure argument or a vector element, it is
+    /// decoded as a `LIST` of zero or one element, the way Move stores it.
+    Option(Box<MoveTypeLayout>),
+    /// Decoded as a `STRUCT` named after the type.
+    Struct(MoveStructLayout),
+}
+
+/// The layout of a Move struct: its type name and its fields, in declaration order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveStructLayout {
+    pub type_name: String,
+    pub fields: Vec<(String, MoveTypeLayout)>,
+}
+
+impl MoveStructLayout {
+    /// Creates a layout without fields.
+    ///
+    /// Parameters:
+    /// - `type_name`: The full type name, e.g. `0x2::coin::Coin<0x2::sui::SUI>`.
+    pub fn new(type_name: impl Into<String>) -> Self {
+        MoveStructLayout {
+            type_name: type_name.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Appends a field, fields having to be added in declaration order.
+    pub fn field(mut self, name: impl Into<String>, layout: impl Into<MoveTypeLayout>) -> Self {
+        self.fields.push((name.into(), layout.into()));
+        self
+    }
+}
+
+impl From<MoveStructLayout> for MoveTypeLayout {
+    fn from(layout: MoveStructLayout) -> Self {
+        MoveTypeLayout::Struct(layout)
+    }
+}
+
+impl MoveTypeLayout {
+    /// Parses a Move type such as `vector<0x1::option::Option<u64>>`.
+    ///
+    /// Only the structs of the framework with a well-known layout are supported:
+    /// `0x1::string::String`, `0x1::ascii::String`, `0x1::option::Option<T>`,
+    /// `0x2::object::UID`/`ID` (decoded as addresses), `0x2::balance::Balance<T>` and
+    /// `0x2::coin::Coin<T>`. Use [`MoveTypeLayout::parse_with`] for others.
+    ///
+    /// Parameters:
+    /// - `type_name`: The Move type.
+    ///
+    /// Returns:
+    /// The layout, or a `BcsError` if the type is malformed or contains an unknown struct.
+    pub fn parse(type_name: &str) -> Result<Self, BcsError> {
+        Self::parse_with(type_name, |_| None)
+    }
+
+    /// Parses a Move type, asking `resolve` for the layout of structs that are not well-known.
+    ///
+    /// Parameters:
+    /// - `type_name`: The Move type.
+    /// - `resolve`: Returns the layout of a struct from its full type name, type arguments
+    ///   included, or `None` if it is unknown.
+    ///
+    /// Returns:
+    /// The layout, or a `BcsError` if the type is malformed or contains an unknown struct.
+    pub fn parse_with(
+        type_name: &str,
+        resolve: impl Fn(&str) -> Option<MoveStructLayout>,
+    ) -> Result<Self, BcsError> {
+        let mut parser = TypeParser {
+            input: type_name,
+            pos: 0,
+        };
+
+        let parsed = parser.parse_type()?;
+        parser.skip_whitespace();
+        if parser.pos != type_name.len() {
+            return Err(parser.error("unexpected characters after the type"));
+        }
+
+        parsed.layout(&resolve)
+    }
+}
+
+/// Converts the type tags of Sui calltraces and commands, parsing struct tags.
+impl TryFrom<&crate::sui_ctx::TypeTag> for MoveTypeLayout {
+    type Error = BcsError;
+
+    fn try_from(tag: &crate::sui_ctx::TypeTag) -> Result<Self, Self::Error> {
+        use crate::sui_ctx::TypeTag;
+
+        Ok(match tag {
+            TypeTag::Bool => MoveTypeLayout::Bool,
+            TypeTag::U8 => MoveTypeLayout::U8,
+            TypeTag::U16 => MoveTypeLayout::U16,
+            TypeTag::U32 => MoveTypeLayout::U32,
+            TypeTag::U64 => MoveTypeLayout::U64,
+            TypeTag::U128 => MoveTypeLayout::U128,
+            TypeTag::U256 => MoveTypeLayout::U256,
+            TypeTag::Address => MoveTypeLayout::Address,
+            TypeTag::Signer => MoveTypeLayout::Signer,
+            TypeTag::Vector(inner) => MoveTypeLayout::Vector(Box::new(inner.as_ref().try_into()?)),
+            TypeTag::Struct(tag) => MoveTypeLayout::parse(tag)?,
+        })
+    }
+}
+
+/// A parsed Move type, whose struct type arguments are only resolved when the layout needs them.
+enum TypeName {
+    Primitive(MoveTypeLayout),
+    Vector(Box<TypeName>),
+    Struct {
+        address: String,
+        module: String,
+        name: String,
+        /// The type arguments, with the text they are written as.
+        args: Vec<(TypeName, String)>,
+        /// The type as written, type arguments included.
+        full_name: String,
+    },
+}
+
+impl TypeName {
+    fn layout(
+        &self,
+        resolve: &impl Fn(&str) -> Option<MoveStructLayout>,
+    ) -> Result<MoveTypeLayout, BcsError> {
+        let (address, module, name, args, full_name) = match self {
+            TypeName::Primitive(layout) => return Ok(layout.clone()),
+            TypeName::Vector(inner) => {
+                return Ok(MoveTypeLayout::Vector(Box::new(inner.layout(resolve)?)))
+            }
+            TypeName::Struct {
+                address,
+                module,
+                name,
+                args,
+                full_name,
+            } => (address, module, name, args, full_name),
+        };
+
+        match (address.as_str(), module.as_str(), name.as_str(), &args[..]) {
+            ("0x1", "string" | "ascii", "String", []) => Ok(MoveTypeLayout::String),
+            ("0x1", "option", "Option", [(inner, _)]) => {
+                Ok(MoveTypeLayout::Option(Box::new(inner.layout(resolve)?)))
+            }
+            ("0x2", "object", "UID" | "ID", []) => Ok(MoveTypeLayout::Address),
+            ("0x2", "balance", "Balance", [_]) => Ok(MoveStructLayout::new(full_name.as_str())
+                .field("value", MoveTypeLayout::U64)
+                .into()),
+            ("0x2", "coin", "Coin", [(_, coin_type)]) => {
+                let balance = MoveStructLayout::new(format!("0x2::balance::Balance<{coin_type}>"))
+                    .field("value", MoveTypeLayout::U64);
+
+                Ok(MoveStructLayout::new(full_name.as_str())
+                    .field("id", MoveTypeLayout::Address)
+                    .field("balance", balance)
+                    .into())
+            }
+            _ => resolve(full_name)
+                .map(MoveTypeLayout::Struct)
+                .ok_or_else(|| BcsError::UnknownStruct(full_name.clone())),
+        }
+    }
+}
+
+struct TypeParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl TypeParser<'_> {
+    fn error(&self, reason: &str) -> BcsError {
+        BcsError::InvalidType {
+            type_name: self.input.to_string(),
+            reason: format!("{reason} at {}", self.pos),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), BcsError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{token}`")))
+        }
+    }
+
+    fn ident(&mut self) -> Result<&str, BcsError> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+
+        if len == 0 {
+            return Err(self.error("expected an identifier"));
+        }
+
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn parse_type(&mut self) -> Result<TypeName, BcsError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let ident = self.ident()?.to_string();
+
+        let primitive = match ident.as_str() {
+            "bool" => Some(MoveTypeLayout::Bool),
+            "u8" => Some(MoveTypeLayout::U8),
+            "u16" => Some(MoveTypeLayout::U16),
+            "u32" => Some(MoveTypeLayout::U32),
+            "u64" => Some(MoveTypeLayout::U64),
+            "u128" => Some(MoveTypeLayout::U128),
+            "u256" => Some(MoveTypeLayout::U256),
+            "address" => Some(MoveTypeLayout::Address),
+            "signer" => Some(MoveTypeLayout::Signer),
+            _ => None,
+        };
+        if let Some(layout) = primitive {
+            return Ok(TypeName::Primitive(layout));
+        }
+
+        if ident == "vector" {
+            self.expect("<")?;
+            let inner = self.parse_type()?;
+            self.expect(">")?;
+            return Ok(TypeName::Vector(Box::new(inner)));
+        }
+
+        self.expect("::")?;
+        let module = self.ident()?.to_string();
+        self.expect("::")?;
+        let name = self.ident()?.to_string();
+
+        let mut args = Vec::new();
+        if self.eat("<") {
+            loop {
+                self.skip_whitespace();
+                let arg_start = self.pos;
+                let arg = self.parse_type()?;
+                args.push((arg, self.input[arg_start..self.pos].to_string()));
+                if self.eat(">") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+
+        Ok(TypeName::Struct {
+            address: normalize_address(&ident),
+            module,
+            name,
+            args,
+            full_name: self.input[start..self.pos].to_string(),
+        })
+    }
+}
+
+/// Normalizes `0x0000…02` and `2` to `0x2`.
+fn normalize_address(address: &str) -> String {
+    let digits = address.strip_prefix("0x").unwrap_or(address);
+    let digits = digits.trim_start_matches('0');
+
+    format!("0x{}", if digits.is_empty() { "0" } else { digits })
+}
+
+/// Decodes a BCS value into value-data.
+///
+/// Parameters:
+/// - `bytes`: The BCS bytes, which must hold exactly one value.
+/// - `layout`: The layout of the Move type of the value.
+///
+/// Returns:
+/// The decoded value-data, or a `BcsError` if the bytes do not match the layout.
+pub fn decode(bytes: &[u8], layout: &MoveTypeLayout) -> Result<ValueData, BcsError> {
+    let mut decoder = Decoder {
+        bytes,
+        pos: 0,
+        data: Vec::new(),
+    };
+
+    let value = decoder.decode(layout)?;
+
+    if decoder.pos != bytes.len() {
+        return Err(BcsError::TrailingBytes(bytes.len() - decoder.pos));
+    }
+
+    Ok(ValueData {
+        data: Some(decoder.data),
+        value,
+    })
+}
+
+/// Decodes a BCS value into a Rust type, through [`decode`] and
+/// [`crate::mamoru_serialize::from_value_data`].
+///
+/// Parameters:
+/// - `bytes`: The BCS bytes, which must hold exactly one value.
+/// - `layout`: The layout of the Move type of the value.
+///
+/// Returns:
+/// The decoded value, or a `BcsError` if the bytes do not match the layout or the Rust type.
+pub fn decode_as<T: DeserializeOwned>(
+    bytes: &[u8],
+    layout: &MoveTypeLayout,
+) -> Result<T, BcsError> {
+    let value = decode(bytes, layout)?;
+
+    Ok(from_value_data(&value)?)
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    data: Vec<ValueType>,
+}
+
+impl Decoder<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], BcsError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(BcsError::UnexpectedEnd(self.pos))?;
+
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BcsError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    /// Reads a ULEB128 length, which can not exceed the remaining bytes for any Move value.
+    fn length(&mut self) -> Result<usize, BcsError> {
+        let start = self.pos;
+        let mut value: u64 = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return usize::try_from(value)
+                    .ok()
+                    .filter(|len| *len <= self.bytes.len() - self.pos)
+                    .ok_or(BcsError::InvalidLength(start));
+            }
+        }
+
+        Err(BcsError::InvalidLength(start))
+    }
+
+    /// Decodes an option, a vector of zero or one element.
+    fn option(&mut self, inner: &MoveTypeLayout) -> Result<Option<ValueType>, BcsError> {
+        let start = self.pos;
+
+        match self.length()? {
+            0 => Ok(None),
+            1 => self.decode(inner).map(Some),
+            len => Err(BcsError::InvalidOption { offset: start, len }),
+        }
+    }
+
+    fn decode(&mut self, layout: &MoveTypeLayout) -> Result<ValueType, BcsError> {
+        let start = self.pos;
+
+        Ok(match layout {
+            MoveTypeLayout::Bool => match self.take(1)?[0] {
+                0 => ValueType::Bool(false),
+                1 => ValueType::Bool(true),
+                value => {
+                    return Err(BcsError::InvalidBool {
+                        offset: start,
+                        value,
+                    })
+                }
+            },
+            MoveTypeLayout::U8 => ValueType::U64(self.take(1)?[0].into()),
+            MoveTypeLayout::U16 => ValueType::U64(u16::from_le_bytes(self.array()?).into()),
+            MoveTypeLayout::U32 => ValueType::U64(u32::from_le_bytes(self.array()?).into()),
+            MoveTypeLayout::U64 => ValueType::U64(u64::from_le_bytes(self.array()?)),
+            MoveTypeLayout::U128 => U256::from(u128::from_le_bytes(self.array()?)).into(),
+            MoveTypeLayout::U256 => {
+                let mut bytes: [u8; 32] = self.array()?;
+                bytes.reverse();
+                ValueType::U256(bytes.to_vec())
+            }
+            MoveTypeLayout::Address | MoveTypeLayout::Signer => {
+                ValueType::String(crate::mamoru_serialize::to_hex(self.take(ADDRESS_LENGTH)?))
+            }
+            MoveTypeLayout::String => {
+                let len = self.length()?;
+                let bytes = self.take(len)?;
+                let value = std::str::from_utf8(bytes).map_err(|_| BcsError::InvalidUtf8(start))?;
+                ValueType::String(value.to_string())
+            }
+            MoveTypeLayout::Vector(inner) if **inner == MoveTypeLayout::U8 => {
+                let len = self.length()?;
+                ValueType::Bytes(self.take(len)?.to_vec())
+            }
+            MoveTypeLayout::Vector(inner) => {
+                let len = self.length()?;
+                let base = self.data.len();
+                self.data.resize(base + len, ValueType::Bool(false));
+
+                for offset in 0..len {
+                    self.data[base + offset] = self.decode(inner)?;
+                }
+
+                ValueType::List((base as u64..(base + len) as u64).collect())
+            }
+            MoveTypeLayout::Option(inner) => match self.option(inner)? {
+                Some(value) => {
+                    let slot = self.data.len();
+                    self.data.push(value);
+                    ValueType::List(vec![slot as u64])
+                }
+                None => ValueType::List(Vec::new()),
+            },
+            MoveTypeLayout::Struct(layout) => {
+                let base = self.data.len();
+                self.data
+                    .resize(base + layout.fields.len(), ValueType::Bool(false));
+
+                let mut fields = Vec::with_capacity(layout.fields.len());
+                for (offset, (name, field)) in layout.fields.iter().enumerate() {
+                    let value = match field {
+                        MoveTypeLayout::Option(inner) => self.option(inner)?,
+                        field => Some(self.decode(field)?),
+                    };
+                    if let Some(value) = value {
+                        self.data[base + offset] = value;
+                        fields.push((name.clone(), (base + offset) as u64));
+                    }
+                }
+
+                ValueType::Struct((layout.type_name.clone(), fields))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mamoru_serialize::to_json;
+    use serde_json::json;
+
+    fn decode_json(bytes: &[u8], type_name: &str) -> serde_json::Value {
+        let layout = MoveTypeLayout::parse(type_name).unwrap();
+        to_json(&decode(bytes, &layout).unwrap()).unwrap()
+    }
+
+    fn decode_err(bytes: &[u8], type_name: &str) -> BcsError {
+        decode(bytes, &MoveTypeLayout::parse(type_name).unwrap()).unwrap_err()
+    }
+
+    #[test]
+    fn decodes_primitives() {
+        assert_eq!(decode_json(&[1], "bool"), json!(true));
+        assert_eq!(decode_json(&[0xff], "u8"), json!(255));
+        assert_eq!(decode_json(&[0x34, 0x12], "u16"), json!(0x1234));
+        assert_eq!(decode_json(&[1, 0, 0, 0], "u32"), json!(1));
+        assert_eq!(decode_json(&u64::MAX.to_le_bytes(), "u64"), json!(u64::MAX));
+        assert_eq!(
+            decode_json(&(u128::from(u64::MAX) + 1).to_le_bytes(), "u128"),
+            json!({"$u256": "0x10000000000000000"})
+        );
+
+        let mut u256 = [0; 32];
+        u256[0] = 0x2a;
+        assert_eq!(decode_json(&u256, "u256"), json!({"$u256": "0x2a"}));
+
+        let address = [[0; 31].as_slice(), &[2]].concat();
+        assert_eq!(
+            decode_json(&address, "address"),
+            json!(format!("0x{}02", "0".repeat(62)))
+        );
+    }
+
+    #[test]
+    fn decodes_strings_and_vectors() {
+        assert_eq!(decode_json(b"\x03SUI", "0x1::string::String"), json!("SUI"));
+        assert_eq!(decode_json(b"\x00", "0x1::ascii::String"), json!(""));
+        assert_eq!(
+            decode_json(&[2, 1, 2], "vector<u8>"),
+            json!({"$bytes": "0x0102"})
+        );
+        assert_eq!(decode_json(&[2, 1, 0, 2, 0], "vector<u16>"), json!([1, 2]));
+        assert_eq!(
+            decode_json(&[2, 1, 7, 0], "vector<vector<u8>>"),
+            json!([{"$bytes": "0x07"}, {"$bytes": "0x"}])
+        );
+        assert_eq!(decode_json(&[0], "vector<bool>"), json!([]));
+    }
+
+    #[test]
+    fn decodes_options_outside_of_structs_as_lists() {
+        assert_eq!(decode_json(&[0], "0x1::option::Option<u8>"), json!([]));
+        assert_eq!(decode_json(&[1, 5], "0x1::option::Option<u8>"), json!([5]));
+        assert_eq!(
+            decode_json(&[3, 1, 5, 0, 1, 6], "vector<0x1::option::Option<u8>>"),
+            json!([[5], [], [6]])
+        );
+        assert_eq!(
+            decode_json(&[1, 0], "0x1::option::Option<vector<u64>>"),
+            json!([[]])
+        );
+
+        let layout = MoveTypeLayout::parse("0x1::option::Option<u64>").unwrap();
+        let some: Vec<u64> = decode_as(&[1, 9, 0, 0, 0, 0, 0, 0, 0], &layout).unwrap();
+        let none: Vec<u64> = decode_as(&[0], &layout).unwrap();
+        assert_eq!(some, [9]);
+        assert!(none.is_empty());
+
+        assert_eq!(
+            decode_err(&[2, 1, 2], "0x1::option::Option<u8>"),
+            BcsError::InvalidOption { offset: 0, len: 2 }
+        );
+    }
+
+    #[test]
+    fn decodes_structs() {
+        let event = MoveStructLayout::new("0xabc::pool::SwapEvent")
+            .field("amount", MoveTypeLayout::U8)
+            .field(
+                "referrer",
+                MoveTypeLayout::parse("0x1::option::Option<u8>").unwrap(),
+            )
+            .field(
+                "memo",
+                MoveTypeLayout::parse("0x1::option::Option<0x1::string::String>").unwrap(),
+            );
+        let layout = MoveTypeLayout::from(event);
+
+        let decoded = to_json(&decode(&[10, 0, 1, 2, b'h', b'i'], &layout).unwrap()).unwrap();
+        assert_eq!(
+            decoded,
+            json!({"$struct": "0xabc::pool::SwapEvent", "$fields": {"amount": 10, "memo": "hi"}})
+        );
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct SwapEvent {
+            amount: u8,
+            referrer: Option<u8>,
+            memo: Option<String>,
+        }
+
+        assert_eq!(
+            decode_as::<SwapEvent>(&[10, 1, 3, 0], &layout).unwrap(),
+            SwapEvent {
+                amount: 10,
+                referrer: Some(3),
+                memo: None,
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_framework_structs() {
+        let id = [7; 32];
+        let bytes = [id.as_slice(), &100u64.to_le_bytes()].concat();
+
+        assert_eq!(
+            decode_json(&bytes, "0x2::coin::Coin<0x2::sui::SUI>"),
+            json!({
+                "$struct": "0x2::coin::Coin<0x2::sui::SUI>",
+                "$fields": {
+                    "id": format!("0x{}", "07".repeat(32)),
+                    "balance": {
+                        "$struct": "0x2::balance::Balance<0x2::sui::SUI>",
+                        "$fields": {"value": 100},
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn parses_types() {
+        assert_eq!(
+            MoveTypeLayout::parse(" vector < 0x0001::option::Option<u64> > ").unwrap(),
+            MoveTypeLayout::Vector(Box::new(MoveTypeLayout::Option(Box::new(
+                MoveTypeLayout::U64
+            ))))
+        );
+        assert_eq!(
+            MoveTypeLayout::parse("0x2::object::UID").unwrap(),
+            MoveTypeLayout::Address
+        );
+        assert_eq!(
+            MoveTypeLayout::parse("0xabc::pool::Pool"),
+            Err(BcsError::UnknownStruct("0xabc::pool::Pool".to_string()))
+        );
+        assert_eq!(
+            MoveTypeLayout::parse_with("0xabc::pool::Pool", |name| {
+                Some(MoveStructLayout::new(name).field("fee", MoveTypeLayout::U64))
+            })
+            .unwrap(),
+            MoveStructLayout::new("0xabc::pool::Pool")
+                .field("fee", MoveTypeLayout::U64)
+                .into()
+        );
+        assert!(matches!(
+            MoveTypeLayout::parse("vector<u64"),
+            Err(BcsError::InvalidType { .. })
+        ));
+        assert!(matches!(
+            MoveTypeLayout::parse("u64 u8"),
+            Err(BcsError::InvalidType { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        assert_eq!(decode_err(&[], "u8"), BcsError::UnexpectedEnd(0));
+        assert_eq!(decode_err(&[1, 0], "u64"), BcsError::UnexpectedEnd(0));
+        assert_eq!(
+            decode_err(&[1, 1, 0], "vector<u64>"),
+            BcsError::UnexpectedEnd(1)
+        );
+        assert_eq!(decode_err(&[1, 2], "u8"), BcsError::TrailingBytes(1));
+        assert_eq!(
+            decode_err(&[2], "bool"),
+            BcsError::InvalidBool {
+                offset: 0,
+                value: 2
+            }
+        );
+        assert_eq!(
+            decode_err(&[1, 0xff], "0x1::string::String"),
+            BcsError::InvalidUtf8(0)
+        );
+        assert_eq!(
+            decode_err(&[5, 1], "vector<u8>"),
+            BcsError::InvalidLength(0)
+        );
+        assert_eq!(
+            decode_err(&[0x80; 11], "vector<u8>"),
+            BcsError::InvalidLength(0)
+        );
+    }
+}
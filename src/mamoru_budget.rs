@@ -0,0 +1,572 @@
+//! Size budget of the incidents sent by [`crate::mamoru::report`].
+//!
+//! A detector attaching a whole call tree to an incident can produce megabytes per report. Every
+//! reported incident is checked against the current [`PayloadBudget`], and its message and data
+//! are shortened according to the [`TruncationPolicy`] when they do not fit:
+//!
+//! ```ignore
+//! mamoru_budget::set_payload_budget(PayloadBudget {
+//!     max_data_bytes: 16 * 1024,
+//!     policy: TruncationPolicy::DropLargestFields,
+//!     ..PayloadBudget::DEFAULT
+//! });
+//! ```
+
+use crate::component::guest::types::{Incident, ValueData, ValueType};
+use crate::mamoru_serialize::{
+    write_map, IndexOverflow, IntoValueData, StructWriter, ValueDataView, ViewError,
+};
+use std::sync::Mutex;
+
+/// Nested values deeper than this are not truncated, as indexes can form cycles.
+const MAX_DEPTH: usize = 128;
+
+/// How an incident over budget is shortened.
+///
+/// When the policy is not enough, as for data holding a few large values, the largest `STRING`
+/// and `BYTES` values are cut as well. Strings end with a `(truncated N bytes)` note.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TruncationPolicy {
+    /// Removes the last elements of the largest `LIST`s, replacing them with a
+    /// `"truncated: N"` string element. Lists keep at least their first element.
+    TruncateLists,
+    /// Removes the largest `MAP` entries and `STRUCT` fields of the data root.
+    DropLargestFields,
+    /// Refuses to report the incident.
+    Fail,
+}
+
+/// The maximum size of the message and data of an incident.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PayloadBudget {
+    /// Maximum size of the data, as measured by [`encoded_size`].
+    pub max_data_bytes: usize,
+    /// Maximum size of the message in bytes. Unless the policy is `Fail`, a longer message is cut
+    /// and ends with a `(truncated N bytes)` note.
+    pub max_message_bytes: usize,
+    pub policy: TruncationPolicy,
+}
+
+impl PayloadBudget {
+    /// 64 KiB of data and 4 KiB of message, with lists truncated.
+    pub const DEFAULT: PayloadBudget = PayloadBudget {
+        max_data_bytes: 64 * 1024,
+        max_message_bytes: 4 * 1024,
+        policy: TruncationPolicy::TruncateLists,
+    };
+}
+
+impl Default for PayloadBudget {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Errors returned when an incident can not be brought within the budget.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum BudgetError {
+    #[error("incident data is {size} bytes, over the budget of {limit} bytes")]
+    DataTooLarge { size: usize, limit: usize },
+    #[error("incident message is {size} bytes, over the budget of {limit} bytes")]
+    MessageTooLarge { size: usize, limit: usize },
+    /// The data has to be truncated but it can not be read.
+    #[error("incident data can not be truncated: {0}")]
+    InvalidData(ViewError),
+    /// The truncated data can not be written back, a `MAP` entry landing past the 256 slots its
+    /// index can address.
+    #[error("truncated incident data can not be written: {0}")]
+    Unaddressable(IndexOverflow),
+}
+
+static BUDGET: Mutex<PayloadBudget> = Mutex::new(PayloadBudget::DEFAULT);
+
+/// Sets the budget applied to the incidents reported from now on.
+pub fn set_payload_budget(budget: PayloadBudget) {
+    *BUDGET.lock().unwrap_or_else(|e| e.into_inner()) = budget;
+}
+
+/// Returns the budget applied to reported incidents, [`PayloadBudget::DEFAULT`] unless set.
+pub fn payload_budget() -> PayloadBudget {
+    *BUDGET.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Returns the size of a value-data once serialized: every value of `data` plus the root, each
+/// counted as a tag byte, its scalar payload, 4 bytes per length prefix, 8 bytes per `LIST` and
+/// `STRUCT` index and 1 byte per `MAP` index.
+pub fn encoded_size(value: &ValueData) -> usize {
+    value
+        .data
+        .iter()
+        .flatten()
+        .chain(std::iter::once(&value.value))
+        .map(value_type_size)
+        .sum()
+}
+
+fn value_type_size(value: &ValueType) -> usize {
+    1 + match value {
+        ValueType::Bool(_) => 1,
+        ValueType::U64(_) | ValueType::Float(_) => 8,
+        ValueType::U256(bytes) | ValueType::Bytes(bytes) => 4 + bytes.len(),
+        ValueType::String(value) => 4 + value.len(),
+        ValueType::List(indexes) => 4 + 8 * indexes.len(),
+        ValueType::Map(elems) => {
+            4 + elems
+                .iter()
+                .map(|(key, _)| 4 + key.len() + 1)
+                .sum::<usize>()
+        }
+        ValueType::Struct((name, fields)) => {
+            4 + name.len()
+                + 4
+                + fields
+                    .iter()
+                    .map(|(key, _)| 4 + key.len() + 8)
+                    .sum::<usize>()
+        }
+    }
+}
+
+/// Shortens the message and data of an incident until they fit in `budget`.
+///
+/// Parameters:
+/// - `incident`: The incident, left as is if it already fits.
+/// - `budget`: The budget to apply.
+///
+/// Returns:
+/// A `BudgetError` if the incident does not fit and the policy is `Fail`, or if truncating
+/// could not make it fit.
+pub fn apply_budget(incident: &mut Incident, budget: &PayloadBudget) -> Result<(), BudgetError> {
+    if incident.message.len() > budget.max_message_bytes {
+        if budget.policy == TruncationPolicy::Fail {
+            return Err(BudgetError::MessageTooLarge {
+                size: incident.message.len(),
+                limit: budget.max_message_bytes,
+            });
+        }
+        truncate_message(&mut incident.message, budget.max_message_bytes);
+    }
+
+    if let Some(data) = &mut incident.data {
+        let size = encoded_size(data);
+        if size > budget.max_data_bytes {
+            *data = truncate_data(data, size, budget)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Cuts `message` on a char boundary so that, with the truncation note, it fits in `limit` bytes.
+/// The note is left out if `limit` is too small to hold it.
+fn truncate_message(message: &mut String, limit: usize) {
+    let note = |cut: usize| format!(" (truncated {cut} bytes)");
+    let room = limit.checked_sub(note(message.len()).len());
+
+    let mut end = room.unwrap_or(limit);
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let cut = message.len() - end;
+    message.truncate(end);
+    if room.is_some() {
+        message.push_str(&note(cut));
+    }
+}
+
+fn truncate_data(
+    data: &ValueData,
+    size: usize,
+    budget: &PayloadBudget,
+) -> Result<ValueData, BudgetError> {
+    let too_large = |size| BudgetError::DataTooLarge {
+        size,
+        limit: budget.max_data_bytes,
+    };
+    if budget.policy == TruncationPolicy::Fail {
+        return Err(too_large(size));
+    }
+
+    let mut root = Node::read(&data.view(), 0).map_err(BudgetError::InvalidData)?;
+    let limit = budget.max_data_bytes;
+
+    match budget.policy {
+        TruncationPolicy::TruncateLists => truncate_lists(&mut root, limit),
+        TruncationPolicy::DropLargestFields => drop_largest_fields(&mut root, limit),
+        TruncationPolicy::Fail => {}
+    }
+    truncate_scalars(&mut root, limit);
+
+    let truncated = root
+        .try_into_value_data()
+        .map_err(BudgetError::Unaddressable)?;
+    match encoded_size(&truncated) {
+        size if size > limit => Err(too_large(size)),
+        _ => Ok(truncated),
+    }
+}
+
+/// Truncates the largest list until the data fits or every list is down to its first element.
+fn truncate_lists(root: &mut Node, limit: usize) {
+    while root.size() > limit {
+        let excess = root.size() - limit;
+        let Some(Node::List {
+            elements,
+            truncated,
+        }) = root.largest_list()
+        else {
+            return;
+        };
+
+        let mut removed = 0;
+        while removed <= excess && elements.len() > 1 {
+            if let Some(element) = elements.pop() {
+                removed += 8 + element.size();
+                *truncated += 1;
+            }
+        }
+    }
+}
+
+/// Cuts the largest `STRING` and `BYTES` values until the data fits or they are all empty.
+fn truncate_scalars(root: &mut Node, limit: usize) {
+    while root.size() > limit {
+        let excess = root.size() - limit;
+        let Some(value) = root.largest_scalar() else {
+            return;
+        };
+
+        match value {
+            ValueType::String(value) => truncate_message(value, value.len().saturating_sub(excess)),
+            ValueType::Bytes(bytes) => bytes.truncate(bytes.len().saturating_sub(excess)),
+            _ => return,
+        }
+    }
+}
+
+/// Removes the largest entries of the root `MAP` or `STRUCT` until the data fits.
+fn drop_largest_fields(root: &mut Node, limit: usize) {
+    while root.size() > limit {
+        let (Node::Map(fields) | Node::Struct(_, fields)) = root else {
+            return;
+        };
+        let largest = fields
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (_, value))| value.size())
+            .map(|(position, _)| position);
+
+        match largest {
+            Some(position) => fields.remove(position),
+            None => return,
+        };
+    }
+}
+
+/// A value-data decoded into a tree, so that values can be removed without re-indexing.
+enum Node {
+    Scalar(ValueType),
+    /// A `LIST`, with the number of elements removed from its end.
+    List {
+        elements: Vec<Node>,
+        truncated: usize,
+    },
+    Map(Vec<(String, Node)>),
+    Struct(String, Vec<(String, Node)>),
+}
+
+impl Node {
+    fn read(view: &ValueDataView, depth: usize) -> Result<Node, ViewError> {
+        if depth > MAX_DEPTH {
+            return Err(view.invalid(format!("nested deeper than {MAX_DEPTH} levels")));
+        }
+        let read_fields = |view: &ValueDataView| {
+            view.entries()?
+                .into_iter()
+                .map(|(key, value)| Ok((key.to_string(), Node::read(&value, depth + 1)?)))
+                .collect::<Result<Vec<_>, ViewError>>()
+        };
+
+        Ok(match view.value() {
+            ValueType::List(_) => Node::List {
+                elements: view
+                    .elements()?
+                    .iter()
+                    .map(|element| Node::read(element, depth + 1))
+                    .collect::<Result<_, _>>()?,
+                truncated: 0,
+            },
+            ValueType::Map(_) => Node::Map(read_fields(view)?),
+            ValueType::Struct((name, _)) => Node::Struct(name.clone(), read_fields(view)?),
+            scalar => Node::Scalar(scalar.clone()),
+        })
+    }
+
+    /// Returns the encoded size of the node and its nested values, as [`encoded_size`] would.
+    fn size(&self) -> usize {
+        match self {
+            Node::Scalar(value) => value_type_size(value),
+            Node::List {
+                elements,
+                truncated,
+            } => {
+                let marker = match truncated {
+                    0 => 0,
+                    count => 8 + value_type_size(&marker(*count)),
+                };
+                5 + marker + elements.iter().map(|e| 8 + e.size()).sum::<usize>()
+            }
+            Node::Map(fields) => {
+                5 + fields
+                    .iter()
+                    .map(|(key, value)| 5 + key.len() + value.size())
+                    .sum::<usize>()
+            }
+            Node::Struct(name, fields) => {
+                9 + name.len()
+                    + fields
+                        .iter()
+                        .map(|(key, value)| 12 + key.len() + value.size())
+                        .sum::<usize>()
+            }
+        }
+    }
+
+    /// Returns the largest non-empty `STRING` or `BYTES` value.
+    fn largest_scalar(&mut self) -> Option<&mut ValueType> {
+        let len = self.largest_scalar_len();
+        match len {
+            0 => None,
+            len => self.find_scalar(len),
+        }
+    }
+
+    fn largest_scalar_len(&self) -> usize {
+        match self {
+            Node::Scalar(value) => scalar_len(value),
+            Node::List { elements, .. } => elements
+                .iter()
+                .map(Node::largest_scalar_len)
+                .max()
+                .unwrap_or(0),
+            Node::Map(fields) | Node::Struct(_, fields) => fields
+                .iter()
+                .map(|(_, value)| value.largest_scalar_len())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    fn find_scalar(&mut self, len: usize) -> Option<&mut ValueType> {
+        match self {
+            Node::Scalar(value) => (scalar_len(value) == len).then_some(value),
+            Node::List { elements, .. } => elements.iter_mut().find_map(|e| e.find_scalar(len)),
+            Node::Map(fields) | Node::Struct(_, fields) => fields
+                .iter_mut()
+                .find_map(|(_, value)| value.find_scalar(len)),
+        }
+    }
+
+    /// Returns the largest list that has more than one element.
+    fn largest_list(&mut self) -> Option<&mut Node> {
+        let size = self.largest_list_size()?;
+        self.find_list(size)
+    }
+
+    fn is_truncatable(&self) -> bool {
+        matches!(self, Node::List { elements, .. } if elements.len() > 1)
+    }
+
+    fn largest_list_size(&self) -> Option<usize> {
+        let nested = match self {
+            Node::Scalar(_) => None,
+            Node::List { elements, .. } => {
+                elements.iter().filter_map(Node::largest_list_size).max()
+            }
+            Node::Map(fields) | Node::Struct(_, fields) => fields
+                .iter()
+                .filter_map(|(_, value)| value.largest_list_size())
+                .max(),
+        };
+
+        match self.is_truncatable() {
+            true => Some(nested.map_or(self.size(), |nested| nested.max(self.size()))),
+            false => nested,
+        }
+    }
+
+    fn find_list(&mut self, size: usize) -> Option<&mut Node> {
+        if self.is_truncatable() && self.size() == size {
+            return Some(self);
+        }
+
+        match self {
+            Node::Scalar(_) => None,
+            Node::List { elements, .. } => elements.iter_mut().find_map(|e| e.find_list(size)),
+            Node::Map(fields) | Node::Struct(_, fields) => fields
+                .iter_mut()
+                .find_map(|(_, value)| value.find_list(size)),
+        }
+    }
+}
+
+/// Returns the length of a `STRING` or `BYTES` value, zero for other values.
+fn scalar_len(value: &ValueType) -> usize {
+    match value {
+        ValueType::String(value) => value.len(),
+        ValueType::Bytes(bytes) => bytes.len(),
+        _ => 0,
+    }
+}
+
+/// The string element appended to a truncated list.
+fn marker(count: usize) -> ValueType {
+    ValueType::String(format!("truncated: {count}"))
+}
+
+impl IntoValueData for Node {
+    fn write_value_data(self, data: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        match self {
+            Node::Scalar(value) => Ok(value),
+            Node::List {
+                elements,
+                truncated,
+            } => {
+                let mut elements = elements;
+                if truncated > 0 {
+                    elements.push(Node::Scalar(marker(truncated)));
+                }
+                elements.write_value_data(data)
+            }
+            Node::Map(fields) => write_map(data, fields.into_iter()),
+            Node::Struct(name, fields) => {
+                let mut writer = StructWriter::new(data, name);
+                for (key, value) in fields {
+                    writer.field(key, value);
+                }
+                writer.finish()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mamoru_serialize::to_json;
+
+    fn budget(max_data_bytes: usize, policy: TruncationPolicy) -> PayloadBudget {
+        PayloadBudget {
+            max_data_bytes,
+            policy,
+            ..PayloadBudget::DEFAULT
+        }
+    }
+
+    fn data(value: ValueType, data: Vec<ValueType>) -> ValueData {
+        ValueData {
+            data: Some(data),
+            value,
+        }
+    }
+
+    #[test]
+    fn truncates_the_largest_lists() {
+        let value = data(
+            ValueType::List((0..100).collect()),
+            (0..100).map(ValueType::U64).collect(),
+        );
+        let size = encoded_size(&value);
+
+        let truncated =
+            truncate_data(&value, size, &budget(200, TruncationPolicy::TruncateLists)).unwrap();
+
+        assert!(encoded_size(&truncated) <= 200);
+        let json = to_json(&truncated).unwrap();
+        let elements = json.as_array().unwrap();
+        assert_eq!(elements[0], 0);
+        assert!(elements
+            .last()
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .starts_with("truncated: "));
+    }
+
+    #[test]
+    fn cuts_large_scalars_lists_can_not_reduce() {
+        let value = data(
+            ValueType::Map(vec![("log".to_string(), 0), ("raw".to_string(), 1)]),
+            vec![
+                ValueType::String("é".repeat(1000)),
+                ValueType::Bytes(vec![0xab; 500]),
+            ],
+        );
+        let size = encoded_size(&value);
+
+        let truncated =
+            truncate_data(&value, size, &budget(1000, TruncationPolicy::TruncateLists)).unwrap();
+
+        assert!(encoded_size(&truncated) <= 1000);
+        let view = truncated.view();
+        let log = view.field("log").unwrap();
+        let log = log.as_str().unwrap();
+        assert!(log.starts_with("éé") && log.ends_with(" bytes)"), "{log}");
+        assert_eq!(view.field("raw").unwrap().as_bytes().unwrap().len(), 500);
+
+        let value = data(ValueType::Bytes(vec![0xab; 5000]), Vec::new());
+        let truncated =
+            truncate_data(&value, 5005, &budget(100, TruncationPolicy::TruncateLists)).unwrap();
+
+        assert_eq!(encoded_size(&truncated), 100);
+    }
+
+    #[test]
+    fn fails_when_the_policy_is_fail() {
+        let value = data(ValueType::String("x".repeat(100)), Vec::new());
+
+        assert_eq!(
+            truncate_data(&value, 105, &budget(50, TruncationPolicy::Fail)).err(),
+            Some(BudgetError::DataTooLarge {
+                size: 105,
+                limit: 50
+            })
+        );
+    }
+
+    #[test]
+    fn reports_maps_that_can_not_be_written_back() {
+        // The map entry is stored first, and lands past slot 255 once the list is written
+        // before it.
+        let mut arena = vec![
+            ValueType::U64(7),
+            ValueType::Map(vec![("k".to_string(), 0)]),
+            ValueType::List((3..303).collect()),
+        ];
+        arena.extend((0..300).map(ValueType::U64));
+        arena.push(ValueType::String("x".repeat(10_000)));
+        let value = data(
+            ValueType::Struct((
+                "Pool".to_string(),
+                vec![
+                    ("ticks".to_string(), 2),
+                    ("reserves".to_string(), 1),
+                    ("log".to_string(), 303),
+                ],
+            )),
+            arena,
+        );
+        let size = encoded_size(&value);
+
+        assert_eq!(
+            truncate_data(
+                &value,
+                size,
+                &budget(8000, TruncationPolicy::DropLargestFields)
+            )
+            .err(),
+            Some(BudgetError::Unaddressable(IndexOverflow(302)))
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Panic and assertion reporting through the host `env` interface.
+//!
+//! Without [`install_panic_hook`], a panic inside an agent only shows up as an opaque wasm trap.
+//! With it, the panic message and its location are sent to `env.abort`:
+//!
+//! ```ignore
+//! mamoru_rust_sdk::mamoru_env::install_panic_hook();
+//!
+//! mamoru_assert!(tx.gas_used <= tx.gas_budget, "gas used over budget in {}", tx.digest);
+//! ```
+
+use crate::component::guest::env;
+use std::cell::Cell;
+use std::fmt::{Arguments, Debug};
+use std::panic::{self, PanicHookInfo};
+use std::sync::Once;
+
+static PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+    /// Set by [`assert_failed`] before it panics, so that the hook does not report the failure a
+    /// second time through `env.abort`.
+    static ASSERTION_REPORTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Installs a panic hook that forwards the panic message and location to `env.abort`.
+///
+/// Calling it more than once has no effect.
+pub fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| panic::set_hook(Box::new(abort_with_panic_info)));
+}
+
+fn abort_with_panic_info(info: &PanicHookInfo) {
+    if ASSERTION_REPORTED.with(|reported| reported.replace(false)) {
+        return;
+    }
+
+    let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "Box<dyn Any>"
+    };
+
+    match info.location() {
+        Some(location) => env::abort(message, location.file(), location.line(), location.column()),
+        None => env::abort(message, "<unknown>", 0, 0),
+    }
+}
+
+/// Sends a failed assertion to `env.assert`, then panics in case the host did not stop the agent.
+/// The panic is not sent again to `env.abort` by the hook of [`install_panic_hook`].
+///
+/// Used by [`mamoru_assert!`] and its variants.
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_failed(message: &str) -> ! {
+    env::assert(0, message);
+    ASSERTION_REPORTED.with(|reported| reported.set(true));
+
+    panic!("{message}");
+}
+
+/// Builds the message of a failed [`mamoru_assert_eq!`] or [`mamoru_assert_ne!`].
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_compare_failed(
+    op: &str,
+    left: &dyn Debug,
+    right: &dyn Debug,
+    args: Option<Arguments>,
+    file: &str,
+    line: u32,
+) -> ! {
+    let mut message = format!("assertion `left {op} right` failed");
+    if let Some(args) = args {
+        message.push_str(&format!(": {args}"));
+    }
+    message.push_str(&format!(
+        "\n  left: {left:?}\n right: {right:?}\n at {file}:{line}"
+    ));
+
+    assert_failed(&message)
+}
+
+/// Asserts that a boolean expression is `true`, reporting the failure to `env.assert`.
+///
+/// An optional message with format arguments can be provided, as with `assert!`.
+#[macro_export]
+macro_rules! mamoru_assert {
+    ($cond:expr $(,)?) => {
+        if !$cond {
+            $crate::mamoru_env::assert_failed(::std::concat!(
+                "assertion failed: ",
+                ::std::stringify!($cond),
+                " at ",
+                ::std::file!(),
+                ":",
+                ::std::line!()
+            ));
+        }
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::mamoru_env::assert_failed(&::std::format!(
+                "{} at {}:{}",
+                ::std::format_args!($($arg)+),
+                ::std::file!(),
+                ::std::line!()
+            ));
+        }
+    };
+}
+
+/// Asserts that two expressions are equal, reporting the failure to `env.assert`.
+#[macro_export]
+macro_rules! mamoru_assert_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $crate::mamoru_env::assert_compare_failed(
+                        "==",
+                        left,
+                        right,
+                        ::std::option::Option::None,
+                        ::std::file!(),
+                        ::std::line!(),
+                    );
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $crate::mamoru_env::assert_compare_failed(
+                        "==",
+                        left,
+                        right,
+                        ::std::option::Option::Some(::std::format_args!($($arg)+)),
+                        ::std::file!(),
+                        ::std::line!(),
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Asserts that two expressions are not equal, reporting the failure to `env.assert`.
+#[macro_export]
+macro_rules! mamoru_assert_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    $crate::mamoru_env::assert_compare_failed(
+                        "!=",
+                        left,
+                        right,
+                        ::std::option::Option::None,
+                        ::std::file!(),
+                        ::std::line!(),
+                    );
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    $crate::mamoru_env::assert_compare_failed(
+                        "!=",
+                        left,
+                        right,
+                        ::std::option::Option::Some(::std::format_args!($($arg)+)),
+                        ::std::file!(),
+                        ::std::line!(),
+                    );
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,74 @@
+//! A [`log`] backend that sends log records to the host `debug` import.
+//!
+//! Call [`init`] once when the agent starts, then use the `log` macros:
+//!
+//! ```ignore
+//! mamoru_rust_sdk::mamoru_log::init();
+//!
+//! log::info!("checking {} events", events.len());
+//! ```
+//!
+//! Log records are never reported as incidents, use [`crate::mamoru::report`] for that.
+
+use crate::component::guest::mamoru::debug;
+use crate::component::guest::types::IncidentSeverity as WitIncidentSeverity;
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+static LOGGER: MamoruLogger = MamoruLogger;
+
+/// The logger installed by [`init`].
+pub struct MamoruLogger;
+
+impl Log for MamoruLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        send(record.level(), &format!("{}", record.args()));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger with the `Trace` level, so that every record is sent to the host.
+///
+/// Returns:
+/// A `SetLoggerError` if a logger was already installed.
+pub fn init() -> Result<(), SetLoggerError> {
+    init_with_level(LevelFilter::Trace)
+}
+
+/// Installs the logger, dropping records less severe than `level`.
+///
+/// Parameters:
+/// - `level`: The most verbose level sent to the host.
+///
+/// Returns:
+/// A `SetLoggerError` if a logger was already installed.
+pub fn init_with_level(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(level);
+
+    Ok(())
+}
+
+/// Maps a log level to the severity the host `debug` import expects.
+///
+/// The host only knows three severities, so `Debug` and `Trace` are sent as `INFO`.
+pub(crate) fn severity(level: Level) -> WitIncidentSeverity {
+    match level {
+        Level::Error => WitIncidentSeverity::Error,
+        Level::Warn => WitIncidentSeverity::Warning,
+        Level::Info | Level::Debug | Level::Trace => WitIncidentSeverity::Info,
+    }
+}
+
+/// Sends a message to the host `debug` import.
+pub(crate) fn send(level: Level, message: &str) {
+    debug(message, severity(level));
+}
@@ -0,0 +1,112 @@
+//! A SQL subset evaluated inside the agent against the chain context loaded by the host.
+//!
+//! Supported statements have the form:
+//!
+//! ```sql
+//! SELECT [DISTINCT] projections
+//! FROM table [alias]
+//! [[INNER | LEFT [OUTER]] JOIN table [alias] ON condition]...
+//! [WHERE condition]
+//! [GROUP BY expressions [HAVING condition]]
+//! [ORDER BY expression [ASC | DESC], ...]
+//! [LIMIT n [OFFSET m]]
+//! ```
+//!
+//! Expressions support comparisons, `AND`/`OR`/`NOT`, arithmetic, `IS [NOT] NULL`, `IN`,
+//! `BETWEEN`, `LIKE` and the `COUNT`, `SUM`, `AVG`, `MIN` and `MAX` aggregates. Bytes columns
+//! can be compared with `0x` prefixed hex strings.
+//!
+//! The tables are named after the chain and the records of the WIT context interfaces, e.g.
+//! `sui_transactions`, `sui_events`, `evm_events`, `aptos_calltraces` or
+//! `cosmos_event_attributes`, with one column per record field.
+
+mod executor;
+mod lexer;
+mod parser;
+mod tables;
+#[cfg(test)]
+mod tests;
+mod value;
+
+use crate::component::guest::types::{ValueData, ValueType};
+
+/// Errors returned by [`query`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum QueryError {
+    #[error("syntax error at offset {position}: {message}")]
+    Syntax { position: usize, message: String },
+    #[error("unknown table `{0}`")]
+    UnknownTable(String),
+    #[error("unknown column `{0}`")]
+    UnknownColumn(String),
+    #[error("ambiguous column `{0}`, qualify it with the table name or alias")]
+    AmbiguousColumn(String),
+    #[error("type error: {0}")]
+    Type(String),
+    #[error("invalid query: {0}")]
+    Invalid(String),
+    #[error("can not encode the result: {0}")]
+    Encoding(String),
+}
+
+impl QueryError {
+    fn syntax(position: usize, message: impl Into<String>) -> Self {
+        QueryError::Syntax {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+/// Names of the tables that can be queried.
+pub fn tables() -> &'static [&'static str] {
+    tables::TABLES
+}
+
+/// Runs a query against the chain context of the current run.
+///
+/// Parameters:
+/// - `query`: The SQL query.
+///
+/// Returns:
+/// One `MAP` value-data per result row, keyed by column name. `NULL` values are left out of
+/// the row.
+pub fn query(query: &str) -> Result<Vec<ValueData>, QueryError> {
+    let select = parser::parse(query)?;
+    let output = executor::execute(&select, &tables::load)?;
+
+    output
+        .rows
+        .into_iter()
+        .map(|row| encode_row(&output.columns, row))
+        .collect()
+}
+
+fn encode_row(columns: &[String], row: Vec<value::Value>) -> Result<ValueData, QueryError> {
+    let mut data = Vec::with_capacity(row.len());
+    let mut elems = Vec::with_capacity(row.len());
+    let mut values = Vec::with_capacity(row.len());
+
+    // Reserve the first slots for the columns, so they stay addressable by the `u8` indexes.
+    for (column, value) in columns.iter().zip(row) {
+        if value.is_null() {
+            continue;
+        }
+
+        let slot = data.len();
+        let index = u8::try_from(slot)
+            .map_err(|_| QueryError::Encoding(format!("too many columns: {}", columns.len())))?;
+        data.push(ValueType::Bool(false));
+        elems.push((column.clone(), index));
+        values.push((slot, value));
+    }
+
+    for (slot, value) in values {
+        value.write_to(&mut data, slot)?;
+    }
+
+    Ok(ValueData {
+        data: Some(data),
+        value: ValueType::Map(elems),
+    })
+}
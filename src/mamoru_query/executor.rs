@@ -0,0 +1,572 @@
+use super::parser::{Aggregate, BinaryOp, Expr, JoinKind, Projection, Select};
+use super::tables::Table;
+use super::value::Value;
+use super::QueryError;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// The columns of the rows being processed, as `(qualifier, name)`.
+type Scope = Vec<(String, String)>;
+
+/// An expression whose columns were resolved to positions of the current row.
+enum Bound {
+    Literal(Value),
+    Column(usize),
+    Not(Box<Bound>),
+    Neg(Box<Bound>),
+    Binary(BinaryOp, Box<Bound>, Box<Bound>),
+    IsNull(Box<Bound>, bool),
+    InList(Box<Bound>, Vec<Bound>, bool),
+    Between(Box<Bound>, Box<Bound>, Box<Bound>, bool),
+    Like(Box<Bound>, Box<Bound>, bool),
+    Aggregate(Aggregate, Option<Box<Bound>>, bool),
+    /// A column of the output row, only used by `ORDER BY`.
+    Output(usize),
+}
+
+/// The rows an expression is evaluated against: a single row, or a group of rows when
+/// aggregating.
+enum Context<'a> {
+    Row(&'a [Value]),
+    Group(&'a [&'a [Value]]),
+}
+
+/// The result of a query: output column names and rows of values.
+pub(super) struct Output {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+pub(super) fn execute(
+    select: &Select,
+    load: &dyn Fn(&str) -> Result<Table, QueryError>,
+) -> Result<Output, QueryError> {
+    let table = load(&select.from.name)?;
+    let qualifier = select.from.qualifier().to_string();
+    let mut scope: Scope = table
+        .columns
+        .into_iter()
+        .map(|column| (qualifier.clone(), column))
+        .collect();
+    let mut rows = table.rows;
+
+    for join in &select.joins {
+        reject_aggregate(&join.on, "JOIN conditions")?;
+        let table = load(&join.table.name)?;
+        let qualifier = join.table.qualifier().to_string();
+        let right_width = table.columns.len();
+        scope.extend(
+            table
+                .columns
+                .into_iter()
+                .map(|column| (qualifier.clone(), column)),
+        );
+        let on = bind(&join.on, &scope)?;
+
+        let mut joined = Vec::new();
+        for left in rows {
+            let mut matched = false;
+            for right in &table.rows {
+                let mut row = left.clone();
+                row.extend(right.iter().cloned());
+                if eval(&on, &Context::Row(&row))?.as_bool()? == Some(true) {
+                    joined.push(row);
+                    matched = true;
+                }
+            }
+
+            if !matched && join.kind == JoinKind::Left {
+                let mut row = left;
+                row.resize(row.len() + right_width, Value::Null);
+                joined.push(row);
+            }
+        }
+        rows = joined;
+    }
+
+    if let Some(filter) = &select.filter {
+        reject_aggregate(filter, "WHERE")?;
+        let filter = bind(filter, &scope)?;
+        let mut kept = Vec::with_capacity(rows.len());
+        for row in rows {
+            if eval(&filter, &Context::Row(&row))?.as_bool()? == Some(true) {
+                kept.push(row);
+            }
+        }
+        rows = kept;
+    }
+
+    let (columns, projections) = expand_projections(select, &scope)?;
+    let order_by = select
+        .order_by
+        .iter()
+        .map(|order| resolve_order(&order.expr, &columns, &scope))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let aggregating = !select.group_by.is_empty()
+        || select.having.is_some()
+        || select.projections.iter().any(|projection| {
+            matches!(projection, Projection::Expr(expr, _) if expr.contains_aggregate())
+        })
+        || select
+            .order_by
+            .iter()
+            .any(|order| order.expr.contains_aggregate());
+
+    // Each output row carries its sort keys until the final ordering.
+    let mut output: Vec<(Vec<Value>, Vec<Value>)> = Vec::new();
+    if aggregating {
+        let group_by = select
+            .group_by
+            .iter()
+            .map(|expr| {
+                reject_aggregate(expr, "GROUP BY")?;
+                bind(expr, &scope)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let having = select
+            .having
+            .as_ref()
+            .map(|having| bind(having, &scope))
+            .transpose()?;
+
+        for group in group_rows(&rows, &group_by)? {
+            let context = Context::Group(&group);
+            if let Some(having) = &having {
+                if eval(having, &context)?.as_bool()? != Some(true) {
+                    continue;
+                }
+            }
+            output.push(project(&projections, &order_by, &context)?);
+        }
+    } else {
+        for row in &rows {
+            output.push(project(&projections, &order_by, &Context::Row(row))?);
+        }
+    }
+
+    if select.distinct {
+        let mut seen = HashSet::new();
+        output.retain(|(values, _)| seen.insert(row_key(values)));
+    }
+
+    if !order_by.is_empty() {
+        output.sort_by(|(_, left), (_, right)| {
+            left.iter()
+                .zip(right)
+                .zip(&select.order_by)
+                .map(|((left, right), order)| {
+                    let ordering = left.sort_cmp(right);
+                    if order.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+
+    let rows = output
+        .into_iter()
+        .skip(select.offset)
+        .take(select.limit.unwrap_or(usize::MAX))
+        .map(|(values, _)| values)
+        .collect();
+
+    Ok(Output { columns, rows })
+}
+
+fn reject_aggregate(expr: &Expr, clause: &str) -> Result<(), QueryError> {
+    if expr.contains_aggregate() {
+        return Err(QueryError::Invalid(format!(
+            "aggregate functions are not allowed in {clause}"
+        )));
+    }
+    Ok(())
+}
+
+fn resolve(scope: &Scope, qualifier: Option<&str>, name: &str) -> Result<usize, QueryError> {
+    let mut found = scope.iter().enumerate().filter(|(_, (table, column))| {
+        column == name && qualifier.is_none_or(|qualifier| table == qualifier)
+    });
+    let display = match qualifier {
+        Some(qualifier) => format!("{qualifier}.{name}"),
+        None => name.to_string(),
+    };
+
+    match (found.next(), found.next()) {
+        (Some((index, _)), None) => Ok(index),
+        (Some(_), Some(_)) => Err(QueryError::AmbiguousColumn(display)),
+        (None, _) => Err(QueryError::UnknownColumn(display)),
+    }
+}
+
+fn bind(expr: &Expr, scope: &Scope) -> Result<Bound, QueryError> {
+    let boxed = |expr: &Expr| bind(expr, scope).map(Box::new);
+
+    Ok(match expr {
+        Expr::Literal(value) => Bound::Literal(value.clone()),
+        Expr::Column { qualifier, name } => {
+            Bound::Column(resolve(scope, qualifier.as_deref(), name)?)
+        }
+        Expr::Not(expr) => Bound::Not(boxed(expr)?),
+        Expr::Neg(expr) => Bound::Neg(boxed(expr)?),
+        Expr::Binary(op, left, right) => Bound::Binary(*op, boxed(left)?, boxed(right)?),
+        Expr::IsNull { expr, negated } => Bound::IsNull(boxed(expr)?, *negated),
+        Expr::InList {
+            expr,
+            list,
+            negated,
+        } => Bound::InList(
+            boxed(expr)?,
+            list.iter()
+                .map(|item| bind(item, scope))
+                .collect::<Result<_, _>>()?,
+            *negated,
+        ),
+        Expr::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => Bound::Between(boxed(expr)?, boxed(low)?, boxed(high)?, *negated),
+        Expr::Like {
+            expr,
+            pattern,
+            negated,
+        } => Bound::Like(boxed(expr)?, boxed(pattern)?, *negated),
+        Expr::Aggregate {
+            func,
+            arg,
+            distinct,
+        } => Bound::Aggregate(*func, arg.as_deref().map(boxed).transpose()?, *distinct),
+    })
+}
+
+fn expand_projections(
+    select: &Select,
+    scope: &Scope,
+) -> Result<(Vec<String>, Vec<Bound>), QueryError> {
+    let mut columns = Vec::new();
+    let mut projections = Vec::new();
+
+    for projection in &select.projections {
+        match projection {
+            Projection::Wildcard => {
+                for (index, (_, name)) in scope.iter().enumerate() {
+                    columns.push(name.clone());
+                    projections.push(Bound::Column(index));
+                }
+            }
+            Projection::QualifiedWildcard(qualifier) => {
+                let before = columns.len();
+                for (index, (_, name)) in scope
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (table, _))| table == qualifier)
+                {
+                    columns.push(name.clone());
+                    projections.push(Bound::Column(index));
+                }
+                if columns.len() == before {
+                    return Err(QueryError::UnknownTable(qualifier.clone()));
+                }
+            }
+            Projection::Expr(expr, alias) => {
+                columns.push(
+                    alias
+                        .clone()
+                        .unwrap_or_else(|| expr.default_name(columns.len() + 1)),
+                );
+                projections.push(bind(expr, scope)?);
+            }
+        }
+    }
+
+    Ok((columns, projections))
+}
+
+/// Resolves an `ORDER BY` expression: a 1-based output position, an output column name,
+/// or an expression over the input rows.
+fn resolve_order(expr: &Expr, columns: &[String], scope: &Scope) -> Result<Bound, QueryError> {
+    match expr {
+        Expr::Literal(Value::Int(position)) => usize::try_from(*position)
+            .ok()
+            .and_then(|position| position.checked_sub(1))
+            .filter(|index| *index < columns.len())
+            .map(Bound::Output)
+            .ok_or_else(|| {
+                QueryError::Invalid(format!("ORDER BY position {position} is out of range"))
+            }),
+        Expr::Column {
+            qualifier: None,
+            name,
+        } if columns.contains(name) => Ok(Bound::Output(
+            columns
+                .iter()
+                .position(|column| column == name)
+                .unwrap_or_default(),
+        )),
+        expr => bind(expr, scope),
+    }
+}
+
+fn project(
+    projections: &[Bound],
+    order_by: &[Bound],
+    context: &Context,
+) -> Result<(Vec<Value>, Vec<Value>), QueryError> {
+    let values = projections
+        .iter()
+        .map(|projection| eval(projection, context))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let keys = order_by
+        .iter()
+        .map(|key| match key {
+            Bound::Output(index) => Ok(values[*index].clone()),
+            key => eval(key, context),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((values, keys))
+}
+
+fn row_key(values: &[Value]) -> Vec<String> {
+    values.iter().map(Value::group_key).collect()
+}
+
+/// Splits the rows into groups with equal `group_by` values, in order of first appearance.
+///
+/// Without `GROUP BY` every row belongs to a single group, which exists even if there is no row.
+fn group_rows<'a>(
+    rows: &'a [Vec<Value>],
+    group_by: &[Bound],
+) -> Result<Vec<Vec<&'a [Value]>>, QueryError> {
+    if group_by.is_empty() {
+        return Ok(vec![rows.iter().map(Vec::as_slice).collect()]);
+    }
+
+    let mut groups: Vec<Vec<&[Value]>> = Vec::new();
+    let mut positions: HashMap<Vec<String>, usize> = HashMap::new();
+    for row in rows {
+        let key = group_by
+            .iter()
+            .map(|expr| eval(expr, &Context::Row(row)).map(|value| value.group_key()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let position = *positions.entry(key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[position].push(row);
+    }
+
+    Ok(groups)
+}
+
+fn eval(expr: &Bound, context: &Context) -> Result<Value, QueryError> {
+    Ok(match expr {
+        Bound::Literal(value) => value.clone(),
+        Bound::Column(index) => match context {
+            Context::Row(row) => row[*index].clone(),
+            Context::Group(rows) => rows
+                .first()
+                .map(|row| row[*index].clone())
+                .unwrap_or(Value::Null),
+        },
+        Bound::Not(expr) => match eval(expr, context)?.as_bool()? {
+            Some(value) => Value::Bool(!value),
+            None => Value::Null,
+        },
+        Bound::Neg(expr) => Value::Int(0).arithmetic('-', &eval(expr, context)?)?,
+        Bound::Binary(BinaryOp::And, left, right) => {
+            let left = eval(left, context)?.as_bool()?;
+            if left == Some(false) {
+                return Ok(Value::Bool(false));
+            }
+            match (left, eval(right, context)?.as_bool()?) {
+                (_, Some(false)) => Value::Bool(false),
+                (Some(true), Some(true)) => Value::Bool(true),
+                _ => Value::Null,
+            }
+        }
+        Bound::Binary(BinaryOp::Or, left, right) => {
+            let left = eval(left, context)?.as_bool()?;
+            if left == Some(true) {
+                return Ok(Value::Bool(true));
+            }
+            match (left, eval(right, context)?.as_bool()?) {
+                (_, Some(true)) => Value::Bool(true),
+                (Some(false), Some(false)) => Value::Bool(false),
+                _ => Value::Null,
+            }
+        }
+        Bound::Binary(op, left, right) => {
+            let left = eval(left, context)?;
+            let right = eval(right, context)?;
+            let comparison = |matches: fn(Ordering) -> bool| -> Result<Value, QueryError> {
+                Ok(left
+                    .compare(&right)?
+                    .map_or(Value::Null, |ordering| Value::Bool(matches(ordering))))
+            };
+
+            match op {
+                BinaryOp::Eq => comparison(Ordering::is_eq)?,
+                BinaryOp::NotEq => comparison(Ordering::is_ne)?,
+                BinaryOp::Lt => comparison(Ordering::is_lt)?,
+                BinaryOp::LtEq => comparison(Ordering::is_le)?,
+                BinaryOp::Gt => comparison(Ordering::is_gt)?,
+                BinaryOp::GtEq => comparison(Ordering::is_ge)?,
+                BinaryOp::Add => left.arithmetic('+', &right)?,
+                BinaryOp::Sub => left.arithmetic('-', &right)?,
+                BinaryOp::Mul => left.arithmetic('*', &right)?,
+                BinaryOp::Div => left.arithmetic('/', &right)?,
+                BinaryOp::Mod => left.arithmetic('%', &right)?,
+                BinaryOp::And | BinaryOp::Or => unreachable!("handled above"),
+            }
+        }
+        Bound::IsNull(expr, negated) => Value::Bool(eval(expr, context)?.is_null() != *negated),
+        Bound::InList(expr, list, negated) => {
+            let value = eval(expr, context)?;
+            if value.is_null() {
+                return Ok(Value::Null);
+            }
+
+            let mut saw_null = false;
+            for item in list {
+                match value.compare(&eval(item, context)?)? {
+                    Some(Ordering::Equal) => return Ok(Value::Bool(!negated)),
+                    Some(_) => {}
+                    None => saw_null = true,
+                }
+            }
+
+            if saw_null {
+                Value::Null
+            } else {
+                Value::Bool(*negated)
+            }
+        }
+        Bound::Between(expr, low, high, negated) => {
+            let value = eval(expr, context)?;
+            let low = value.compare(&eval(low, context)?)?;
+            let high = value.compare(&eval(high, context)?)?;
+            match (low, high) {
+                (Some(low), Some(high)) => Value::Bool((low.is_ge() && high.is_le()) != *negated),
+                _ => Value::Null,
+            }
+        }
+        Bound::Like(expr, pattern, negated) => {
+            match (eval(expr, context)?, eval(pattern, context)?) {
+                (Value::Null, _) | (_, Value::Null) => Value::Null,
+                (Value::String(value), Value::String(pattern)) => {
+                    let value: Vec<char> = value.chars().collect();
+                    let pattern: Vec<char> = pattern.chars().collect();
+                    Value::Bool(like(&value, &pattern) != *negated)
+                }
+                _ => return Err(QueryError::Type("LIKE expects strings".to_string())),
+            }
+        }
+        Bound::Output(_) => unreachable!("output columns are only read by `project`"),
+        Bound::Aggregate(func, arg, distinct) => {
+            let Context::Group(rows) = context else {
+                return Err(QueryError::Invalid(
+                    "aggregate functions are only allowed in SELECT, HAVING and ORDER BY"
+                        .to_string(),
+                ));
+            };
+            aggregate(*func, arg.as_deref(), *distinct, rows)?
+        }
+    })
+}
+
+fn aggregate(
+    func: Aggregate,
+    arg: Option<&Bound>,
+    distinct: bool,
+    rows: &[&[Value]],
+) -> Result<Value, QueryError> {
+    let Some(arg) = arg else {
+        return Ok(Value::Int(rows.len() as i128));
+    };
+
+    let mut values = Vec::with_capacity(rows.len());
+    let mut seen = HashSet::new();
+    for row in rows {
+        let value = eval(arg, &Context::Row(row))?;
+        if value.is_null() || (distinct && !seen.insert(value.group_key())) {
+            continue;
+        }
+        values.push(value);
+    }
+
+    Ok(match func {
+        Aggregate::Count => Value::Int(values.len() as i128),
+        Aggregate::Sum => {
+            let mut total = Value::Null;
+            for value in values {
+                if value.as_f64().is_none() {
+                    return Err(QueryError::Type("SUM expects numbers".to_string()));
+                }
+                total = match total {
+                    Value::Null => value,
+                    total => total.arithmetic('+', &value)?,
+                };
+            }
+            total
+        }
+        Aggregate::Avg => {
+            let mut sum = 0.0;
+            for value in &values {
+                sum += value
+                    .as_f64()
+                    .ok_or_else(|| QueryError::Type("AVG expects numbers".to_string()))?;
+            }
+            if values.is_empty() {
+                Value::Null
+            } else {
+                Value::Float(sum / values.len() as f64)
+            }
+        }
+        Aggregate::Min => values
+            .into_iter()
+            .min_by(Value::sort_cmp)
+            .unwrap_or(Value::Null),
+        Aggregate::Max => values
+            .into_iter()
+            .max_by(Value::sort_cmp)
+            .unwrap_or(Value::Null),
+    })
+}
+
+/// Matches `value` against a `LIKE` pattern where `%` is any sequence and `_` any character.
+fn like(value: &[char], pattern: &[char]) -> bool {
+    let (mut v, mut p) = (0, 0);
+    // Position of the last `%` in the pattern and of the value it is matched against.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(c) if *c == '_' || *c == value[v] => {
+                v += 1;
+                p += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    v = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '%')
+}
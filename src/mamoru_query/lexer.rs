@@ -0,0 +1,185 @@
+use super::QueryError;
+
+/// A token of the SQL subset, with the byte offset where it starts.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Token {
+    /// An unquoted identifier or keyword, as written.
+    Word(String),
+    /// A `"double quoted"` identifier.
+    QuotedIdent(String),
+    /// A `'single quoted'` string literal.
+    Str(String),
+    Int(u128),
+    Float(f64),
+    Comma,
+    Dot,
+    LParen,
+    RParen,
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+pub(super) fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let c = bytes[pos];
+
+        let token = match c {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                pos += 1;
+                continue;
+            }
+            b'-' if bytes.get(pos + 1) == Some(&b'-') => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            b',' => single(&mut pos, Token::Comma),
+            b'.' if !bytes.get(pos + 1).is_some_and(u8::is_ascii_digit) => {
+                single(&mut pos, Token::Dot)
+            }
+            b'(' => single(&mut pos, Token::LParen),
+            b')' => single(&mut pos, Token::RParen),
+            b'*' => single(&mut pos, Token::Star),
+            b'+' => single(&mut pos, Token::Plus),
+            b'-' => single(&mut pos, Token::Minus),
+            b'/' => single(&mut pos, Token::Slash),
+            b'%' => single(&mut pos, Token::Percent),
+            b'=' => {
+                pos += if bytes.get(pos + 1) == Some(&b'=') {
+                    2
+                } else {
+                    1
+                };
+                Token::Eq
+            }
+            b'!' if bytes.get(pos + 1) == Some(&b'=') => {
+                pos += 2;
+                Token::NotEq
+            }
+            b'<' => match bytes.get(pos + 1) {
+                Some(b'=') => {
+                    pos += 2;
+                    Token::LtEq
+                }
+                Some(b'>') => {
+                    pos += 2;
+                    Token::NotEq
+                }
+                _ => single(&mut pos, Token::Lt),
+            },
+            b'>' => match bytes.get(pos + 1) {
+                Some(b'=') => {
+                    pos += 2;
+                    Token::GtEq
+                }
+                _ => single(&mut pos, Token::Gt),
+            },
+            b'\'' => Token::Str(quoted(input, &mut pos, '\'')?),
+            b'"' => Token::QuotedIdent(quoted(input, &mut pos, '"')?),
+            c if c.is_ascii_digit() || c == b'.' => number(input, &mut pos)?,
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                while pos < bytes.len()
+                    && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_')
+                {
+                    pos += 1;
+                }
+                Token::Word(input[start..pos].to_string())
+            }
+            _ => {
+                let c = input[pos..].chars().next().unwrap_or_default();
+                return Err(QueryError::syntax(
+                    pos,
+                    format!("unexpected character `{c}`"),
+                ));
+            }
+        };
+
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+fn single(pos: &mut usize, token: Token) -> Token {
+    *pos += 1;
+    token
+}
+
+/// Reads a quoted string starting at `pos`, where a doubled quote escapes the quote itself.
+fn quoted(input: &str, pos: &mut usize, quote: char) -> Result<String, QueryError> {
+    let start = *pos;
+    let mut value = String::new();
+    let mut chars = input[start + 1..].char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        if c != quote {
+            value.push(c);
+            continue;
+        }
+
+        if chars.peek().is_some_and(|(_, next)| *next == quote) {
+            chars.next();
+            value.push(quote);
+            continue;
+        }
+
+        *pos = start + 1 + offset + 1;
+        return Ok(value);
+    }
+
+    Err(QueryError::syntax(start, "unterminated quoted string"))
+}
+
+/// Reads a decimal or `0x` hexadecimal integer, or a decimal float.
+fn number(input: &str, pos: &mut usize) -> Result<Token, QueryError> {
+    let start = *pos;
+    let bytes = input.as_bytes();
+
+    if input[start..].starts_with("0x") || input[start..].starts_with("0X") {
+        *pos += 2;
+        while *pos < bytes.len() && bytes[*pos].is_ascii_hexdigit() {
+            *pos += 1;
+        }
+
+        return u128::from_str_radix(&input[start + 2..*pos], 16)
+            .map(Token::Int)
+            .map_err(|e| QueryError::syntax(start, format!("invalid hex number: {e}")));
+    }
+
+    let mut is_float = false;
+    while *pos < bytes.len() {
+        match bytes[*pos] {
+            b'0'..=b'9' => {}
+            b'.' | b'e' | b'E' => is_float = true,
+            b'+' | b'-' if matches!(bytes[*pos - 1], b'e' | b'E') => {}
+            _ => break,
+        }
+        *pos += 1;
+    }
+
+    let text = &input[start..*pos];
+    if is_float {
+        text.parse()
+            .map(Token::Float)
+            .map_err(|e| QueryError::syntax(start, format!("invalid number `{text}`: {e}")))
+    } else {
+        text.parse()
+            .map(Token::Int)
+            .map_err(|e| QueryError::syntax(start, format!("invalid number `{text}`: {e}")))
+    }
+}
@@ -0,0 +1,687 @@
+use super::lexer::{tokenize, Token};
+use super::value::Value;
+use super::QueryError;
+
+/// A parsed `SELECT` statement.
+#[derive(Clone, Debug)]
+pub(super) struct Select {
+    pub distinct: bool,
+    pub projections: Vec<Projection>,
+    pub from: TableRef,
+    pub joins: Vec<Join>,
+    pub filter: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub having: Option<Expr>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+#[derive(Clone, Debug)]
+pub(super) enum Projection {
+    /// `*`
+    Wildcard,
+    /// `alias.*`
+    QualifiedWildcard(String),
+    /// `expr [AS alias]`
+    Expr(Expr, Option<String>),
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl TableRef {
+    /// The name used to qualify the columns of this table.
+    pub fn qualifier(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum JoinKind {
+    Inner,
+    Left,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Join {
+    pub kind: JoinKind,
+    pub table: TableRef,
+    pub on: Expr,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct OrderBy {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "COUNT" => Some(Aggregate::Count),
+            "SUM" => Some(Aggregate::Sum),
+            "AVG" => Some(Aggregate::Avg),
+            "MIN" => Some(Aggregate::Min),
+            "MAX" => Some(Aggregate::Max),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(super) enum Expr {
+    Literal(Value),
+    Column {
+        qualifier: Option<String>,
+        name: String,
+    },
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    InList {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
+    Like {
+        expr: Box<Expr>,
+        pattern: Box<Expr>,
+        negated: bool,
+    },
+    /// An aggregate call, `arg` is `None` for `COUNT(*)`.
+    Aggregate {
+        func: Aggregate,
+        arg: Option<Box<Expr>>,
+        distinct: bool,
+    },
+}
+
+impl Expr {
+    pub fn contains_aggregate(&self) -> bool {
+        match self {
+            Expr::Aggregate { .. } => true,
+            Expr::Literal(_) | Expr::Column { .. } => false,
+            Expr::Not(expr) | Expr::Neg(expr) | Expr::IsNull { expr, .. } => {
+                expr.contains_aggregate()
+            }
+            Expr::Binary(_, left, right) => left.contains_aggregate() || right.contains_aggregate(),
+            Expr::InList { expr, list, .. } => {
+                expr.contains_aggregate() || list.iter().any(Expr::contains_aggregate)
+            }
+            Expr::Between {
+                expr, low, high, ..
+            } => expr.contains_aggregate() || low.contains_aggregate() || high.contains_aggregate(),
+            Expr::Like { expr, pattern, .. } => {
+                expr.contains_aggregate() || pattern.contains_aggregate()
+            }
+        }
+    }
+
+    /// The name of the output column when the projection has no alias.
+    pub fn default_name(&self, position: usize) -> String {
+        match self {
+            Expr::Column { name, .. } => name.clone(),
+            Expr::Aggregate {
+                func, arg: None, ..
+            } => format!("{}(*)", func.name()),
+            Expr::Aggregate {
+                func,
+                arg: Some(arg),
+                distinct,
+            } => match arg.as_ref() {
+                Expr::Column { name, .. } if *distinct => {
+                    format!("{}(distinct {name})", func.name())
+                }
+                Expr::Column { name, .. } => format!("{}({name})", func.name()),
+                _ => func.name().to_string(),
+            },
+            _ => format!("column{position}"),
+        }
+    }
+}
+
+const RESERVED: &[&str] = &[
+    "SELECT", "DISTINCT", "FROM", "WHERE", "GROUP", "BY", "HAVING", "ORDER", "ASC", "DESC",
+    "LIMIT", "OFFSET", "JOIN", "INNER", "LEFT", "OUTER", "ON", "AS", "AND", "OR", "NOT", "IS",
+    "NULL", "IN", "LIKE", "BETWEEN", "TRUE", "FALSE",
+];
+
+pub(super) fn parse(input: &str) -> Result<Select, QueryError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+        end: input.len(),
+    };
+    let select = parser.select()?;
+
+    if let Some((token, offset)) = parser.tokens.get(parser.pos) {
+        return Err(QueryError::syntax(
+            *offset,
+            format!("unexpected {token:?} after the end of the query"),
+        ));
+    }
+
+    Ok(select)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(_, offset)| *offset)
+            .unwrap_or(self.end)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, QueryError> {
+        Err(QueryError::syntax(self.offset(), message))
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn peek_keyword_at(&self, ahead: usize, keyword: &str) -> bool {
+        matches!(
+            self.tokens.get(self.pos + ahead),
+            Some((Token::Word(word), _)) if word.eq_ignore_ascii_case(keyword)
+        )
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), QueryError> {
+        if self.eat_keyword(keyword) {
+            return Ok(());
+        }
+        self.error(format!("expected `{keyword}`"))
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), QueryError> {
+        if self.eat(token) {
+            return Ok(());
+        }
+        self.error(format!("expected {token:?}"))
+    }
+
+    /// Reads an identifier that is not a reserved keyword.
+    fn identifier(&mut self) -> Result<String, QueryError> {
+        match self.peek() {
+            Some(Token::Word(word)) if !is_reserved(word) => {
+                let word = word.to_ascii_lowercase();
+                self.pos += 1;
+                Ok(word)
+            }
+            Some(Token::QuotedIdent(ident)) => {
+                let ident = ident.clone();
+                self.pos += 1;
+                Ok(ident)
+            }
+            _ => self.error("expected an identifier"),
+        }
+    }
+
+    fn optional_alias(&mut self) -> Result<Option<String>, QueryError> {
+        if self.eat_keyword("AS") {
+            return self.identifier().map(Some);
+        }
+
+        match self.peek() {
+            Some(Token::Word(word)) if !is_reserved(word) => self.identifier().map(Some),
+            Some(Token::QuotedIdent(_)) => self.identifier().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn unsigned(&mut self, clause: &str) -> Result<usize, QueryError> {
+        match self.advance() {
+            Some(Token::Int(value)) => usize::try_from(value)
+                .or_else(|_| self.error(format!("{clause} value is too large"))),
+            _ => {
+                self.pos -= 1;
+                self.error(format!("expected a number after {clause}"))
+            }
+        }
+    }
+
+    fn select(&mut self) -> Result<Select, QueryError> {
+        self.expect_keyword("SELECT")?;
+        let distinct = self.eat_keyword("DISTINCT");
+
+        let mut projections = vec![self.projection()?];
+        while self.eat(&Token::Comma) {
+            projections.push(self.projection()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let from = self.table_ref()?;
+
+        let mut joins = Vec::new();
+        loop {
+            let kind = if self.eat_keyword("JOIN") {
+                JoinKind::Inner
+            } else if self.peek_keyword("INNER") && self.peek_keyword_at(1, "JOIN") {
+                self.pos += 2;
+                JoinKind::Inner
+            } else if self.eat_keyword("LEFT") {
+                self.eat_keyword("OUTER");
+                self.expect_keyword("JOIN")?;
+                JoinKind::Left
+            } else {
+                break;
+            };
+
+            let table = self.table_ref()?;
+            self.expect_keyword("ON")?;
+            let on = self.expr()?;
+            joins.push(Join { kind, table, on });
+        }
+
+        let filter = if self.eat_keyword("WHERE") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+
+        let mut group_by = Vec::new();
+        if self.eat_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by.push(self.expr()?);
+            while self.eat(&Token::Comma) {
+                group_by.push(self.expr()?);
+            }
+        }
+
+        let having = if self.eat_keyword("HAVING") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let expr = self.expr()?;
+                let descending = if self.eat_keyword("DESC") {
+                    true
+                } else {
+                    self.eat_keyword("ASC");
+                    false
+                };
+                order_by.push(OrderBy { expr, descending });
+
+                if !self.eat(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let limit = if self.eat_keyword("LIMIT") {
+            Some(self.unsigned("LIMIT")?)
+        } else {
+            None
+        };
+
+        let offset = if self.eat_keyword("OFFSET") {
+            self.unsigned("OFFSET")?
+        } else {
+            0
+        };
+
+        Ok(Select {
+            distinct,
+            projections,
+            from,
+            joins,
+            filter,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn projection(&mut self) -> Result<Projection, QueryError> {
+        if self.eat(&Token::Star) {
+            return Ok(Projection::Wildcard);
+        }
+
+        let is_qualified_wildcard = matches!(
+            (self.tokens.get(self.pos + 1), self.tokens.get(self.pos + 2)),
+            (Some((Token::Dot, _)), Some((Token::Star, _)))
+        );
+        if is_qualified_wildcard {
+            let qualifier = self.identifier()?;
+            self.pos += 2;
+            return Ok(Projection::QualifiedWildcard(qualifier));
+        }
+
+        let expr = self.expr()?;
+        let alias = self.optional_alias()?;
+
+        Ok(Projection::Expr(expr, alias))
+    }
+
+    fn table_ref(&mut self) -> Result<TableRef, QueryError> {
+        let name = self.identifier()?;
+        let alias = self.optional_alias()?;
+
+        Ok(TableRef { name, alias })
+    }
+
+    fn expr(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.and()?;
+        while self.eat_keyword("OR") {
+            let right = self.and()?;
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.not()?;
+        while self.eat_keyword("AND") {
+            let right = self.not()?;
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, QueryError> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, QueryError> {
+        let left = self.additive()?;
+
+        let op = match self.peek() {
+            Some(Token::Eq) => Some(BinaryOp::Eq),
+            Some(Token::NotEq) => Some(BinaryOp::NotEq),
+            Some(Token::Lt) => Some(BinaryOp::Lt),
+            Some(Token::LtEq) => Some(BinaryOp::LtEq),
+            Some(Token::Gt) => Some(BinaryOp::Gt),
+            Some(Token::GtEq) => Some(BinaryOp::GtEq),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.pos += 1;
+            let right = self.additive()?;
+            return Ok(Expr::Binary(op, Box::new(left), Box::new(right)));
+        }
+
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull {
+                expr: Box::new(left),
+                negated,
+            });
+        }
+
+        let negated = self.peek_keyword("NOT")
+            && (self.peek_keyword_at(1, "IN")
+                || self.peek_keyword_at(1, "LIKE")
+                || self.peek_keyword_at(1, "BETWEEN"));
+        if negated {
+            self.pos += 1;
+        }
+
+        if self.eat_keyword("IN") {
+            self.expect(&Token::LParen)?;
+            let mut list = vec![self.expr()?];
+            while self.eat(&Token::Comma) {
+                list.push(self.expr()?);
+            }
+            self.expect(&Token::RParen)?;
+            return Ok(Expr::InList {
+                expr: Box::new(left),
+                list,
+                negated,
+            });
+        }
+
+        if self.eat_keyword("LIKE") {
+            let pattern = self.additive()?;
+            return Ok(Expr::Like {
+                expr: Box::new(left),
+                pattern: Box::new(pattern),
+                negated,
+            });
+        }
+
+        if self.eat_keyword("BETWEEN") {
+            let low = self.additive()?;
+            self.expect_keyword("AND")?;
+            let high = self.additive()?;
+            return Ok(Expr::Between {
+                expr: Box::new(left),
+                low: Box::new(low),
+                high: Box::new(high),
+                negated,
+            });
+        }
+
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinaryOp::Add,
+                Some(Token::Minus) => BinaryOp::Sub,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.multiplicative()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinaryOp::Mul,
+                Some(Token::Slash) => BinaryOp::Div,
+                Some(Token::Percent) => BinaryOp::Mod,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        if self.eat(&Token::Minus) {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::Plus) {
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, QueryError> {
+        let Some(token) = self.peek().cloned() else {
+            return self.error("unexpected end of the query");
+        };
+
+        match token {
+            Token::Int(value) => match i128::try_from(value) {
+                Ok(value) => {
+                    self.pos += 1;
+                    Ok(Expr::Literal(Value::Int(value)))
+                }
+                Err(_) => self.error("number is too large"),
+            },
+            Token::Float(value) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Float(value)))
+            }
+            Token::Str(value) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::String(value)))
+            }
+            Token::LParen => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect(&Token::RParen)?;
+                Ok(expr)
+            }
+            Token::Word(word) if word.eq_ignore_ascii_case("NULL") => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Null))
+            }
+            Token::Word(word) if word.eq_ignore_ascii_case("TRUE") => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Bool(true)))
+            }
+            Token::Word(word) if word.eq_ignore_ascii_case("FALSE") => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Bool(false)))
+            }
+            Token::Word(word)
+                if matches!(self.tokens.get(self.pos + 1), Some((Token::LParen, _))) =>
+            {
+                self.function(&word)
+            }
+            Token::Word(_) | Token::QuotedIdent(_) => {
+                let first = self.identifier()?;
+                if self.eat(&Token::Dot) {
+                    let name = self.identifier()?;
+                    return Ok(Expr::Column {
+                        qualifier: Some(first),
+                        name,
+                    });
+                }
+                Ok(Expr::Column {
+                    qualifier: None,
+                    name: first,
+                })
+            }
+            other => self.error(format!("unexpected {other:?}")),
+        }
+    }
+
+    fn function(&mut self, name: &str) -> Result<Expr, QueryError> {
+        let Some(func) = Aggregate::from_name(name) else {
+            return self.error(format!("unknown function `{name}`"));
+        };
+        self.pos += 2;
+
+        if func == Aggregate::Count && self.eat(&Token::Star) {
+            self.expect(&Token::RParen)?;
+            return Ok(Expr::Aggregate {
+                func,
+                arg: None,
+                distinct: false,
+            });
+        }
+
+        let distinct = self.eat_keyword("DISTINCT");
+        let arg = self.expr()?;
+        self.expect(&Token::RParen)?;
+
+        if arg.contains_aggregate() {
+            return self.error("aggregate functions can not be nested");
+        }
+
+        Ok(Expr::Aggregate {
+            func,
+            arg: Some(Box::new(arg)),
+            distinct,
+        })
+    }
+}
+
+fn is_reserved(word: &str) -> bool {
+    RESERVED
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
@@ -0,0 +1,345 @@
+use super::value::Value;
+use super::QueryError;
+use crate::component::guest::types::SuiTransactionExpiration;
+use crate::component::guest::{aptos_ctx, cosmos_ctx, evm_ctx, sui_ctx};
+
+/// The rows of a table loaded from the chain context.
+pub(super) struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Builds a table whose columns are fields of the WIT records of the same name.
+macro_rules! table {
+    ($records:expr, [$($column:ident),* $(,)?]) => {
+        Table {
+            columns: vec![$(stringify!($column).to_string()),*],
+            rows: $records
+                .into_iter()
+                .map(|record| vec![$(Value::from(record.$column)),*])
+                .collect(),
+        }
+    };
+}
+
+/// Names of the tables that can be queried.
+pub(super) const TABLES: &[&str] = &[
+    "sui_transactions",
+    "sui_events",
+    "sui_calltraces",
+    "sui_calltrace_args",
+    "sui_calltrace_type_args",
+    "evm_blocks",
+    "evm_transactions",
+    "evm_events",
+    "evm_calltraces",
+    "aptos_blocks",
+    "aptos_transactions",
+    "aptos_events",
+    "aptos_calltraces",
+    "aptos_calltrace_args",
+    "aptos_calltrace_type_args",
+    "cosmos_blocks",
+    "cosmos_transactions",
+    "cosmos_events",
+    "cosmos_event_attributes",
+    "cosmos_validator_updates",
+    "cosmos_misbehaviors",
+    "cosmos_vote_infos",
+    "cosmos_calltraces",
+];
+
+/// Loads a table through the chain context imports of the host.
+pub(super) fn load(name: &str) -> Result<Table, QueryError> {
+    let table = match name {
+        "sui_transactions" => sui_transactions(),
+        "sui_events" => table!(
+            sui_ctx::get_events(),
+            [
+                tx_seq,
+                package_id,
+                transaction_module,
+                typ,
+                sender,
+                contents
+            ]
+        ),
+        "sui_calltraces" => table!(
+            sui_ctx::get_calltraces(),
+            [
+                seq,
+                tx_seq,
+                depth,
+                call_type,
+                gas_used,
+                transaction_module,
+                function
+            ]
+        ),
+        "sui_calltrace_args" => table!(sui_ctx::get_calltraces_arg(), [seq, calltrace_seq, arg]),
+        "sui_calltrace_type_args" => {
+            table!(
+                sui_ctx::get_calltraces_type_arg(),
+                [seq, calltrace_seq, arg]
+            )
+        }
+        "evm_blocks" => table!(
+            evm_ctx::get_blocks(),
+            [
+                block_index,
+                hash,
+                parent_hash,
+                state_root,
+                nonce,
+                status,
+                timestamp,
+                block_reward,
+                fee_recipient,
+                total_difficulty,
+                size,
+                gas_used,
+                gas_limit
+            ]
+        ),
+        "evm_transactions" => table!(
+            evm_ctx::get_transactions(),
+            [
+                tx_index,
+                tx_hash,
+                typ,
+                nonce,
+                status,
+                block_index,
+                frm,
+                to,
+                value,
+                fee,
+                gas_price,
+                gas_limit,
+                gas_used,
+                input,
+                size
+            ]
+        ),
+        "evm_events" => table!(
+            evm_ctx::get_events(),
+            [
+                index,
+                tx_index,
+                tx_hash,
+                block_number,
+                block_hash,
+                address,
+                topic0,
+                topic1,
+                topic2,
+                topic3,
+                topic4,
+                data
+            ]
+        ),
+        "evm_calltraces" => table!(
+            evm_ctx::get_calltraces(),
+            [
+                seq,
+                tx_index,
+                block_index,
+                depth,
+                typ,
+                frm,
+                to,
+                value,
+                gas_limit,
+                gas_used,
+                input
+            ]
+        ),
+        "aptos_blocks" => table!(aptos_ctx::get_blocks(), [hash, epoch, timestamp_usecs]),
+        "aptos_transactions" => table!(
+            aptos_ctx::get_transactions(),
+            [
+                seq,
+                block_hash,
+                hash,
+                event_root_hash,
+                state_change_hash,
+                gas_used,
+                max_gas_amount,
+                gas_unit_price,
+                expiration_timestamp_secs,
+                status,
+                sender,
+                sequence_number
+            ]
+        ),
+        "aptos_events" => table!(
+            aptos_ctx::get_events(),
+            [tx_seq, key, sequence_number, typ, data]
+        ),
+        "aptos_calltraces" => table!(
+            aptos_ctx::get_calltraces(),
+            [
+                seq,
+                tx_seq,
+                depth,
+                call_type,
+                gas_used,
+                transaction_module,
+                function
+            ]
+        ),
+        "aptos_calltrace_args" => {
+            table!(aptos_ctx::get_calltraces_arg(), [seq, calltrace_seq, arg])
+        }
+        "aptos_calltrace_type_args" => {
+            table!(
+                aptos_ctx::get_calltraces_type_arg(),
+                [seq, calltrace_seq, arg]
+            )
+        }
+        "cosmos_blocks" => table!(
+            cosmos_ctx::get_blocks(),
+            [
+                seq,
+                height,
+                hash,
+                version_block,
+                version_app,
+                chain_id,
+                time,
+                last_block_id_hash,
+                last_block_id_part_set_header_total,
+                last_block_id_part_set_header_hash,
+                last_commit_hash,
+                data_hash,
+                validators_hash,
+                next_validators_hash,
+                consensus_hash,
+                app_hash,
+                last_results_hash,
+                evidence_hash,
+                proposer_address,
+                last_commit_info_round,
+                consensus_param_updates_block_max_bytes,
+                consensus_param_updates_block_max_gas,
+                consensus_param_updates_evidence_max_age_num_blocks,
+                consensus_param_updates_evidence_max_age_duration,
+                consensus_param_updates_evidence_max_bytes,
+                consensus_param_updates_validator_pub_key_types,
+                consensus_param_updates_version_app
+            ]
+        ),
+        "cosmos_transactions" => table!(
+            cosmos_ctx::get_transactions(),
+            [seq, tx, tx_hash, tx_index, code, data, log, info, gas_wanted, gas_used, codespace]
+        ),
+        "cosmos_events" => table!(cosmos_ctx::get_events(), [seq, event_type]),
+        "cosmos_event_attributes" => table!(
+            cosmos_ctx::get_event_attributes(),
+            [seq, event_seq, key, value, index]
+        ),
+        "cosmos_validator_updates" => table!(
+            cosmos_ctx::get_validator_updates(),
+            [block_seq, pub_key, power]
+        ),
+        "cosmos_misbehaviors" => table!(
+            cosmos_ctx::get_misbehavior(),
+            [
+                block_seq,
+                typ,
+                validator_power,
+                validator_address,
+                height,
+                time,
+                total_voting_power
+            ]
+        ),
+        "cosmos_vote_infos" => cosmos_vote_infos(),
+        "cosmos_calltraces" => table!(
+            cosmos_ctx::get_evm_calltraces(),
+            [
+                tx_hash,
+                tx_index,
+                block_index,
+                depth,
+                typ,
+                frm,
+                to,
+                value,
+                gas_limit,
+                gas_used,
+                input,
+                output,
+                error,
+                revert_reason
+            ]
+        ),
+        _ => return Err(QueryError::UnknownTable(name.to_string())),
+    };
+
+    Ok(table)
+}
+
+fn sui_transactions() -> Table {
+    let transaction = sui_ctx::get_transaction();
+    let expiration = transaction
+        .as_ref()
+        .map(|transaction| match transaction.expiration {
+            SuiTransactionExpiration::None => Value::Null,
+            SuiTransactionExpiration::Epoch(epoch) => epoch.into(),
+        });
+
+    let mut table = table!(
+        transaction,
+        [
+            seq,
+            digest,
+            time,
+            sender,
+            gas_owner,
+            gas_price,
+            gas_budget,
+            gas_used,
+            gas_computation_cost,
+            gas_storage_cost,
+            is_system_tx,
+            is_genesis_tx,
+            is_end_of_epoch,
+            is_sponsored_tx,
+            kind,
+            success
+        ]
+    );
+
+    table.columns.push("expiration_epoch".to_string());
+    if let (Some(row), Some(expiration)) = (table.rows.first_mut(), expiration) {
+        row.push(expiration);
+    }
+
+    table
+}
+
+fn cosmos_vote_infos() -> Table {
+    // The WIT record names this field `validator-adders`.
+    Table {
+        columns: [
+            "block_seq",
+            "validator_address",
+            "validator_power",
+            "signed_last_block",
+        ]
+        .map(str::to_string)
+        .to_vec(),
+        rows: cosmos_ctx::get_vote_infos()
+            .into_iter()
+            .map(|vote| {
+                vec![
+                    vote.block_seq.into(),
+                    vote.validator_adders.into(),
+                    vote.validator_power.into(),
+                    vote.signed_last_block.into(),
+                ]
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,312 @@
+use super::executor::execute;
+use super::parser::parse;
+use super::tables::Table;
+use super::value::Value;
+use super::QueryError;
+
+fn transfers() -> Table {
+    let row = |id: u64, sender: &str, amount: Option<u64>, token: Option<&str>| {
+        vec![
+            Value::from(id),
+            Value::from(sender.to_string()),
+            Value::from(amount),
+            Value::from(token.map(str::to_string)),
+        ]
+    };
+
+    Table {
+        columns: ["id", "sender", "amount", "token"]
+            .map(String::from)
+            .to_vec(),
+        rows: vec![
+            row(1, "0xa", Some(100), Some("SUI")),
+            row(2, "0xb", Some(250), Some("USDC")),
+            row(3, "0xa", Some(50), Some("USDC")),
+            row(4, "0xc", None, Some("SUI")),
+            row(5, "0xb", Some(10), None),
+        ],
+    }
+}
+
+fn accounts() -> Table {
+    let row = |address: &str, name: &str| {
+        vec![
+            Value::from(address.to_string()),
+            Value::from(name.to_string()),
+        ]
+    };
+
+    Table {
+        columns: ["address", "name"].map(String::from).to_vec(),
+        rows: vec![row("0xa", "alice"), row("0xb", "bob"), row("0xd", "dave")],
+    }
+}
+
+fn load(name: &str) -> Result<Table, QueryError> {
+    match name {
+        "transfers" => Ok(transfers()),
+        "accounts" => Ok(accounts()),
+        other => Err(QueryError::UnknownTable(other.to_string())),
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::Int(value) => value.to_string(),
+        Value::Float(value) => value.to_string(),
+        Value::String(value) => value.clone(),
+        Value::Bytes(value) => format!("{value:?}"),
+        Value::Data(value) => format!("{value:?}"),
+    }
+}
+
+/// Runs a query against the `transfers` and `accounts` tables, returning each row as its
+/// comma-separated cells.
+fn run(query: &str) -> Result<Vec<String>, QueryError> {
+    let output = execute(&parse(query)?, &load)?;
+
+    Ok(output
+        .rows
+        .iter()
+        .map(|row| row.iter().map(cell).collect::<Vec<_>>().join(","))
+        .collect())
+}
+
+fn columns(query: &str) -> Vec<String> {
+    execute(&parse(query).unwrap(), &load).unwrap().columns
+}
+
+fn syntax_error(query: &str) -> (usize, String) {
+    match parse(query) {
+        Err(QueryError::Syntax { position, message }) => (position, message),
+        other => panic!("expected a syntax error for `{query}`, got {other:?}"),
+    }
+}
+
+#[test]
+fn parses_the_full_grammar() {
+    let select = parse(
+        "select distinct t.sender as who, count(*) from transfers t \
+         left outer join accounts a on a.address = t.sender \
+         where t.amount > 10 group by t.sender having count(*) > 1 \
+         order by who desc, 2 limit 10 offset 1 -- trailing comment",
+    )
+    .unwrap();
+
+    assert!(select.distinct);
+    assert_eq!(select.projections.len(), 2);
+    assert_eq!(select.from.qualifier(), "t");
+    assert_eq!(select.joins.len(), 1);
+    assert!(select.filter.is_some());
+    assert_eq!(select.group_by.len(), 1);
+    assert!(select.having.is_some());
+    assert_eq!(select.order_by.len(), 2);
+    assert!(select.order_by[0].descending);
+    assert_eq!(select.limit, Some(10));
+    assert_eq!(select.offset, 1);
+}
+
+#[test]
+fn reports_syntax_errors_with_their_offset() {
+    assert_eq!(
+        syntax_error("SELECT id"),
+        (9, "expected `FROM`".to_string())
+    );
+    assert_eq!(
+        syntax_error("SELECT id FROM transfers LIMIT x"),
+        (31, "expected a number after LIMIT".to_string())
+    );
+    assert_eq!(syntax_error("SELECT FROM transfers").0, 7);
+    assert_eq!(syntax_error("SELECT id FROM transfers WHERE").0, 30);
+    assert_eq!(syntax_error("SELECT id FROM transfers transfers x").0, 35);
+    assert_eq!(syntax_error("SELECT 'unterminated FROM transfers").0, 7);
+    assert!(parse("UPDATE transfers SET id = 1").is_err());
+}
+
+#[test]
+fn rejects_unknown_tables_and_columns() {
+    assert_eq!(
+        run("SELECT id FROM missing"),
+        Err(QueryError::UnknownTable("missing".to_string()))
+    );
+    assert_eq!(
+        run("SELECT nope FROM transfers"),
+        Err(QueryError::UnknownColumn("nope".to_string()))
+    );
+    assert!(matches!(
+        run("SELECT address FROM accounts a JOIN accounts b ON a.address = b.address"),
+        Err(QueryError::AmbiguousColumn(_))
+    ));
+}
+
+#[test]
+fn filters_rows() {
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE amount >= 50 AND token = 'USDC'").unwrap(),
+        ["2", "3"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE id IN (1, 5) OR amount BETWEEN 200 AND 300").unwrap(),
+        ["1", "2", "5"]
+    );
+    assert_eq!(
+        run("SELECT id, amount * 2 + 1 FROM transfers WHERE NOT id > 1").unwrap(),
+        ["1,201"]
+    );
+}
+
+#[test]
+fn uses_three_valued_logic_for_null() {
+    // Comparing NULL is unknown, which filters the row out either way.
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE amount > 0").unwrap(),
+        ["1", "2", "3", "5"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE NOT amount > 0").unwrap(),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE token <> 'SUI'").unwrap(),
+        ["2", "3"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE token NOT IN ('SUI')").unwrap(),
+        ["2", "3"]
+    );
+
+    // Unknown OR true is true, unknown AND false is false.
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE amount > 0 OR id = 4").unwrap(),
+        ["1", "2", "3", "4", "5"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE NOT (amount > 0 AND id = 0)").unwrap(),
+        ["1", "2", "3", "4", "5"]
+    );
+
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE amount IS NULL OR token IS NULL").unwrap(),
+        ["4", "5"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE amount IS NOT NULL AND token IS NOT NULL").unwrap(),
+        ["1", "2", "3"]
+    );
+    assert_eq!(
+        run("SELECT amount + 1 FROM transfers WHERE id = 4").unwrap(),
+        ["NULL"]
+    );
+}
+
+#[test]
+fn matches_like_patterns() {
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE token LIKE 'US%'").unwrap(),
+        ["2", "3"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE token LIKE '_U_'").unwrap(),
+        ["1", "4"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE token LIKE '%D%C'").unwrap(),
+        ["2", "3"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE token NOT LIKE '%S%'").unwrap(),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers WHERE token LIKE 'sui'").unwrap(),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn joins_tables() {
+    assert_eq!(
+        run("SELECT t.id, a.name FROM transfers t JOIN accounts a ON a.address = t.sender")
+            .unwrap(),
+        ["1,alice", "2,bob", "3,alice", "5,bob"]
+    );
+    assert_eq!(
+        run("SELECT t.id, a.name FROM transfers t LEFT JOIN accounts a ON a.address = t.sender")
+            .unwrap(),
+        ["1,alice", "2,bob", "3,alice", "4,NULL", "5,bob"]
+    );
+    assert_eq!(
+        run("SELECT name FROM accounts a INNER JOIN transfers t \
+             ON t.sender = a.address AND t.amount > 60")
+        .unwrap(),
+        ["alice", "bob"]
+    );
+}
+
+#[test]
+fn groups_and_aggregates() {
+    assert_eq!(
+        run(
+            "SELECT sender, COUNT(*), COUNT(amount), SUM(amount), MIN(amount), MAX(amount) \
+             FROM transfers GROUP BY sender ORDER BY sender"
+        )
+        .unwrap(),
+        [
+            "0xa,2,2,150,50,100",
+            "0xb,2,2,260,10,250",
+            "0xc,1,0,NULL,NULL,NULL"
+        ]
+    );
+    assert_eq!(
+        run("SELECT AVG(amount), COUNT(DISTINCT token) FROM transfers").unwrap(),
+        ["102.5,2"]
+    );
+    assert_eq!(
+        run(
+            "SELECT token, COUNT(*) FROM transfers GROUP BY token HAVING COUNT(*) > 1 \
+             ORDER BY token"
+        )
+        .unwrap(),
+        ["SUI,2", "USDC,2"]
+    );
+    assert_eq!(
+        run("SELECT COUNT(*) FROM transfers WHERE id > 10").unwrap(),
+        ["0"]
+    );
+    assert_eq!(
+        columns("SELECT sender, COUNT(*), SUM(amount) AS total FROM transfers GROUP BY sender"),
+        ["sender", "count(*)", "total"]
+    );
+    // Columns outside of the GROUP BY take their value from the first row of the group.
+    assert_eq!(
+        run("SELECT sender, amount FROM transfers GROUP BY sender ORDER BY sender").unwrap(),
+        ["0xa,100", "0xb,250", "0xc,NULL"]
+    );
+    assert!(run("SELECT id FROM transfers WHERE COUNT(*) > 1").is_err());
+}
+
+#[test]
+fn orders_and_limits_rows() {
+    assert_eq!(
+        run("SELECT id FROM transfers ORDER BY amount DESC, id").unwrap(),
+        ["2", "1", "3", "5", "4"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers ORDER BY sender DESC, id DESC LIMIT 3").unwrap(),
+        ["4", "5", "2"]
+    );
+    assert_eq!(
+        run("SELECT id AS n FROM transfers ORDER BY n DESC LIMIT 2 OFFSET 1").unwrap(),
+        ["4", "3"]
+    );
+    assert_eq!(
+        run("SELECT id FROM transfers ORDER BY id LIMIT 10 OFFSET 4").unwrap(),
+        ["5"]
+    );
+    assert_eq!(
+        run("SELECT DISTINCT sender FROM transfers ORDER BY sender").unwrap(),
+        ["0xa", "0xb", "0xc"]
+    );
+}
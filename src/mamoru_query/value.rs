@@ -0,0 +1,237 @@
+use super::QueryError;
+use crate::component::guest::types::{ValueData, ValueType};
+use crate::u256::U256;
+use std::cmp::Ordering;
+
+/// A value of a table cell or of an evaluated expression.
+#[derive(Clone, Debug)]
+pub(super) enum Value {
+    Null,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    /// Nested value-data such as call arguments or event contents, only checked for `NULL`.
+    Data(ValueData),
+}
+
+macro_rules! impl_from_int {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Value::Int(value.into())
+            }
+        }
+    )*};
+}
+
+impl_from_int!(u8, u16, u32, u64, i32, i64);
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Bytes(value)
+    }
+}
+
+impl From<ValueData> for Value {
+    fn from(value: ValueData) -> Self {
+        Value::Data(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Value::Null)
+    }
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Bytes(_) => "bytes",
+            Value::Data(_) => "value-data",
+        }
+    }
+
+    /// Reads the value as a SQL boolean, `None` being `NULL`.
+    pub fn as_bool(&self) -> Result<Option<bool>, QueryError> {
+        match self {
+            Value::Null => Ok(None),
+            Value::Bool(value) => Ok(Some(*value)),
+            other => Err(QueryError::Type(format!(
+                "expected a bool, found {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// Compares two values, `None` if one of them is `NULL`.
+    ///
+    /// Integers and floats are compared numerically, and bytes can be compared with `0x`
+    /// prefixed hexadecimal strings.
+    pub fn compare(&self, other: &Value) -> Result<Option<Ordering>, QueryError> {
+        let ordering = match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => return Ok(None),
+            (Value::Bool(left), Value::Bool(right)) => left.cmp(right),
+            (Value::Int(left), Value::Int(right)) => left.cmp(right),
+            (Value::Int(left), Value::Float(right)) => return Ok((*left as f64).partial_cmp(right)),
+            (Value::Float(left), Value::Int(right)) => {
+                return Ok(left.partial_cmp(&(*right as f64)))
+            }
+            (Value::Float(left), Value::Float(right)) => return Ok(left.partial_cmp(right)),
+            (Value::String(left), Value::String(right)) => left.cmp(right),
+            (Value::Bytes(left), Value::Bytes(right)) => left.cmp(right),
+            (Value::Bytes(left), Value::String(right)) => left.as_slice().cmp(&decode_hex(right)?),
+            (Value::String(left), Value::Bytes(right)) => decode_hex(left)?.as_slice().cmp(right),
+            (left, right) => {
+                return Err(QueryError::Type(format!(
+                    "can not compare {} with {}",
+                    left.type_name(),
+                    right.type_name()
+                )))
+            }
+        };
+
+        Ok(Some(ordering))
+    }
+
+    /// A total order used by `ORDER BY`, `MIN` and `MAX`: `NULL` first, then by type.
+    pub fn sort_cmp(&self, other: &Value) -> Ordering {
+        match self.compare(other) {
+            Ok(Some(ordering)) => ordering,
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Int(_) | Value::Float(_) => 2,
+            Value::String(_) => 3,
+            Value::Bytes(_) => 4,
+            Value::Data(_) => 5,
+        }
+    }
+
+    /// A key identifying equal values, used by `GROUP BY` and `DISTINCT`.
+    pub fn group_key(&self) -> String {
+        match self {
+            Value::Float(value) if value.fract() == 0.0 && value.abs() < i128::MAX as f64 => {
+                format!("{:?}", Value::Int(*value as i128))
+            }
+            other => format!("{other:?}"),
+        }
+    }
+
+    pub fn arithmetic(&self, op: char, other: &Value) -> Result<Value, QueryError> {
+        let overflow = || QueryError::Type(format!("integer overflow in `{op}`"));
+
+        Ok(match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (Value::Int(left), Value::Int(right)) => match op {
+                '+' => Value::Int(left.checked_add(*right).ok_or_else(overflow)?),
+                '-' => Value::Int(left.checked_sub(*right).ok_or_else(overflow)?),
+                '*' => Value::Int(left.checked_mul(*right).ok_or_else(overflow)?),
+                '/' => left.checked_div(*right).map_or(Value::Null, Value::Int),
+                _ => left.checked_rem(*right).map_or(Value::Null, Value::Int),
+            },
+            (left, right) => {
+                let (Some(left), Some(right)) = (left.as_f64(), right.as_f64()) else {
+                    return Err(QueryError::Type(format!(
+                        "can not apply `{op}` to {} and {}",
+                        left.type_name(),
+                        right.type_name()
+                    )));
+                };
+                match op {
+                    '+' => Value::Float(left + right),
+                    '-' => Value::Float(left - right),
+                    '*' => Value::Float(left * right),
+                    '/' if right == 0.0 => Value::Null,
+                    '/' => Value::Float(left / right),
+                    _ if right == 0.0 => Value::Null,
+                    _ => Value::Float(left % right),
+                }
+            }
+        })
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Converts the value into the `value-type` written at `arena[slot]`.
+    ///
+    /// Integers that do not fit in `U64` become a big-endian `U256`, negative integers become a
+    /// `FLOAT`, and nested value-data is embedded after the current end of `arena`.
+    pub fn write_to(self, arena: &mut Vec<ValueType>, slot: usize) -> Result<(), QueryError> {
+        arena[slot] = match self {
+            Value::Null => return Ok(()),
+            Value::Bool(value) => ValueType::Bool(value),
+            Value::Int(value) => match u64::try_from(value) {
+                Ok(value) => ValueType::U64(value),
+                Err(_) if value < 0 => ValueType::Float(value as f64),
+                Err(_) => U256::from(value as u128).into(),
+            },
+            Value::Float(value) => ValueType::Float(value),
+            Value::String(value) => ValueType::String(value),
+            Value::Bytes(value) => ValueType::Bytes(value),
+            Value::Data(value) => {
+                return crate::mamoru_serialize::embed(arena, slot, &value)
+                    .map_err(|e| QueryError::Encoding(e.to_string()))
+            }
+        };
+
+        Ok(())
+    }
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, QueryError> {
+    let invalid = || QueryError::Type(format!("`{value}` is not a 0x prefixed hex string"));
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .ok_or_else(invalid)?;
+
+    if digits.len() % 2 != 0 {
+        return Err(invalid());
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
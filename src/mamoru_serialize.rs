@@ -1,13 +1,51 @@
 use crate::component::guest::types::{ValueData, ValueType};
-use std::collections::HashMap;
+use crate::u256::U256;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Display;
+
+mod convert;
+mod de;
+mod display;
+mod json;
+mod schema;
+mod ser;
+mod view;
+
+pub(crate) use convert::write_map;
+#[doc(hidden)]
+pub use convert::{read_bytes, read_optional_field, read_u256};
+pub use convert::{FromValueData, IntoValueData, StructWriter};
+pub use mamoru_rust_sdk_macros::{FromValueData, HasSchema, IntoValueData};
+pub use schema::{FieldSchema, HasSchema, RecordSchema, SchemaViolation, ValueSchema};
+pub(crate) use view::type_name;
+pub use view::{ValueDataView, ViewError};
 
 /// A builder for dynamically constructing data entries.
+///
+/// Entries are kept in insertion order, so the same calls always build the same value-data.
+/// Setting a name again replaces its value in place.
+#[derive(Clone, Debug, Default)]
 pub struct DataBuilder {
-    /// A hashmap to hold the data entries with their names and corresponding values.
-    inner_data: HashMap<&'static str, ValueData>,
+    /// The data entries with their names and corresponding values, in insertion order.
+    inner_data: Vec<(String, Entry)>,
+}
+
+/// A data entry, nested builders being flattened only when the whole value-data is built.
+#[derive(Clone, Debug)]
+enum Entry {
+    Value(ValueData),
+    List(Vec<ValueData>),
+    Map(DataBuilder),
+    Structure(String, DataBuilder),
 }
 
 impl DataBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
     /// Adds an integer value to the data collection.
     ///
     /// Parameters:
@@ -16,15 +54,32 @@ impl DataBuilder {
     ///
     /// Returns:
     /// A mutable reference to the builder to allow for chaining.
-    pub fn integer(&mut self, name: &'static str, value: u64) -> &mut Self {
-        self.inner_data.insert(
-            name,
-            ValueData {
-                data: None,
-                value: ValueType::U64(value),
-            },
-        );
-        self
+    pub fn integer(&mut self, name: impl Into<String>, value: u64) -> &mut Self {
+        self.insert(name.into(), Entry::scalar(ValueType::U64(value)))
+    }
+
+    /// Adds a 256-bit integer value to the data collection.
+    ///
+    /// Parameters:
+    /// - `name`: The name of the data entry.
+    /// - `value`: The integer value to store, as 32 big-endian bytes.
+    ///
+    /// Returns:
+    /// A mutable reference to the builder to allow for chaining.
+    pub fn u256(&mut self, name: impl Into<String>, value: U256) -> &mut Self {
+        self.insert(name.into(), Entry::scalar(value.into()))
+    }
+
+    /// Adds a floating point value to the data collection.
+    ///
+    /// Parameters:
+    /// - `name`: The name of the data entry.
+    /// - `value`: The float value to store.
+    ///
+    /// Returns:
+    /// A mutable reference to the builder to allow for chaining.
+    pub fn float(&mut self, name: impl Into<String>, value: f64) -> &mut Self {
+        self.insert(name.into(), Entry::scalar(ValueType::Float(value)))
     }
 
     /// Adds a text string to the data collection.
@@ -35,15 +90,8 @@ impl DataBuilder {
     ///
     /// Returns:
     /// A mutable reference to the builder to allow for chaining.
-    pub fn text(&mut self, name: &'static str, value: String) -> &mut Self {
-        self.inner_data.insert(
-            name,
-            ValueData {
-                data: None,
-                value: ValueType::String(value),
-            },
-        );
-        self
+    pub fn text(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.insert(name.into(), Entry::scalar(ValueType::String(value.into())))
     }
 
     /// Adds a boolean value to the data collection.
@@ -54,50 +102,645 @@ impl DataBuilder {
     ///
     /// Returns:
     /// A mutable reference to the builder to allow for chaining.
-    pub fn bool(&mut self, name: &'static str, value: bool) -> &mut Self {
-        self.inner_data.insert(
-            name,
-            ValueData {
-                data: None,
-                value: ValueType::Bool(value),
-            },
-        );
+    pub fn bool(&mut self, name: impl Into<String>, value: bool) -> &mut Self {
+        self.insert(name.into(), Entry::scalar(ValueType::Bool(value)))
+    }
+
+    /// Adds raw bytes to the data collection.
+    ///
+    /// Parameters:
+    /// - `name`: The name of the data entry.
+    /// - `value`: The bytes to store.
+    ///
+    /// Returns:
+    /// A mutable reference to the builder to allow for chaining.
+    pub fn bytes(&mut self, name: impl Into<String>, value: impl Into<Vec<u8>>) -> &mut Self {
+        self.insert(name.into(), Entry::scalar(ValueType::Bytes(value.into())))
+    }
+
+    /// Adds an account or object address to the data collection.
+    ///
+    /// Parameters:
+    /// - `name`: The name of the data entry.
+    /// - `value`: The address, stored as a string such as `0x2`.
+    ///
+    /// Returns:
+    /// A mutable reference to the builder to allow for chaining.
+    pub fn address(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.insert(name.into(), Entry::scalar(ValueType::String(value.into())))
+    }
+
+    /// Adds an already built value-data to the data collection.
+    ///
+    /// Parameters:
+    /// - `name`: The name of the data entry.
+    /// - `value`: The value to store, which can itself hold nested values.
+    ///
+    /// Returns:
+    /// A mutable reference to the builder to allow for chaining.
+    pub fn value(&mut self, name: impl Into<String>, value: impl Into<ValueData>) -> &mut Self {
+        self.insert(name.into(), Entry::Value(value.into()))
+    }
+
+    /// Adds a nested map to the data collection.
+    ///
+    /// Parameters:
+    /// - `name`: The name of the data entry.
+    /// - `value`: The builder holding the entries of the map.
+    ///
+    /// Returns:
+    /// A mutable reference to the builder to allow for chaining.
+    pub fn map(&mut self, name: impl Into<String>, value: DataBuilder) -> &mut Self {
+        self.insert(name.into(), Entry::Map(value))
+    }
+
+    /// Adds a list of values to the data collection.
+    ///
+    /// Parameters:
+    /// - `name`: The name of the data entry.
+    /// - `values`: The items of the list, which can themselves be nested values.
+    ///
+    /// Returns:
+    /// A mutable reference to the builder to allow for chaining.
+    pub fn list(&mut self, name: impl Into<String>, values: Vec<ValueData>) -> &mut Self {
+        self.insert(name.into(), Entry::List(values))
+    }
+
+    /// Adds a struct to the data collection.
+    ///
+    /// Parameters:
+    /// - `name`: The name of the data entry.
+    /// - `type_name`: The name of the struct type, e.g. `0x2::coin::Coin`.
+    /// - `fields`: The builder holding the fields of the struct.
+    ///
+    /// Returns:
+    /// A mutable reference to the builder to allow for chaining.
+    pub fn structure(
+        &mut self,
+        name: impl Into<String>,
+        type_name: impl Into<String>,
+        fields: DataBuilder,
+    ) -> &mut Self {
+        self.insert(name.into(), Entry::Structure(type_name.into(), fields))
+    }
+
+    /// Returns `true` if the builder has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.inner_data.is_empty()
+    }
+
+    /// Returns the number of entries of the builder.
+    pub fn len(&self) -> usize {
+        self.inner_data.len()
+    }
+
+    /// Stores an entry, replacing the value of an existing name without moving it.
+    fn insert(&mut self, name: String, entry: Entry) -> &mut Self {
+        match self.inner_data.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, existing)) => *existing = entry,
+            None => self.inner_data.push((name, entry)),
+        }
         self
     }
+
+    /// Builds the `MAP` value-data holding every entry, nested ones included.
+    ///
+    /// Returns:
+    /// The value-data, or an `IndexOverflow` error if an entry is stored past the 256 slots a `MAP`
+    /// can address.
+    pub fn build(&self) -> Result<ValueData, IndexOverflow> {
+        let mut arena = Vec::new();
+        let value = self.map_value(&mut arena)?;
+
+        Ok(ValueData {
+            data: Some(arena),
+            value,
+        })
+    }
+
+    /// Returns the `MAP` of the entries, appending the entries to `arena`.
+    fn map_value(&self, arena: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        let elems = self
+            .write_entries(arena)?
+            .into_iter()
+            .map(|(key, index)| {
+                u8::try_from(index)
+                    .map(|index| (key, index))
+                    .map_err(|_| IndexOverflow(index))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ValueType::Map(elems))
+    }
+
+    /// Appends the entries to `arena`, returning their keys and indexes.
+    fn write_entries(
+        &self,
+        arena: &mut Vec<ValueType>,
+    ) -> Result<Vec<(String, usize)>, IndexOverflow> {
+        let base = reserve(arena, self.inner_data.len());
+        let mut written = Vec::with_capacity(self.inner_data.len());
+
+        for (offset, (key, entry)) in self.inner_data.iter().enumerate() {
+            arena[base + offset] = entry.value(arena)?;
+            written.push((key.clone(), base + offset));
+        }
+
+        Ok(written)
+    }
 }
 
-/// Converts a `DataBuilder` instance into a `ValueData` structure.
+/// Converts a `DataBuilder` instance into a `ValueData` structure, as [`DataBuilder::build`]
+/// does.
 ///
 /// This conversion allows the `DataBuilder` to be used directly where `ValueData` is required,
 /// packaging the entire set of entries into a single `ValueData` item.
-impl From<&DataBuilder> for ValueData {
-    fn from(builder: &DataBuilder) -> Self {
-        let mut data: Vec<ValueType> = Vec::new();
-        let mut elems: Vec<(String, u8)> = Vec::new();
-
-        for (index, (key, val)) in builder.inner_data.iter().enumerate() {
-            //TODO it is not supported not primitive types
-            if val.data.is_some() {
-                continue;
+impl TryFrom<&DataBuilder> for ValueData {
+    type Error = IndexOverflow;
+
+    fn try_from(builder: &DataBuilder) -> Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl TryFrom<DataBuilder> for ValueData {
+    type Error = IndexOverflow;
+
+    fn try_from(builder: DataBuilder) -> Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+/// Converts the `DataBuilder` to `ValueData` using the implementation from the `TryFrom` trait.
+///
+/// Parameters:
+/// - `data`: A reference to the `DataBuilder` instance.
+///
+/// Returns:
+/// A `ValueData` instance constructed from the builder, or an `IndexOverflow` error if an entry
+/// is stored past the 256 slots a `MAP` can address.
+pub fn data(data: &DataBuilder) -> Result<ValueData, IndexOverflow> {
+    data.build()
+}
+
+impl Entry {
+    fn scalar(value: ValueType) -> Self {
+        Entry::Value(ValueData { data: None, value })
+    }
+
+    /// Returns the value-type of the entry, appending its nested values to `arena`.
+    fn value(&self, arena: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        match self {
+            Entry::Value(value) => append(arena, value),
+            Entry::Map(builder) => builder.map_value(arena),
+            Entry::List(values) => {
+                let base = reserve(arena, values.len());
+                for (offset, value) in values.iter().enumerate() {
+                    embed(arena, base + offset, value)?;
+                }
+
+                Ok(ValueType::List(
+                    (base..base + values.len()).map(|i| i as u64).collect(),
+                ))
+            }
+            Entry::Structure(type_name, fields) => {
+                let elems = fields
+                    .write_entries(arena)?
+                    .into_iter()
+                    .map(|(key, index)| (key, index as u64))
+                    .collect();
+
+                Ok(ValueType::Struct((type_name.clone(), elems)))
             }
-            data.push(val.value.clone());
-            elems.push((key.to_string(), index as u8));
         }
+    }
+}
 
-        ValueData {
-            data: Some(data),
-            value: ValueType::Map(elems),
+/// Appends `len` placeholder slots to `arena`, returning the index of the first one.
+fn reserve(arena: &mut Vec<ValueType>, len: usize) -> usize {
+    let base = arena.len();
+    arena.resize(base + len, ValueType::Bool(false));
+    base
+}
+
+/// Error returned when a value can not be addressed by the `u8` indexes of a `MAP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("value-data index {0} does not fit in a MAP entry")]
+pub struct IndexOverflow(pub usize);
+
+/// Shifts the indexes held by a container `value` by `base`.
+fn shift_indexes(value: &ValueType, base: usize) -> Result<ValueType, IndexOverflow> {
+    let shift_u8 = |index: u8| {
+        let shifted = index as usize + base;
+        u8::try_from(shifted).map_err(|_| IndexOverflow(shifted))
+    };
+
+    Ok(match value {
+        ValueType::Map(elems) => ValueType::Map(
+            elems
+                .iter()
+                .map(|(key, index)| Ok((key.clone(), shift_u8(*index)?)))
+                .collect::<Result<_, _>>()?,
+        ),
+        ValueType::List(indexes) => {
+            ValueType::List(indexes.iter().map(|index| index + base as u64).collect())
         }
+        ValueType::Struct((name, fields)) => ValueType::Struct((
+            name.clone(),
+            fields
+                .iter()
+                .map(|(field, index)| (field.clone(), index + base as u64))
+                .collect(),
+        )),
+        other => other.clone(),
+    })
+}
+
+/// Embeds `value` into the flat `data` of another value-data.
+///
+/// The root of `value` is written to `arena[slot]` and its nested entries are appended at the
+/// end of `arena`, with their indexes shifted accordingly.
+pub(crate) fn embed(
+    arena: &mut Vec<ValueType>,
+    slot: usize,
+    value: &ValueData,
+) -> Result<(), IndexOverflow> {
+    arena[slot] = append(arena, value)?;
+
+    Ok(())
+}
+
+/// Appends the nested entries of `value` to `arena` and returns its root, with shifted indexes.
+fn append(arena: &mut Vec<ValueType>, value: &ValueData) -> Result<ValueType, IndexOverflow> {
+    let base = arena.len();
+    for nested in value.data.iter().flatten() {
+        arena.push(shift_indexes(nested, base)?);
+    }
+
+    shift_indexes(&value.value, base)
+}
+
+/// Name of the newtype struct `U256` serializes through, so that it becomes a `U256` value-type.
+pub(crate) const U256_TOKEN: &str = "$mamoru::U256";
+
+/// Errors returned when converting between Rust values and value-data.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ValueDataError {
+    /// The Rust value has no value-data representation, e.g. a negative integer.
+    #[error("{0}")]
+    Unsupported(String),
+    /// A `MAP` entry is stored past the slots its `u8` index can address.
+    #[error(transparent)]
+    IndexOverflow(#[from] IndexOverflow),
+    /// A container points at an index outside of `data`.
+    #[error("value-data index {0} is out of bounds")]
+    InvalidIndex(u64),
+    /// Any other error reported by a `Serialize` or `Deserialize` implementation.
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl serde::ser::Error for ValueDataError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ValueDataError::Custom(msg.to_string())
+    }
+}
+
+impl serde::de::Error for ValueDataError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ValueDataError::Custom(msg.to_string())
+    }
+}
+
+/// Serializes a Rust value into value-data.
+///
+/// Structs become `STRUCT`s named after the Rust type, maps become `MAP`s, sequences and tuples
+/// become `LIST`s, and `None` fields are left out. Enum variants with data are wrapped in a single
+/// entry `MAP` keyed by the variant name, unit variants are strings.
+///
+/// Parameters:
+/// - `value`: The value to serialize.
+///
+/// Returns:
+/// The value-data, or a `ValueDataError` if the value can not be represented, e.g. a negative
+/// integer or a top level `None`.
+pub fn to_value_data<T: Serialize + ?Sized>(value: &T) -> Result<ValueData, ValueDataError> {
+    value.serialize(ser::Serializer)?.ok_or_else(|| {
+        ValueDataError::Unsupported("`None` can not be represented as value-data".to_string())
+    })
+}
+
+/// Deserializes a Rust value from value-data, the reverse of [`to_value_data`].
+///
+/// Parameters:
+/// - `value`: The value-data to read.
+///
+/// Returns:
+/// The value, or a `ValueDataError` if the value-data does not match the expected type.
+pub fn from_value_data<T: DeserializeOwned>(value: &ValueData) -> Result<T, ValueDataError> {
+    T::deserialize(de::Deserializer {
+        value: &value.value,
+        data: value.data.as_deref().unwrap_or_default(),
+        depth: 0,
+    })
+}
+
+/// Nested values deeper than this are rejected, as indexes can form cycles.
+const MAX_DEPTH: usize = 128;
+
+fn too_deep() -> ValueDataError {
+    ValueDataError::Custom(format!(
+        "value-data is nested deeper than {MAX_DEPTH} levels"
+    ))
+}
+
+/// Returns the value-type a container index points at.
+fn resolve(data: &[ValueType], index: u64) -> Result<&ValueType, ValueDataError> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| data.get(index))
+        .ok_or(ValueDataError::InvalidIndex(index))
+}
+
+/// Formats bytes as a `0x` prefixed lowercase hex string.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+/// Parses a hex string, with or without the `0x` prefix.
+pub(crate) fn from_hex(value: &str) -> Result<Vec<u8>, String> {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+
+    if !digits.len().is_multiple_of(2) {
+        return Err("odd number of hex digits".to_string());
     }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            digits
+                .get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| format!("invalid hex digits at {i}"))
+        })
+        .collect()
 }
 
-/// Converts the `DataBuilder` to `ValueData` using the implementation from the `From` trait.
+/// Converts value-data into JSON, without losing any value-type.
+///
+/// Booleans, `U64`s, strings, `LIST`s and `MAP`s become their JSON counterpart, and finite floats
+/// become JSON numbers with a fractional part. The other value-types become single entry objects
+/// with hex strings, which `MAP` keys can not be confused with as their leading `$` is doubled:
+/// - `U256`: `{"$u256": "0x2a"}`
+/// - `BYTES`: `{"$bytes": "0x0102"}`
+/// - non-finite `FLOAT`: `{"$float": "NaN"}`
+/// - `STRUCT`: `{"$struct": "0x2::coin::Coin", "$fields": {"value": 5}}`
 ///
 /// Parameters:
-/// - `data`: A reference to the `DataBuilder` instance.
+/// - `value`: The value-data to convert.
+///
+/// Returns:
+/// The JSON value, or a `ValueDataError` if an index of the value-data is out of bounds.
+pub fn to_json(value: &ValueData) -> Result<serde_json::Value, ValueDataError> {
+    json::to_json(&value.value, value.data.as_deref().unwrap_or_default(), 0)
+}
+
+/// Converts JSON into value-data, the reverse of [`to_json`].
+///
+/// Numbers that are not unsigned integers become floats, and `null` object entries are left out.
+///
+/// Parameters:
+/// - `value`: The JSON value to convert.
 ///
 /// Returns:
-/// A `ValueData` instance constructed from the builder.
-pub fn data(data: &DataBuilder) -> ValueData {
-    data.into()
+/// The value-data, or a `ValueDataError` for a `null` outside of an object or an object with
+/// more entries than a `MAP` can address.
+pub fn from_json(value: &serde_json::Value) -> Result<ValueData, ValueDataError> {
+    let mut data = Vec::new();
+    let value = json::from_json(value, &mut data, 0)?;
+
+    Ok(ValueData {
+        data: Some(data),
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts the exact root and arena of a value-data.
+    fn assert_value_data(actual: &ValueData, value: ValueType, data: Vec<ValueType>) {
+        let expected = ValueData {
+            data: Some(data),
+            value,
+        };
+        assert_eq!(format!("{actual:?}"), format!("{expected:?}"));
+    }
+
+    fn scalar(value: ValueType) -> ValueData {
+        ValueData { data: None, value }
+    }
+
+    fn key(name: &str, index: u8) -> (String, u8) {
+        (name.to_string(), index)
+    }
+
+    #[test]
+    fn builds_entries_in_insertion_order() {
+        let mut builder = DataBuilder::new();
+        builder
+            .integer("zeta", 1)
+            .text(String::from("alpha"), "x")
+            .bool(format!("flag_{}", 2), true);
+
+        assert_value_data(
+            &builder.build().unwrap(),
+            ValueType::Map(vec![key("zeta", 0), key("alpha", 1), key("flag_2", 2)]),
+            vec![
+                ValueType::U64(1),
+                ValueType::String("x".to_string()),
+                ValueType::Bool(true),
+            ],
+        );
+    }
+
+    #[test]
+    fn replaces_entries_in_place() {
+        let mut builder = DataBuilder::new();
+        builder
+            .integer("amount", 1)
+            .text("token", "SUI")
+            .integer("amount", 2);
+
+        assert_eq!(builder.len(), 2);
+        assert_value_data(
+            &builder.build().unwrap(),
+            ValueType::Map(vec![key("amount", 0), key("token", 1)]),
+            vec![ValueType::U64(2), ValueType::String("SUI".to_string())],
+        );
+    }
+
+    #[test]
+    fn builds_typed_scalars() {
+        let mut builder = DataBuilder::new();
+        builder
+            .float("ratio", 0.5)
+            .u256("supply", U256::from(7u64))
+            .bytes("digest", [0xab, 0xcd])
+            .address("sender", "0x2");
+
+        assert_value_data(
+            &builder.build().unwrap(),
+            ValueType::Map(vec![
+                key("ratio", 0),
+                key("supply", 1),
+                key("digest", 2),
+                key("sender", 3),
+            ]),
+            vec![
+                ValueType::Float(0.5),
+                U256::from(7u64).into(),
+                ValueType::Bytes(vec![0xab, 0xcd]),
+                ValueType::String("0x2".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn indexes_entries_after_nested_values() {
+        let nested = ValueData {
+            data: Some(vec![ValueType::U64(1), ValueType::U64(2)]),
+            value: ValueType::List(vec![0, 1]),
+        };
+        let mut builder = DataBuilder::new();
+        builder.value("nested", nested).integer("after", 3);
+
+        assert_value_data(
+            &builder.build().unwrap(),
+            ValueType::Map(vec![key("nested", 0), key("after", 1)]),
+            vec![
+                ValueType::List(vec![2, 3]),
+                ValueType::U64(3),
+                ValueType::U64(1),
+                ValueType::U64(2),
+            ],
+        );
+    }
+
+    #[test]
+    fn fails_on_entries_past_slot_255() {
+        let mut builder = DataBuilder::new();
+        for i in 0..256 {
+            builder.integer(format!("{i:03}"), i);
+        }
+        assert_eq!(builder.build().unwrap().view().len().unwrap(), 256);
+
+        builder.integer("256", 256);
+        assert_eq!(builder.build().err(), Some(IndexOverflow(256)));
+        assert_eq!(
+            ValueData::try_from(&builder).err(),
+            Some(IndexOverflow(256))
+        );
+    }
+
+    #[test]
+    fn fails_on_nested_maps_past_slot_255() {
+        let mut inner = DataBuilder::new();
+        inner.integer("late", 1);
+        let mut builder = DataBuilder::new();
+        builder
+            .list("ticks", vec![U256::from(0u64).into(); 300])
+            .map("reserves", inner);
+
+        // The map is reserved at slot 1, the list fills 2..302 and the map entry lands at 302.
+        assert_eq!(builder.build().err(), Some(IndexOverflow(302)));
+    }
+
+    #[test]
+    fn flattens_nested_maps_and_structs() {
+        let mut coin = DataBuilder::new();
+        coin.text("type", "0x2::sui::SUI").integer("balance", 10);
+        let mut owner = DataBuilder::new();
+        owner
+            .address("address", "0xa")
+            .structure("coin", "0x2::coin::Coin", coin);
+        let mut builder = DataBuilder::new();
+        builder
+            .integer("before", 1)
+            .map("owner", owner)
+            .integer("after", 2);
+
+        let value = builder.build().unwrap();
+        let view = value.view();
+
+        assert_eq!(view.get("before").unwrap().as_u64().unwrap(), 1);
+        assert_eq!(view.get("after").unwrap().as_u64().unwrap(), 2);
+        assert_eq!(view.get("owner.address").unwrap().as_str().unwrap(), "0xa");
+        let coin = view.get("owner.coin").unwrap();
+        assert_eq!(coin.struct_name(), Some("0x2::coin::Coin"));
+        assert_eq!(
+            coin.field("type").unwrap().as_str().unwrap(),
+            "0x2::sui::SUI"
+        );
+        assert_eq!(coin.field("balance").unwrap().as_u64().unwrap(), 10);
+        assert_eq!(value.data.as_ref().unwrap().len(), 7);
+    }
+
+    #[test]
+    fn flattens_lists_of_nested_value_data() {
+        let mut first = DataBuilder::new();
+        first.integer("amount", 100).list(
+            "path",
+            vec![
+                scalar(ValueType::String("0xa".to_string())),
+                scalar(ValueType::String("0xb".to_string())),
+            ],
+        );
+        let mut second = DataBuilder::new();
+        second.integer("amount", 250);
+        let own_data = ValueData {
+            data: Some(vec![
+                ValueType::U64(7),
+                ValueType::Map(vec![("count".to_string(), 0)]),
+            ]),
+            value: ValueType::List(vec![1]),
+        };
+
+        let mut builder = DataBuilder::new();
+        builder.integer("head", 0).list(
+            "transfers",
+            vec![first.build().unwrap(), second.build().unwrap(), own_data],
+        );
+
+        let value = builder.build().unwrap();
+        let view = value.view();
+
+        assert_eq!(view.get("head").unwrap().as_u64().unwrap(), 0);
+        assert_eq!(view.get("transfers").unwrap().len().unwrap(), 3);
+        assert_eq!(
+            view.get("transfers[0].amount").unwrap().as_u64().unwrap(),
+            100
+        );
+        assert_eq!(
+            view.get("transfers[0].path[1]").unwrap().as_str().unwrap(),
+            "0xb"
+        );
+        assert_eq!(
+            view.get("transfers[1].amount").unwrap().as_u64().unwrap(),
+            250
+        );
+        assert_eq!(
+            view.get("transfers[2][0].count").unwrap().as_u64().unwrap(),
+            7
+        );
+    }
 }
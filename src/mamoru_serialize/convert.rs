@@ -0,0 +1,438 @@
+use super::{IndexOverflow, ValueDataView, ViewError};
+use crate::component::guest::types::{ValueData, ValueType};
+use crate::u256::U256;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+
+/// Rust types written directly to value-data, usually derived with `#[derive(IntoValueData)]`.
+///
+/// Derived structs become a `STRUCT` named after the type, with a field per Rust field and `None`
+/// fields left out. Derived enums become a `STRUCT` named after the enum, whose `variant` field
+/// holds the variant name next to the variant fields. The derive supports the following
+/// attributes:
+/// - `#[value(rename = "...")]` on the type, a variant or a field: the name to use instead.
+/// - `#[value(tag = "...")]` on an enum: the name of the variant field.
+/// - `#[value(u256)]` on a field: stores an integer as a `U256`.
+/// - `#[value(bytes)]` on a field: stores a `Vec<u8>` or a byte array as `BYTES`.
+///
+/// Deriving it also implements `TryFrom<T> for ValueData`, failing as
+/// [`IntoValueData::try_into_value_data`] does. The type can be passed to
+/// [`crate::mamoru_storage::KvStorage::set`] as is.
+pub trait IntoValueData: Sized {
+    /// Writes the value, appending its nested values to `data`.
+    ///
+    /// Returns:
+    /// The root value-type, or an `IndexOverflow` if a `MAP` entry, at any depth, can not be
+    /// addressed by its `u8` index.
+    fn write_value_data(self, data: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow>;
+
+    /// Converts the value into a standalone value-data.
+    ///
+    /// Returns:
+    /// The value-data, or an `IndexOverflow` if a `MAP` entry can not be addressed.
+    fn try_into_value_data(self) -> Result<ValueData, IndexOverflow> {
+        let mut data = Vec::new();
+        let value = self.write_value_data(&mut data)?;
+
+        Ok(ValueData {
+            data: Some(data),
+            value,
+        })
+    }
+}
+
+/// Rust types read directly from value-data, usually derived with `#[derive(FromValueData)]`.
+///
+/// The derive reads what [`IntoValueData`] writes and accepts the same attributes. Fields are
+/// looked up by name in a `STRUCT` or a `MAP`, `Option` fields may be missing, and the `STRUCT`
+/// type name is not checked.
+pub trait FromValueData: Sized {
+    /// Reads the value from a view, errors naming the path of the offending value.
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError>;
+
+    /// Reads the value from the root of a value-data.
+    fn from_value_data(value: &ValueData) -> Result<Self, ViewError> {
+        Self::from_view(&value.view())
+    }
+}
+
+impl ValueDataView<'_> {
+    /// Reads the viewed value into a Rust type, see [`FromValueData`].
+    pub fn read<T: FromValueData>(&self) -> Result<T, ViewError> {
+        T::from_view(self)
+    }
+
+    /// Returns an error naming the viewed value, for conversions that fail on its content.
+    pub fn invalid(&self, reason: impl Into<String>) -> ViewError {
+        ViewError::InvalidValue {
+            path: self.path().to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Writes the fields of a `STRUCT`, for hand-written [`IntoValueData`] implementations.
+///
+/// ```ignore
+/// fn write_value_data(self, data: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+///     let mut writer = StructWriter::new(data, "Swap");
+///     writer.field("pool", self.pool).u256("amount", self.amount);
+///     writer.finish()
+/// }
+/// ```
+pub struct StructWriter<'a> {
+    data: &'a mut Vec<ValueType>,
+    type_name: String,
+    fields: Vec<(String, u64)>,
+    /// The first field that could not be written, returned by [`StructWriter::finish`].
+    error: Option<IndexOverflow>,
+}
+
+impl<'a> StructWriter<'a> {
+    /// Starts a `STRUCT` whose fields are appended to `data`.
+    pub fn new(data: &'a mut Vec<ValueType>, type_name: impl Into<String>) -> Self {
+        StructWriter {
+            data,
+            type_name: type_name.into(),
+            fields: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Writes a field. Once a field fails, the next ones are ignored and the error is returned
+    /// by [`StructWriter::finish`].
+    pub fn field(&mut self, name: impl Into<String>, value: impl IntoValueData) -> &mut Self {
+        if self.error.is_none() {
+            match write_slot(self.data, value) {
+                Ok(index) => self.fields.push((name.into(), index as u64)),
+                Err(error) => self.error = Some(error),
+            }
+        }
+        self
+    }
+
+    /// Writes an integer field as a `U256`.
+    pub fn u256(&mut self, name: impl Into<String>, value: impl Into<U256>) -> &mut Self {
+        self.field(name, value.into())
+    }
+
+    /// Writes a byte field as `BYTES`.
+    pub fn bytes(&mut self, name: impl Into<String>, value: impl Into<Vec<u8>>) -> &mut Self {
+        self.field(name, Bytes(value.into()))
+    }
+
+    /// Returns the `STRUCT` value-type, or the error of the first field that could not be
+    /// written.
+    pub fn finish(self) -> Result<ValueType, IndexOverflow> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(ValueType::Struct((self.type_name, self.fields))),
+        }
+    }
+}
+
+/// Writes `value` to a new slot of `data` and returns its index.
+fn write_slot(
+    data: &mut Vec<ValueType>,
+    value: impl IntoValueData,
+) -> Result<usize, IndexOverflow> {
+    let slot = super::reserve(data, 1);
+    data[slot] = value.write_value_data(data)?;
+
+    Ok(slot)
+}
+
+/// Reads a field that may be missing.
+#[doc(hidden)]
+pub fn read_optional_field<T>(
+    view: &ValueDataView,
+    name: &str,
+    read: impl FnOnce(&ValueDataView) -> Result<T, ViewError>,
+) -> Result<Option<T>, ViewError> {
+    match view.field(name) {
+        Ok(field) => read(&field).map(Some),
+        Err(ViewError::MissingField { .. }) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Reads a `U256` or `U64` into an integer, for `#[value(u256)]` fields.
+#[doc(hidden)]
+pub fn read_u256<T: TryFrom<U256>>(view: &ValueDataView) -> Result<T, ViewError> {
+    let value = view.as_u256()?;
+
+    T::try_from(value).map_err(|_| {
+        view.invalid(format!(
+            "{value} does not fit in {}",
+            std::any::type_name::<T>()
+        ))
+    })
+}
+
+/// Reads `BYTES` into a byte container, for `#[value(bytes)]` fields.
+#[doc(hidden)]
+pub fn read_bytes<T: TryFrom<Vec<u8>>>(view: &ValueDataView) -> Result<T, ViewError> {
+    let bytes = view.as_bytes()?;
+
+    T::try_from(bytes.to_vec()).map_err(|_| {
+        view.invalid(format!(
+            "{} bytes do not fit in {}",
+            bytes.len(),
+            std::any::type_name::<T>()
+        ))
+    })
+}
+
+/// Bytes written as `BYTES` rather than as a `LIST` of integers.
+#[doc(hidden)]
+pub struct Bytes(pub Vec<u8>);
+
+impl IntoValueData for Bytes {
+    fn write_value_data(self, _: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        Ok(ValueType::Bytes(self.0))
+    }
+}
+
+macro_rules! impl_value_data_for_integer {
+    ($($ty:ty),*) => {$(
+        impl IntoValueData for $ty {
+            fn write_value_data(self, _: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+                Ok(ValueType::U64(self as u64))
+            }
+        }
+
+        impl FromValueData for $ty {
+            fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+                let value = view.as_u64()?;
+
+                <$ty>::try_from(value)
+                    .map_err(|_| view.invalid(format!("{value} does not fit in {}", stringify!($ty))))
+            }
+        }
+    )*};
+}
+
+impl_value_data_for_integer!(u8, u16, u32, u64, usize);
+
+impl IntoValueData for bool {
+    fn write_value_data(self, _: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        Ok(ValueType::Bool(self))
+    }
+}
+
+impl FromValueData for bool {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        view.as_bool()
+    }
+}
+
+impl IntoValueData for u128 {
+    fn write_value_data(self, data: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        U256::from(self).write_value_data(data)
+    }
+}
+
+impl FromValueData for u128 {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        read_u256(view)
+    }
+}
+
+impl IntoValueData for U256 {
+    fn write_value_data(self, _: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        Ok(self.into())
+    }
+}
+
+impl FromValueData for U256 {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        view.as_u256()
+    }
+}
+
+impl IntoValueData for f64 {
+    fn write_value_data(self, _: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        Ok(ValueType::Float(self))
+    }
+}
+
+/// Also reads a `U64`, as JSON numbers without a fraction are read as `U64`.
+impl FromValueData for f64 {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        match view.value() {
+            ValueType::U64(value) => Ok(*value as f64),
+            _ => view.as_f64(),
+        }
+    }
+}
+
+impl IntoValueData for String {
+    fn write_value_data(self, _: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        Ok(ValueType::String(self))
+    }
+}
+
+impl IntoValueData for &str {
+    fn write_value_data(self, _: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        Ok(ValueType::String(self.to_string()))
+    }
+}
+
+impl FromValueData for String {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        view.as_str().map(str::to_string)
+    }
+}
+
+impl<T: IntoValueData> IntoValueData for Box<T> {
+    fn write_value_data(self, data: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        (*self).write_value_data(data)
+    }
+}
+
+impl<T: FromValueData> FromValueData for Box<T> {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        T::from_view(view).map(Box::new)
+    }
+}
+
+impl<T: IntoValueData> IntoValueData for Vec<T> {
+    fn write_value_data(self, data: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        let indexes = self
+            .into_iter()
+            .map(|element| write_slot(data, element).map(|index| index as u64))
+            .collect::<Result<_, _>>()?;
+
+        Ok(ValueType::List(indexes))
+    }
+}
+
+impl<T: FromValueData> FromValueData for Vec<T> {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        view.elements()?.iter().map(T::from_view).collect()
+    }
+}
+
+/// Writes the entries of a `MAP`.
+///
+/// Returns:
+/// The `MAP` value-type, or an `IndexOverflow` if an entry lands past the 256 slots its `u8`
+/// index can address.
+pub(crate) fn write_map<V: IntoValueData>(
+    data: &mut Vec<ValueType>,
+    entries: impl ExactSizeIterator<Item = (String, V)>,
+) -> Result<ValueType, IndexOverflow> {
+    let base = super::reserve(data, entries.len());
+    let mut elems = Vec::new();
+
+    for (offset, (key, value)) in entries.enumerate() {
+        let index = u8::try_from(base + offset).map_err(|_| IndexOverflow(base + offset))?;
+        data[index as usize] = value.write_value_data(data)?;
+        elems.push((key, index));
+    }
+
+    Ok(ValueType::Map(elems))
+}
+
+impl<V: IntoValueData> IntoValueData for BTreeMap<String, V> {
+    fn write_value_data(self, data: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        write_map(data, self.into_iter())
+    }
+}
+
+impl<V: FromValueData> FromValueData for BTreeMap<String, V> {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        view.entries()?
+            .into_iter()
+            .map(|(key, value)| Ok((key.to_string(), V::from_view(&value)?)))
+            .collect()
+    }
+}
+
+impl<V: IntoValueData, S> IntoValueData for HashMap<String, V, S> {
+    fn write_value_data(self, data: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        write_map(data, self.into_iter())
+    }
+}
+
+impl<V: FromValueData, S: BuildHasher + Default> FromValueData for HashMap<String, V, S> {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        view.entries()?
+            .into_iter()
+            .map(|(key, value)| Ok((key.to_string(), V::from_view(&value)?)))
+            .collect()
+    }
+}
+
+impl IntoValueData for ValueData {
+    fn write_value_data(self, data: &mut Vec<ValueType>) -> Result<ValueType, IndexOverflow> {
+        super::append(data, &self)
+    }
+}
+
+impl FromValueData for ValueData {
+    fn from_view(view: &ValueDataView) -> Result<Self, ViewError> {
+        view.to_value_data()
+            .map_err(|error| view.invalid(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(count: usize) -> BTreeMap<String, u64> {
+        (0..count as u64).map(|i| (format!("{i:03}"), i)).collect()
+    }
+
+    #[test]
+    fn writes_maps_up_to_the_addressable_slots() {
+        let value = entries(256).try_into_value_data().unwrap();
+
+        assert_eq!(value.view().entries().unwrap().len(), 256);
+        assert_eq!(
+            entries(257).try_into_value_data().err(),
+            Some(IndexOverflow(256))
+        );
+    }
+
+    #[test]
+    fn fails_on_unaddressable_elements() {
+        let mut list = vec![BTreeMap::new(); 255];
+        list.push(entries(1));
+
+        assert_eq!(list.try_into_value_data().err(), Some(IndexOverflow(256)));
+    }
+
+    #[test]
+    fn fails_on_unaddressable_fields() {
+        let mut data = Vec::new();
+        let mut writer = StructWriter::new(&mut data, "Pool");
+        writer
+            .field("ticks", vec![0u64; 300])
+            .field("reserves", entries(1))
+            .field("fee", 3u64);
+
+        assert_eq!(writer.finish().err(), Some(IndexOverflow(302)));
+    }
+
+    #[test]
+    fn writes_struct_fields() {
+        let mut data = Vec::new();
+        let mut writer = StructWriter::new(&mut data, "Pool");
+        writer
+            .field("ticks", vec![1u64, 2])
+            .field("reserves", entries(2))
+            .u256("liquidity", 7u64)
+            .bytes("id", [0xab]);
+        let value = ValueData {
+            value: writer.finish().unwrap(),
+            data: Some(data),
+        };
+
+        assert_eq!(
+            super::super::to_json(&value).unwrap().to_string(),
+            r#"{"$struct":"Pool","$fields":{"ticks":[1,2],"reserves":{"000":0,"001":1},"liquidity":{"$u256":"0x7"},"id":{"$bytes":"0xab"}}}"#
+        );
+    }
+}
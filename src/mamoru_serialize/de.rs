@@ -0,0 +1,395 @@
+use super::{ValueDataError, MAX_DEPTH, U256_TOKEN};
+use crate::component::guest::types::ValueType;
+use crate::u256::U256;
+use serde::de::value::SeqDeserializer;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+/// Deserializes a value-type whose container indexes point into `data`.
+pub(super) struct Deserializer<'a> {
+    pub(super) value: &'a ValueType,
+    pub(super) data: &'a [ValueType],
+    pub(super) depth: usize,
+}
+
+impl<'a> Deserializer<'a> {
+    fn nested(&self, index: u64) -> Result<Deserializer<'a>, ValueDataError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(super::too_deep());
+        }
+
+        let value = super::resolve(self.data, index)?;
+
+        Ok(Deserializer {
+            value,
+            data: self.data,
+            depth: self.depth + 1,
+        })
+    }
+
+    fn entries(&self) -> Option<Vec<(&'a str, u64)>> {
+        match self.value {
+            ValueType::Map(elems) => Some(
+                elems
+                    .iter()
+                    .map(|(key, index)| (key.as_str(), u64::from(*index)))
+                    .collect(),
+            ),
+            ValueType::Struct((_, fields)) => Some(
+                fields
+                    .iter()
+                    .map(|(key, index)| (key.as_str(), *index))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'_> {
+    type Error = ValueDataError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueDataError> {
+        match self.value {
+            ValueType::Bool(value) => visitor.visit_bool(*value),
+            ValueType::U64(value) => visitor.visit_u64(*value),
+            ValueType::U256(bytes) => {
+                let value = U256::from_be_slice(bytes)
+                    .map_err(|e| ValueDataError::Custom(e.to_string()))?;
+                match u128::try_from(value) {
+                    Ok(value) => visitor.visit_u128(value),
+                    Err(_) => visitor.visit_bytes(bytes),
+                }
+            }
+            ValueType::Float(value) => visitor.visit_f64(*value),
+            ValueType::String(value) => visitor.visit_str(value),
+            ValueType::Bytes(value) => visitor.visit_bytes(value),
+            ValueType::List(indexes) => visitor.visit_seq(SeqAccess {
+                parent: self,
+                indexes: indexes.iter(),
+            }),
+            ValueType::Map(_) | ValueType::Struct(_) => {
+                let entries = self.entries().unwrap_or_default();
+                visitor.visit_map(MapAccess {
+                    parent: self,
+                    entries: entries.into_iter(),
+                    next: None,
+                })
+            }
+        }
+    }
+
+    /// Values are always present, missing struct fields being handled by serde itself.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueDataError> {
+        visitor.visit_some(self)
+    }
+
+    /// `BYTES` can also be read as a sequence, e.g. into a `Vec<u8>`.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueDataError> {
+        match self.value {
+            ValueType::Bytes(bytes) => {
+                visitor.visit_seq(SeqDeserializer::new(bytes.iter().copied()))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueDataError> {
+        match self.value {
+            ValueType::U256(bytes) if name == U256_TOKEN => visitor.visit_bytes(bytes),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueDataError> {
+        match self.value {
+            ValueType::String(variant) => visitor.visit_enum(variant.as_str().into_deserializer()),
+            ValueType::Map(elems) if elems.len() == 1 => {
+                let (variant, index) = &elems[0];
+                visitor.visit_enum(EnumAccess {
+                    variant,
+                    content: self.nested((*index).into())?,
+                })
+            }
+            other => Err(ValueDataError::Custom(format!(
+                "expected a string or a single entry MAP for an enum, found {other:?}"
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+struct SeqAccess<'a, I> {
+    parent: Deserializer<'a>,
+    indexes: I,
+}
+
+impl<'de, 'a, I: Iterator<Item = &'a u64>> de::SeqAccess<'de> for SeqAccess<'a, I> {
+    type Error = ValueDataError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ValueDataError> {
+        match self.indexes.next() {
+            Some(index) => seed.deserialize(self.parent.nested(*index)?).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct MapAccess<'a, I> {
+    parent: Deserializer<'a>,
+    entries: I,
+    next: Option<u64>,
+}
+
+impl<'de, 'a, I: Iterator<Item = (&'a str, u64)>> de::MapAccess<'de> for MapAccess<'a, I> {
+    type Error = ValueDataError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ValueDataError> {
+        match self.entries.next() {
+            Some((key, index)) => {
+                self.next = Some(index);
+                seed.deserialize(MapKeyDeserializer { key }).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ValueDataError> {
+        let index = self
+            .next
+            .take()
+            .ok_or_else(|| ValueDataError::Custom("map value without a key".to_string()))?;
+
+        seed.deserialize(self.parent.nested(index)?)
+    }
+}
+
+struct EnumAccess<'a> {
+    variant: &'a str,
+    content: Deserializer<'a>,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumAccess<'a> {
+    type Error = ValueDataError;
+    type Variant = Deserializer<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Deserializer<'a>), ValueDataError> {
+        let variant = seed.deserialize(MapKeyDeserializer { key: self.variant })?;
+        Ok((variant, self.content))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer<'_> {
+    type Error = ValueDataError;
+
+    fn unit_variant(self) -> Result<(), ValueDataError> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ValueDataError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ValueDataError> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueDataError> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+/// Deserializes the string keys of a `MAP` or `STRUCT`, parsing them when an integer is expected.
+struct MapKeyDeserializer<'a> {
+    key: &'a str,
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident),*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueDataError> {
+            match self.key.parse() {
+                Ok(value) => visitor.$visit(value),
+                Err(_) => visitor.visit_str(self.key),
+            }
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for MapKeyDeserializer<'_> {
+    type Error = ValueDataError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueDataError> {
+        visitor.visit_str(self.key)
+    }
+
+    deserialize_parsed_key!(
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128
+    );
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueDataError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueDataError> {
+        visitor.visit_enum(self.key.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{from_value_data, to_value_data};
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+    use std::fmt::Debug;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        Mint,
+        Burn(u64),
+        Split(u64, u64),
+        Swap { pool: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Transfer {
+        sender: String,
+        amount: u8,
+        ratio: f64,
+        big: u128,
+        value: U256,
+        memo: Option<String>,
+        note: Option<String>,
+        tags: Vec<char>,
+        balances: BTreeMap<String, u64>,
+        kinds: Vec<Kind>,
+    }
+
+    fn round_trip<T>(value: T)
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Debug,
+    {
+        let data = to_value_data(&value).unwrap();
+
+        assert_eq!(from_value_data::<T>(&data).unwrap(), value);
+    }
+
+    #[test]
+    fn round_trips_each_kind_of_value() {
+        round_trip(Transfer {
+            sender: "0xa".to_string(),
+            amount: u8::MAX,
+            ratio: -0.25,
+            big: u128::MAX,
+            value: U256::from_dec_str("1000000000000000000000000000000000000000").unwrap(),
+            memo: Some("gm".to_string()),
+            note: None,
+            tags: vec!['a', 'é'],
+            balances: BTreeMap::from([("0xa".to_string(), 1), ("0xb".to_string(), 2)]),
+            kinds: vec![
+                Kind::Mint,
+                Kind::Burn(1),
+                Kind::Split(2, 3),
+                Kind::Swap {
+                    pool: "0xc".to_string(),
+                },
+            ],
+        });
+        round_trip(u64::MAX);
+        round_trip(1.5f32);
+        round_trip("gm".to_string());
+        round_trip((true, 7u16, "x".to_string()));
+        round_trip(Vec::<u64>::new());
+    }
+
+    #[test]
+    fn reads_bytes_as_a_sequence_of_u8() {
+        let mut data = to_value_data(&0u8).unwrap();
+        data.value = ValueType::Bytes(vec![1, 2, 255]);
+
+        assert_eq!(from_value_data::<Vec<u8>>(&data).unwrap(), vec![1, 2, 255]);
+    }
+
+    #[test]
+    fn rejects_mismatched_types() {
+        let text = to_value_data(&"gm").unwrap();
+        let number = to_value_data(&300u64).unwrap();
+
+        assert!(matches!(
+            from_value_data::<u64>(&text),
+            Err(ValueDataError::Custom(_))
+        ));
+        assert!(matches!(
+            from_value_data::<u8>(&number),
+            Err(ValueDataError::Custom(_))
+        ));
+        assert!(matches!(
+            from_value_data::<Kind>(&number),
+            Err(ValueDataError::Custom(reason)) if reason.starts_with("expected a string or a single entry MAP")
+        ));
+        assert!(matches!(
+            from_value_data::<Kind>(&text),
+            Err(ValueDataError::Custom(_))
+        ));
+    }
+}
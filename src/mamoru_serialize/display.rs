@@ -0,0 +1,186 @@
+use super::MAX_DEPTH;
+use crate::component::guest::types::{ValueData, ValueType};
+use crate::u256::U256;
+use std::fmt::{self, Display, Formatter, Write};
+
+/// Formats the value with its `MAP`, `LIST` and `STRUCT` indexes resolved to the nested values.
+///
+/// `{}` writes the value on a single line, and `{:#}` writes nested values on indented lines:
+///
+/// ```text
+/// {amount: 100, coin: 0x2::coin::Coin {value: 5u256}, tags: ["a", "b"], digest: 0x0102}
+/// ```
+impl Display for ValueData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let data = self.data.as_deref().unwrap_or_default();
+        let pretty = f.alternate();
+
+        write_value(f, &self.value, data, pretty, 0)
+    }
+}
+
+fn write_value(
+    f: &mut Formatter<'_>,
+    value: &ValueType,
+    data: &[ValueType],
+    pretty: bool,
+    depth: usize,
+) -> fmt::Result {
+    if depth > MAX_DEPTH {
+        return f.write_str("...");
+    }
+
+    match value {
+        ValueType::Bool(value) => write!(f, "{value}"),
+        ValueType::U64(value) => write!(f, "{value}"),
+        ValueType::Float(value) => write!(f, "{value:?}"),
+        ValueType::String(value) => write!(f, "{value:?}"),
+        ValueType::U256(bytes) => match U256::from_be_slice(bytes) {
+            Ok(value) => write!(f, "{value}u256"),
+            Err(_) => write!(f, "<invalid u256 {}>", super::to_hex(bytes)),
+        },
+        ValueType::Bytes(bytes) => f.write_str(&super::to_hex(bytes)),
+        ValueType::List(indexes) => {
+            let items = indexes.iter().map(|index| (None, *index));
+            write_entries(f, "[", "]", items, data, pretty, depth)
+        }
+        ValueType::Map(elems) => {
+            let items = elems
+                .iter()
+                .map(|(key, index)| (Some(key.as_str()), u64::from(*index)));
+            write_entries(f, "{", "}", items, data, pretty, depth)
+        }
+        ValueType::Struct((name, fields)) => {
+            write!(f, "{name} ")?;
+            let items = fields
+                .iter()
+                .map(|(key, index)| (Some(key.as_str()), *index));
+            write_entries(f, "{", "}", items, data, pretty, depth)
+        }
+    }
+}
+
+fn write_entries<'a>(
+    f: &mut Formatter<'_>,
+    open: &str,
+    close: &str,
+    items: impl ExactSizeIterator<Item = (Option<&'a str>, u64)>,
+    data: &[ValueType],
+    pretty: bool,
+    depth: usize,
+) -> fmt::Result {
+    if items.len() == 0 {
+        return write!(f, "{open}{close}");
+    }
+
+    f.write_str(open)?;
+    for (position, (key, index)) in items.enumerate() {
+        if pretty {
+            f.write_char('\n')?;
+            write_indent(f, depth + 1)?;
+        } else if position > 0 {
+            f.write_str(", ")?;
+        }
+
+        if let Some(key) = key {
+            write!(f, "{key}: ")?;
+        }
+
+        match super::resolve(data, index) {
+            Ok(value) => write_value(f, value, data, pretty, depth + 1)?,
+            Err(_) => write!(f, "<invalid index {index}>")?,
+        }
+
+        if pretty {
+            f.write_char(',')?;
+        }
+    }
+
+    if pretty {
+        f.write_char('\n')?;
+        write_indent(f, depth)?;
+    }
+    f.write_str(close)
+}
+
+fn write_indent(f: &mut Formatter<'_>, depth: usize) -> fmt::Result {
+    for _ in 0..depth {
+        f.write_str("    ")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::from_json;
+    use super::*;
+    use serde_json::json;
+
+    fn transfer() -> ValueData {
+        from_json(&json!({
+            "amount": 100,
+            "coin": {"$struct": "0x2::coin::Coin", "$fields": {"value": {"$u256": "0x5"}}},
+            "tags": ["a", "b"],
+            "digest": {"$bytes": "0x0102"},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn formats_on_a_single_line() {
+        assert_eq!(
+            transfer().to_string(),
+            r#"{amount: 100, coin: 0x2::coin::Coin {value: 5u256}, tags: ["a", "b"], digest: 0x0102}"#
+        );
+    }
+
+    #[test]
+    fn formats_nested_values_on_indented_lines() {
+        assert_eq!(
+            format!("{:#}", transfer()),
+            concat!(
+                "{\n",
+                "    amount: 100,\n",
+                "    coin: 0x2::coin::Coin {\n",
+                "        value: 5u256,\n",
+                "    },\n",
+                "    tags: [\n",
+                "        \"a\",\n",
+                "        \"b\",\n",
+                "    ],\n",
+                "    digest: 0x0102,\n",
+                "}",
+            )
+        );
+    }
+
+    #[test]
+    fn formats_scalars() {
+        let format = |value| from_json(&value).unwrap().to_string();
+
+        assert_eq!(format(json!(true)), "true");
+        assert_eq!(format(json!(1.0)), "1.0");
+        assert_eq!(format(json!({"$float": "NaN"})), "NaN");
+        assert_eq!(
+            format(json!("a \"quoted\" string")),
+            r#""a \"quoted\" string""#
+        );
+        assert_eq!(format(json!([])), "[]");
+        assert_eq!(format(json!({})), "{}");
+    }
+
+    #[test]
+    fn formats_invalid_values_without_failing() {
+        let value = ValueData {
+            data: None,
+            value: ValueType::List(vec![3]),
+        };
+        let u256 = ValueData {
+            data: None,
+            value: ValueType::U256(vec![0; 33]),
+        };
+
+        assert_eq!(value.to_string(), "[<invalid index 3>]");
+        assert!(u256.to_string().starts_with("<invalid u256 0x00"));
+    }
+}
@@ -0,0 +1,293 @@
+use super::{ValueDataError, MAX_DEPTH};
+use crate::component::guest::types::ValueType;
+use crate::u256::U256;
+use serde_json::{Map, Number, Value};
+
+const U256_KEY: &str = "$u256";
+const BYTES_KEY: &str = "$bytes";
+const FLOAT_KEY: &str = "$float";
+const STRUCT_KEY: &str = "$struct";
+const FIELDS_KEY: &str = "$fields";
+
+pub(super) fn to_json(
+    value: &ValueType,
+    data: &[ValueType],
+    depth: usize,
+) -> Result<Value, ValueDataError> {
+    if depth > MAX_DEPTH {
+        return Err(super::too_deep());
+    }
+
+    let nested = |index: u64| {
+        let value = super::resolve(data, index)?;
+        to_json(value, data, depth + 1)
+    };
+
+    Ok(match value {
+        ValueType::Bool(value) => Value::Bool(*value),
+        ValueType::U64(value) => Value::Number((*value).into()),
+        ValueType::Float(value) => match Number::from_f64(*value) {
+            Some(number) => Value::Number(number),
+            None => tagged(FLOAT_KEY, Value::String(value.to_string())),
+        },
+        ValueType::String(value) => Value::String(value.clone()),
+        ValueType::U256(bytes) => {
+            let value =
+                U256::from_be_slice(bytes).map_err(|e| ValueDataError::Custom(e.to_string()))?;
+            tagged(U256_KEY, Value::String(format!("{value:#x}")))
+        }
+        ValueType::Bytes(bytes) => tagged(BYTES_KEY, Value::String(super::to_hex(bytes))),
+        ValueType::List(indexes) => Value::Array(
+            indexes
+                .iter()
+                .map(|index| nested(*index))
+                .collect::<Result<_, _>>()?,
+        ),
+        ValueType::Map(elems) => Value::Object(
+            elems
+                .iter()
+                .map(|(key, index)| Ok((escape_key(key), nested((*index).into())?)))
+                .collect::<Result<_, ValueDataError>>()?,
+        ),
+        ValueType::Struct((name, fields)) => {
+            let fields = fields
+                .iter()
+                .map(|(key, index)| Ok((key.clone(), nested(*index)?)))
+                .collect::<Result<Map<_, _>, ValueDataError>>()?;
+
+            let mut object = Map::new();
+            object.insert(STRUCT_KEY.to_string(), Value::String(name.clone()));
+            object.insert(FIELDS_KEY.to_string(), Value::Object(fields));
+            Value::Object(object)
+        }
+    })
+}
+
+fn tagged(key: &str, value: Value) -> Value {
+    let mut object = Map::new();
+    object.insert(key.to_string(), value);
+    Value::Object(object)
+}
+
+/// Map keys starting with `$` are escaped with another `$`, so they never look like a tag.
+fn escape_key(key: &str) -> String {
+    if key.starts_with('$') {
+        format!("${key}")
+    } else {
+        key.to_string()
+    }
+}
+
+fn unescape_key(key: &str) -> String {
+    key.strip_prefix("$$")
+        .map(|key| format!("${key}"))
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Appends the value-types of `value` to `arena`, returning the root value-type.
+pub(super) fn from_json(
+    value: &Value,
+    arena: &mut Vec<ValueType>,
+    depth: usize,
+) -> Result<ValueType, ValueDataError> {
+    if depth > MAX_DEPTH {
+        return Err(super::too_deep());
+    }
+
+    Ok(match value {
+        Value::Null => {
+            return Err(ValueDataError::Unsupported(
+                "`null` can not be represented as value-data".to_string(),
+            ))
+        }
+        Value::Bool(value) => ValueType::Bool(*value),
+        Value::Number(number) => match number.as_u64() {
+            Some(value) => ValueType::U64(value),
+            None => ValueType::Float(number.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(value) => ValueType::String(value.clone()),
+        Value::Array(items) => {
+            let base = super::reserve(arena, items.len());
+            for (offset, item) in items.iter().enumerate() {
+                arena[base + offset] = from_json(item, arena, depth + 1)?;
+            }
+
+            ValueType::List((base..base + items.len()).map(|i| i as u64).collect())
+        }
+        Value::Object(object) => {
+            if let Some(value) = from_tagged(object)? {
+                return Ok(value);
+            }
+
+            if let (Some(Value::String(name)), Some(Value::Object(fields)), 2) =
+                (object.get(STRUCT_KEY), object.get(FIELDS_KEY), object.len())
+            {
+                let fields = entries(fields, arena, depth, |key| key.to_string())?
+                    .into_iter()
+                    .map(|(key, index)| (key, index as u64))
+                    .collect();
+
+                return Ok(ValueType::Struct((name.clone(), fields)));
+            }
+
+            let elems = entries(object, arena, depth, unescape_key)?
+                .into_iter()
+                .map(|(key, index)| {
+                    u8::try_from(index)
+                        .map(|index| (key, index))
+                        .map_err(|_| super::IndexOverflow(index).into())
+                })
+                .collect::<Result<_, ValueDataError>>()?;
+
+            ValueType::Map(elems)
+        }
+    })
+}
+
+/// Appends the non-null values of an object to `arena`, returning their keys and indexes.
+fn entries(
+    object: &Map<String, Value>,
+    arena: &mut Vec<ValueType>,
+    depth: usize,
+    key: impl Fn(&str) -> String,
+) -> Result<Vec<(String, usize)>, ValueDataError> {
+    let present: Vec<_> = object
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .collect();
+    let base = super::reserve(arena, present.len());
+    let mut entries = Vec::with_capacity(present.len());
+
+    for (offset, (name, value)) in present.into_iter().enumerate() {
+        arena[base + offset] = from_json(value, arena, depth + 1)?;
+        entries.push((key(name), base + offset));
+    }
+
+    Ok(entries)
+}
+
+/// Reads the single entry objects written for `U256`, `BYTES` and non-finite `FLOAT` values.
+fn from_tagged(object: &Map<String, Value>) -> Result<Option<ValueType>, ValueDataError> {
+    let Some((key, Value::String(value))) = object.iter().next().filter(|_| object.len() == 1)
+    else {
+        return Ok(None);
+    };
+
+    let invalid = |e: &dyn std::fmt::Display| {
+        ValueDataError::Custom(format!("invalid `{key}` value `{value}`: {e}"))
+    };
+
+    Ok(Some(match key.as_str() {
+        U256_KEY => value.parse::<U256>().map_err(|e| invalid(&e))?.into(),
+        BYTES_KEY => ValueType::Bytes(super::from_hex(value).map_err(|e| invalid(&e))?),
+        FLOAT_KEY => ValueType::Float(value.parse().map_err(|e| invalid(&e))?),
+        _ => return Ok(None),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{from_json, to_json};
+    use super::*;
+    use crate::component::guest::types::ValueData;
+    use serde_json::json;
+
+    fn round_trip(value: Value) {
+        let data = from_json(&value).unwrap();
+
+        assert_eq!(to_json(&data).unwrap(), value);
+    }
+
+    #[test]
+    fn round_trips_tagged_values() {
+        round_trip(json!({
+            "amount": 5,
+            "ratio": 0.5,
+            "whole": 1.0,
+            "flag": false,
+            "name": "gm",
+            "value": {"$u256": "0x10000000000000000"},
+            "digest": {"$bytes": "0x01ff"},
+            "empty": {"$bytes": "0x"},
+            "nan": {"$float": "NaN"},
+            "inf": {"$float": "-inf"},
+            "coin": {"$struct": "0x2::coin::Coin", "$fields": {"value": 5, "tags": ["a", []]}},
+            "nested": {"empty": {}, "list": [1, {"$u256": "0x0"}]},
+        }));
+    }
+
+    #[test]
+    fn escapes_map_keys_starting_with_a_dollar() {
+        round_trip(json!({"$$u256": "0x1"}));
+        round_trip(json!({"$$bytes": "0x1", "$$$fields": 1}));
+
+        let data = from_json(&json!({"$$u256": "0x1"})).unwrap();
+        let ValueType::Map(elems) = &data.value else {
+            panic!("expected a map, got {:?}", data.value);
+        };
+        assert_eq!(elems[0].0, "$u256");
+
+        let data = ValueData {
+            data: Some(vec![ValueType::U64(1)]),
+            value: ValueType::Map(vec![("$struct".to_string(), 0)]),
+        };
+        assert_eq!(to_json(&data).unwrap(), json!({"$$struct": 1}));
+    }
+
+    #[test]
+    fn reads_objects_that_only_look_like_tags_as_maps() {
+        let data = from_json(&json!({"$u256": 1, "$struct": "x"})).unwrap();
+
+        assert!(matches!(&data.value, ValueType::Map(elems) if elems.len() == 2));
+        assert_eq!(
+            to_json(&data).unwrap(),
+            json!({"$$u256": 1, "$$struct": "x"})
+        );
+    }
+
+    #[test]
+    fn leaves_out_null_entries() {
+        let data = from_json(&json!({"a": null, "b": 1})).unwrap();
+
+        assert_eq!(to_json(&data).unwrap(), json!({"b": 1}));
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        assert!(matches!(
+            from_json(&json!(null)),
+            Err(ValueDataError::Unsupported(_))
+        ));
+        assert!(matches!(
+            from_json(&json!([1, null])),
+            Err(ValueDataError::Unsupported(_))
+        ));
+        assert!(matches!(
+            from_json(&json!({"$u256": "0xzz"})),
+            Err(ValueDataError::Custom(reason)) if reason.starts_with("invalid `$u256` value `0xzz`")
+        ));
+        assert!(matches!(
+            from_json(&json!({"$bytes": "0x1"})),
+            Err(ValueDataError::Custom(_))
+        ));
+
+        let wide: Map<String, Value> = (0..300).map(|i| (i.to_string(), json!(i))).collect();
+        assert!(matches!(
+            from_json(&Value::Object(wide)),
+            Err(ValueDataError::IndexOverflow(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_indexes() {
+        let data = ValueData {
+            data: Some(Vec::new()),
+            value: ValueType::List(vec![0]),
+        };
+
+        assert!(matches!(
+            to_json(&data),
+            Err(ValueDataError::InvalidIndex(0))
+        ));
+    }
+}
@@ -0,0 +1,600 @@
+use super::view::{display_path, type_name};
+use super::{ValueDataView, ViewError};
+use crate::component::guest::types::{ValueData, ValueType};
+use crate::u256::U256;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+/// The expected shape of a value-data, checked with [`ValueSchema::validate`].
+///
+/// ```ignore
+/// let schema: ValueSchema = ValueSchema::record()
+///     .required("amount", ValueSchema::U256)
+///     .optional("memo", ValueSchema::String)
+///     .into();
+///
+/// if let Err(violations) = schema.validate(&event.contents) {
+///     let reasons: Vec<String> = violations.iter().map(ToString::to_string).collect();
+///     return Err(AgentError::msg(format!("malformed event: {}", reasons.join("; "))));
+/// }
+/// ```
+///
+/// Schemas can also be derived from Rust types with [`HasSchema`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValueSchema {
+    /// Any value.
+    Any,
+    /// A `BOOL`.
+    Bool,
+    /// A `U64`.
+    U64,
+    /// A `U256`, or a `U64`.
+    U256,
+    /// A `FLOAT`, or a `U64` as JSON numbers without a fraction are read as `U64`.
+    Float,
+    /// A `STRING`.
+    String,
+    /// `BYTES`.
+    Bytes,
+    /// A `LIST` whose elements all match the schema. `BYTES` are accepted too when the elements
+    /// are integers.
+    List(Box<ValueSchema>),
+    /// A `MAP` with arbitrary keys, whose values all match the schema.
+    Map(Box<ValueSchema>),
+    /// A `MAP` or `STRUCT` with known fields.
+    Record(RecordSchema),
+}
+
+/// The fields of a [`ValueSchema::Record`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecordSchema {
+    /// The expected `STRUCT` type name, if a `MAP` or any `STRUCT` would not do.
+    pub type_name: Option<String>,
+    pub fields: Vec<FieldSchema>,
+    /// Reports the fields that are not part of the schema.
+    pub deny_unknown_fields: bool,
+}
+
+/// A field of a [`RecordSchema`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldSchema {
+    pub name: String,
+    pub schema: ValueSchema,
+    pub required: bool,
+}
+
+/// A mismatch between a value-data and a [`ValueSchema`], with the path of the offending value.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SchemaViolation {
+    /// The value is not of the expected value-type.
+    #[error("`{}`: expected {expected}, found {found}", display_path(.path))]
+    TypeMismatch {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The `STRUCT` has another type name than the expected one.
+    #[error("`{}`: expected struct `{expected}`, found `{found}`", display_path(.path))]
+    StructMismatch {
+        path: String,
+        expected: String,
+        found: String,
+    },
+    /// A required field is missing.
+    #[error("`{}`: missing required field", display_path(.path))]
+    MissingField { path: String },
+    /// The field is not part of a schema that denies unknown fields.
+    #[error("`{}`: unknown field", display_path(.path))]
+    UnknownField { path: String },
+    /// A container index points outside of the `data` of the value-data.
+    #[error("`{}`: value-data index {index} is out of bounds", display_path(.path))]
+    InvalidIndex { path: String, index: u64 },
+}
+
+impl SchemaViolation {
+    /// Returns the path of the offending value, empty for the root.
+    pub fn path(&self) -> &str {
+        match self {
+            SchemaViolation::TypeMismatch { path, .. }
+            | SchemaViolation::StructMismatch { path, .. }
+            | SchemaViolation::MissingField { path }
+            | SchemaViolation::UnknownField { path }
+            | SchemaViolation::InvalidIndex { path, .. } => path,
+        }
+    }
+}
+
+impl ValueSchema {
+    /// Creates a record schema without fields, to be turned into a `ValueSchema` with `into()`.
+    pub fn record() -> RecordSchema {
+        RecordSchema::default()
+    }
+
+    /// Creates a `LIST` schema.
+    pub fn list(elements: impl Into<ValueSchema>) -> Self {
+        ValueSchema::List(Box::new(elements.into()))
+    }
+
+    /// Creates a `MAP` schema with arbitrary keys.
+    pub fn map(values: impl Into<ValueSchema>) -> Self {
+        ValueSchema::Map(Box::new(values.into()))
+    }
+
+    /// Checks that a value-data matches the schema.
+    ///
+    /// Parameters:
+    /// - `value`: The value-data to check.
+    ///
+    /// Returns:
+    /// Every violation found, rather than only the first one.
+    pub fn validate(&self, value: &ValueData) -> Result<(), Vec<SchemaViolation>> {
+        self.validate_view(&value.view())
+    }
+
+    /// Checks that a value nested in a value-data matches the schema, the violation paths being
+    /// relative to the root of the value-data.
+    pub fn validate_view(&self, view: &ValueDataView) -> Result<(), Vec<SchemaViolation>> {
+        let mut violations = Vec::new();
+        self.check(view, &mut violations);
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    fn check(&self, view: &ValueDataView, violations: &mut Vec<SchemaViolation>) {
+        let value = view.value();
+        let matches = match (self, value) {
+            (ValueSchema::Any, _)
+            | (ValueSchema::Bool, ValueType::Bool(_))
+            | (ValueSchema::U64, ValueType::U64(_))
+            | (ValueSchema::U256, ValueType::U64(_) | ValueType::U256(_))
+            | (ValueSchema::Float, ValueType::Float(_) | ValueType::U64(_))
+            | (ValueSchema::String, ValueType::String(_))
+            | (ValueSchema::Bytes, ValueType::Bytes(_)) => true,
+            (ValueSchema::List(elements), ValueType::Bytes(_)) => elements.accepts_integers(),
+            (ValueSchema::List(elements), ValueType::List(_)) => {
+                for element in collect(view.elements(), violations) {
+                    elements.check(&element, violations);
+                }
+                true
+            }
+            (ValueSchema::Map(values), ValueType::Map(_)) => {
+                for (_, value) in collect(view.entries(), violations) {
+                    values.check(&value, violations);
+                }
+                true
+            }
+            (ValueSchema::Record(record), ValueType::Map(_) | ValueType::Struct(_)) => {
+                record.check(view, violations);
+                true
+            }
+            _ => false,
+        };
+
+        if !matches {
+            violations.push(SchemaViolation::TypeMismatch {
+                path: view.path().to_string(),
+                expected: self.kind(),
+                found: type_name(value),
+            });
+        }
+    }
+
+    fn accepts_integers(&self) -> bool {
+        matches!(
+            self,
+            ValueSchema::Any | ValueSchema::U64 | ValueSchema::U256 | ValueSchema::Float
+        )
+    }
+
+    /// Returns the value-types accepted by the schema, used in violations.
+    fn kind(&self) -> &'static str {
+        match self {
+            ValueSchema::Any => "any value",
+            ValueSchema::Bool => "BOOL",
+            ValueSchema::U64 => "U64",
+            ValueSchema::U256 => "U256",
+            ValueSchema::Float => "FLOAT",
+            ValueSchema::String => "STRING",
+            ValueSchema::Bytes => "BYTES",
+            ValueSchema::List(elements) if elements.accepts_integers() => "LIST or BYTES",
+            ValueSchema::List(_) => "LIST",
+            ValueSchema::Map(_) => "MAP",
+            ValueSchema::Record(RecordSchema {
+                type_name: Some(_), ..
+            }) => "STRUCT",
+            ValueSchema::Record(_) => "MAP or STRUCT",
+        }
+    }
+}
+
+impl RecordSchema {
+    /// Requires a `STRUCT` with the given type name.
+    pub fn type_name(mut self, type_name: impl Into<String>) -> Self {
+        self.type_name = Some(type_name.into());
+        self
+    }
+
+    /// Adds a field that must be present.
+    pub fn required(self, name: impl Into<String>, schema: impl Into<ValueSchema>) -> Self {
+        self.field(name, schema, true)
+    }
+
+    /// Adds a field that is checked only if present.
+    pub fn optional(self, name: impl Into<String>, schema: impl Into<ValueSchema>) -> Self {
+        self.field(name, schema, false)
+    }
+
+    /// Adds a field, replacing a previous field with the same name.
+    pub fn field(
+        mut self,
+        name: impl Into<String>,
+        schema: impl Into<ValueSchema>,
+        required: bool,
+    ) -> Self {
+        let field = FieldSchema {
+            name: name.into(),
+            schema: schema.into(),
+            required,
+        };
+
+        match self.fields.iter_mut().find(|f| f.name == field.name) {
+            Some(existing) => *existing = field,
+            None => self.fields.push(field),
+        }
+        self
+    }
+
+    /// Reports the fields that are not part of the schema.
+    pub fn deny_unknown_fields(mut self) -> Self {
+        self.deny_unknown_fields = true;
+        self
+    }
+
+    fn check(&self, view: &ValueDataView, violations: &mut Vec<SchemaViolation>) {
+        if let Some(expected) = &self.type_name {
+            match view.struct_name() {
+                Some(found) if found == expected => {}
+                Some(found) => violations.push(SchemaViolation::StructMismatch {
+                    path: view.path().to_string(),
+                    expected: expected.clone(),
+                    found: found.to_string(),
+                }),
+                None => violations.push(SchemaViolation::TypeMismatch {
+                    path: view.path().to_string(),
+                    expected: "STRUCT",
+                    found: type_name(view.value()),
+                }),
+            }
+        }
+
+        for field in &self.fields {
+            match view.field(&field.name) {
+                Ok(value) => field.schema.check(&value, violations),
+                Err(ViewError::MissingField { path }) => {
+                    if field.required {
+                        violations.push(SchemaViolation::MissingField { path });
+                    }
+                }
+                Err(error) => violations.extend(violation(error)),
+            }
+        }
+
+        if self.deny_unknown_fields {
+            for (key, value) in collect(view.entries(), violations) {
+                if !self.fields.iter().any(|field| field.name == key) {
+                    violations.push(SchemaViolation::UnknownField {
+                        path: value.path().to_string(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl From<RecordSchema> for ValueSchema {
+    fn from(record: RecordSchema) -> Self {
+        ValueSchema::Record(record)
+    }
+}
+
+/// Returns the items of a navigation, recording the error if it failed.
+fn collect<T>(items: Result<Vec<T>, ViewError>, violations: &mut Vec<SchemaViolation>) -> Vec<T> {
+    items.unwrap_or_else(|error| {
+        violations.extend(violation(error));
+        Vec::new()
+    })
+}
+
+/// Converts the errors a navigation can hit on a value that matched its container schema.
+fn violation(error: ViewError) -> Option<SchemaViolation> {
+    match error {
+        ViewError::InvalidIndex { path, index } => {
+            Some(SchemaViolation::InvalidIndex { path, index })
+        }
+        ViewError::MissingField { path } => Some(SchemaViolation::MissingField { path }),
+        ViewError::TypeMismatch {
+            path,
+            expected,
+            found,
+        } => Some(SchemaViolation::TypeMismatch {
+            path,
+            expected,
+            found,
+        }),
+        ViewError::InvalidPath { .. }
+        | ViewError::IndexOutOfBounds { .. }
+        | ViewError::InvalidValue { .. } => None,
+    }
+}
+
+/// Rust types with a known value-data shape.
+///
+/// Implemented for primitives, strings, collections and [`U256`], and derived for structs with
+/// named fields with `#[derive(HasSchema)]`. The derive follows `#[serde(rename_all = "...")]`
+/// and the `#[serde(rename = "...")]`, `#[serde(default)]`, `#[serde(skip)]` and
+/// `#[value(rename = "...")]` field attributes, so that the schema matches what
+/// [`super::from_value_data`] and [`super::FromValueData`] accept.
+pub trait HasSchema {
+    fn schema() -> ValueSchema;
+
+    /// Whether a field of this type may be missing, as with `Option`.
+    fn is_optional() -> bool {
+        false
+    }
+}
+
+macro_rules! impl_has_schema {
+    ($schema:ident: $($ty:ty),*) => {$(
+        impl HasSchema for $ty {
+            fn schema() -> ValueSchema {
+                ValueSchema::$schema
+            }
+        }
+    )*};
+}
+
+impl_has_schema!(Bool: bool);
+impl_has_schema!(U64: u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+impl_has_schema!(U256: u128, i128, U256);
+impl_has_schema!(Float: f32, f64);
+impl_has_schema!(String: String, str, char);
+impl_has_schema!(Any: ValueData, serde_json::Value);
+
+impl<T: HasSchema + ?Sized> HasSchema for Box<T> {
+    fn schema() -> ValueSchema {
+        T::schema()
+    }
+
+    fn is_optional() -> bool {
+        T::is_optional()
+    }
+}
+
+impl<T: HasSchema> HasSchema for Option<T> {
+    fn schema() -> ValueSchema {
+        T::schema()
+    }
+
+    fn is_optional() -> bool {
+        true
+    }
+}
+
+macro_rules! impl_has_schema_list {
+    ($($ty:ident),*) => {$(
+        impl<T: HasSchema> HasSchema for $ty<T> {
+            fn schema() -> ValueSchema {
+                ValueSchema::list(T::schema())
+            }
+        }
+    )*};
+}
+
+impl_has_schema_list!(Vec, VecDeque, BTreeSet, HashSet);
+
+impl<T: HasSchema> HasSchema for [T] {
+    fn schema() -> ValueSchema {
+        ValueSchema::list(T::schema())
+    }
+}
+
+impl<T: HasSchema, const N: usize> HasSchema for [T; N] {
+    fn schema() -> ValueSchema {
+        ValueSchema::list(T::schema())
+    }
+}
+
+impl<K, V: HasSchema> HasSchema for BTreeMap<K, V> {
+    fn schema() -> ValueSchema {
+        ValueSchema::map(V::schema())
+    }
+}
+
+impl<K, V: HasSchema, S> HasSchema for HashMap<K, V, S> {
+    fn schema() -> ValueSchema {
+        ValueSchema::map(V::schema())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::from_json;
+    use super::*;
+    use serde_json::json;
+
+    fn pool_schema() -> ValueSchema {
+        ValueSchema::record()
+            .type_name("0x2::pool::Pool")
+            .required("fee", ValueSchema::Float)
+            .required("paused", ValueSchema::Bool)
+            .optional("admin", ValueSchema::String)
+            .required(
+                "reserves",
+                ValueSchema::list(
+                    ValueSchema::record()
+                        .required("amount", ValueSchema::U256)
+                        .required("coin", ValueSchema::String)
+                        .deny_unknown_fields(),
+                ),
+            )
+            .required("balances", ValueSchema::map(ValueSchema::U64))
+            .required("digest", ValueSchema::list(ValueSchema::U64))
+            .into()
+    }
+
+    fn violations(schema: &ValueSchema, value: serde_json::Value) -> Vec<SchemaViolation> {
+        schema.validate(&from_json(&value).unwrap()).unwrap_err()
+    }
+
+    #[test]
+    fn accepts_matching_values() {
+        let value = from_json(&json!({"$struct": "0x2::pool::Pool", "$fields": {
+            "fee": 3,
+            "paused": false,
+            "reserves": [
+                {"amount": 1, "coin": "0x2::sui::SUI"},
+                {"amount": {"$u256": "0x2"}, "coin": "0x5::usdc::USDC"},
+            ],
+            "balances": {"0xa": 1},
+            "digest": {"$bytes": "0x0102"},
+        }}))
+        .unwrap();
+
+        assert_eq!(pool_schema().validate(&value), Ok(()));
+        assert_eq!(ValueSchema::Any.validate(&value), Ok(()));
+    }
+
+    #[test]
+    fn collects_every_violation() {
+        let found = violations(
+            &pool_schema(),
+            json!({"$struct": "0x2::pool::Other", "$fields": {
+                "fee": "0.3",
+                "admin": 1,
+                "reserves": [
+                    {"amount": 1, "coin": "0x2::sui::SUI", "extra": true},
+                    {"coin": "0x5::usdc::USDC"},
+                ],
+                "balances": {"0xa": 1, "0xb": -1.5},
+                "digest": "0x0102",
+            }}),
+        );
+
+        assert_eq!(
+            found,
+            [
+                SchemaViolation::StructMismatch {
+                    path: String::new(),
+                    expected: "0x2::pool::Pool".to_string(),
+                    found: "0x2::pool::Other".to_string(),
+                },
+                SchemaViolation::TypeMismatch {
+                    path: "fee".to_string(),
+                    expected: "FLOAT",
+                    found: "STRING",
+                },
+                SchemaViolation::MissingField {
+                    path: "paused".to_string()
+                },
+                SchemaViolation::TypeMismatch {
+                    path: "admin".to_string(),
+                    expected: "STRING",
+                    found: "U64",
+                },
+                SchemaViolation::UnknownField {
+                    path: "reserves[0].extra".to_string()
+                },
+                SchemaViolation::MissingField {
+                    path: "reserves[1].amount".to_string()
+                },
+                SchemaViolation::TypeMismatch {
+                    path: "balances.0xb".to_string(),
+                    expected: "U64",
+                    found: "FLOAT",
+                },
+                SchemaViolation::TypeMismatch {
+                    path: "digest".to_string(),
+                    expected: "LIST or BYTES",
+                    found: "STRING",
+                },
+            ]
+        );
+
+        let paths: Vec<_> = found.iter().map(SchemaViolation::path).collect();
+        assert_eq!(
+            paths,
+            [
+                "",
+                "fee",
+                "paused",
+                "admin",
+                "reserves[0].extra",
+                "reserves[1].amount",
+                "balances.0xb",
+                "digest"
+            ]
+        );
+    }
+
+    #[test]
+    fn requires_a_struct_when_a_type_name_is_expected() {
+        assert_eq!(
+            violations(&pool_schema(), json!([])),
+            [SchemaViolation::TypeMismatch {
+                path: String::new(),
+                expected: "STRUCT",
+                found: "LIST",
+            }]
+        );
+        assert_eq!(
+            violations(&pool_schema(), json!({"fee": 1.5}))[0],
+            SchemaViolation::TypeMismatch {
+                path: String::new(),
+                expected: "STRUCT",
+                found: "MAP",
+            }
+        );
+    }
+
+    #[test]
+    fn reports_invalid_indexes() {
+        let value = ValueData {
+            data: Some(vec![ValueType::U64(1)]),
+            value: ValueType::List(vec![0, 7]),
+        };
+        let found = ValueSchema::list(ValueSchema::U64)
+            .validate(&value)
+            .unwrap_err();
+
+        assert_eq!(
+            found,
+            [SchemaViolation::InvalidIndex {
+                path: "[1]".to_string(),
+                index: 7
+            }]
+        );
+        assert_eq!(found[0].path(), "[1]");
+        assert_eq!(
+            found[0].to_string(),
+            "`[1]`: value-data index 7 is out of bounds"
+        );
+    }
+
+    #[test]
+    fn validates_views_with_paths_from_the_root() {
+        let value = from_json(&json!({"pool": {"fee": "high"}})).unwrap();
+        let pool = value.view().get("pool").unwrap();
+        let found = ValueSchema::from(ValueSchema::record().required("fee", ValueSchema::Float))
+            .validate_view(&pool)
+            .unwrap_err();
+
+        assert_eq!(found[0].path(), "pool.fee");
+        assert_eq!(
+            found[0].to_string(),
+            "`pool.fee`: expected FLOAT, found STRING"
+        );
+    }
+}
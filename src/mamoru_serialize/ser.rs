@@ -0,0 +1,717 @@
+use super::{embed, reserve, ValueDataError, U256_TOKEN};
+use crate::component::guest::types::{ValueData, ValueType};
+use serde::ser::{self, Impossible, Serialize};
+
+/// Serializes a Rust value into a value-data, `None` standing for a missing optional value.
+pub(super) struct Serializer;
+
+type Output = Option<ValueData>;
+
+fn scalar(value: ValueType) -> Result<Output, ValueDataError> {
+    Ok(Some(ValueData { data: None, value }))
+}
+
+fn unsupported(what: &str) -> ValueDataError {
+    ValueDataError::Unsupported(format!("{what} can not be represented as value-data"))
+}
+
+/// Serializes a value that must be present, e.g. a list item.
+fn required<T: ?Sized + Serialize>(value: &T) -> Result<ValueData, ValueDataError> {
+    value
+        .serialize(Serializer)?
+        .ok_or_else(|| unsupported("`None` outside of a struct or map"))
+}
+
+/// Packs `children` after the end of a fresh arena, returning it with the index of the first one.
+fn pack(children: Vec<ValueData>) -> Result<(Vec<ValueType>, usize), ValueDataError> {
+    let mut arena = Vec::new();
+    let base = reserve(&mut arena, children.len());
+    for (offset, child) in children.iter().enumerate() {
+        embed(&mut arena, base + offset, child)?;
+    }
+
+    Ok((arena, base))
+}
+
+fn list(items: Vec<ValueData>) -> Result<ValueData, ValueDataError> {
+    let len = items.len() as u64;
+    let (data, base) = pack(items)?;
+    let base = base as u64;
+
+    Ok(ValueData {
+        data: Some(data),
+        value: ValueType::List((base..base + len).collect()),
+    })
+}
+
+fn map(entries: Vec<(String, ValueData)>) -> Result<ValueData, ValueDataError> {
+    let (keys, values): (Vec<_>, Vec<_>) = entries.into_iter().unzip();
+    let (data, base) = pack(values)?;
+    let elems = keys
+        .into_iter()
+        .enumerate()
+        .map(|(offset, key)| {
+            let index = base + offset;
+            u8::try_from(index)
+                .map(|index| (key, index))
+                .map_err(|_| super::IndexOverflow(index).into())
+        })
+        .collect::<Result<_, ValueDataError>>()?;
+
+    Ok(ValueData {
+        data: Some(data),
+        value: ValueType::Map(elems),
+    })
+}
+
+fn structure(name: &str, fields: Vec<(String, ValueData)>) -> Result<ValueData, ValueDataError> {
+    let (keys, values): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+    let (data, base) = pack(values)?;
+    let elems = keys
+        .into_iter()
+        .enumerate()
+        .map(|(offset, key)| (key, (base + offset) as u64))
+        .collect();
+
+    Ok(ValueData {
+        data: Some(data),
+        value: ValueType::Struct((name.to_string(), elems)),
+    })
+}
+
+/// Wraps the content of an enum variant in a single entry `MAP`, as serde does for JSON.
+fn variant(name: &str, content: ValueData) -> Result<Output, ValueDataError> {
+    map(vec![(name.to_string(), content)]).map(Some)
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Output;
+    type Error = ValueDataError;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeStruct;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Output, ValueDataError> {
+        scalar(ValueType::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Output, ValueDataError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Output, ValueDataError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Output, ValueDataError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Output, ValueDataError> {
+        let v = u64::try_from(v).map_err(|_| unsupported(&format!("negative integer {v}")))?;
+        self.serialize_u64(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Output, ValueDataError> {
+        let v = u128::try_from(v).map_err(|_| unsupported(&format!("negative integer {v}")))?;
+        self.serialize_u128(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Output, ValueDataError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Output, ValueDataError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Output, ValueDataError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Output, ValueDataError> {
+        scalar(ValueType::U64(v))
+    }
+
+    /// Integers that do not fit in `U64` become a `U256`.
+    fn serialize_u128(self, v: u128) -> Result<Output, ValueDataError> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            Err(_) => scalar(crate::u256::U256::from(v).into()),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Output, ValueDataError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Output, ValueDataError> {
+        scalar(ValueType::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Output, ValueDataError> {
+        scalar(ValueType::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Output, ValueDataError> {
+        scalar(ValueType::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Output, ValueDataError> {
+        scalar(ValueType::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Output, ValueDataError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Output, ValueDataError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Output, ValueDataError> {
+        Err(unsupported("`()`"))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Output, ValueDataError> {
+        structure(name, Vec::new()).map(Some)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Output, ValueDataError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Output, ValueDataError> {
+        let inner = value.serialize(self)?;
+
+        if name == U256_TOKEN {
+            return match inner {
+                Some(ValueData {
+                    value: ValueType::Bytes(bytes),
+                    ..
+                }) => scalar(ValueType::U256(bytes)),
+                _ => Err(ValueDataError::Custom("malformed U256".to_string())),
+            };
+        }
+
+        Ok(inner)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Output, ValueDataError> {
+        self::variant(variant, required(value)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, ValueDataError> {
+        Ok(SerializeList {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, ValueDataError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, ValueDataError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, ValueDataError> {
+        Ok(SerializeList {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap, ValueDataError> {
+        Ok(SerializeMap {
+            key: None,
+            entries: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<SerializeStruct, ValueDataError> {
+        Ok(SerializeStruct {
+            name,
+            variant: false,
+            fields: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeStruct, ValueDataError> {
+        Ok(SerializeStruct {
+            name: variant,
+            variant: true,
+            fields: Vec::with_capacity(len),
+        })
+    }
+}
+
+/// Collects the items of a sequence, a tuple or a tuple variant into a `LIST`.
+pub(super) struct SerializeList {
+    variant: Option<&'static str>,
+    items: Vec<ValueData>,
+}
+
+impl SerializeList {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ValueDataError> {
+        self.items.push(required(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Output, ValueDataError> {
+        let list = list(self.items)?;
+
+        match self.variant {
+            Some(variant) => self::variant(variant, list),
+            None => Ok(Some(list)),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Output;
+    type Error = ValueDataError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Output, ValueDataError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Output;
+    type Error = ValueDataError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Output, ValueDataError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Output;
+    type Error = ValueDataError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Output, ValueDataError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Output;
+    type Error = ValueDataError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Output, ValueDataError> {
+        self.finish()
+    }
+}
+
+/// Collects the entries of a map into a `MAP`, skipping `None` values.
+pub(super) struct SerializeMap {
+    key: Option<String>,
+    entries: Vec<(String, ValueData)>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Output;
+    type Error = ValueDataError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        self.key = Some(key.serialize(MapKeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ValueDataError::Custom("map value without a key".to_string()))?;
+
+        if let Some(value) = value.serialize(Serializer)? {
+            self.entries.push((key, value));
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Output, ValueDataError> {
+        map(self.entries).map(Some)
+    }
+}
+
+/// Collects the fields of a struct or a struct variant into a `STRUCT`, skipping `None` values.
+pub(super) struct SerializeStruct {
+    name: &'static str,
+    variant: bool,
+    fields: Vec<(String, ValueData)>,
+}
+
+impl SerializeStruct {
+    fn push<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueDataError> {
+        if let Some(value) = value.serialize(Serializer)? {
+            self.fields.push((key.to_string(), value));
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Output, ValueDataError> {
+        let value = structure(self.name, self.fields)?;
+
+        if self.variant {
+            variant(self.name, value)
+        } else {
+            Ok(Some(value))
+        }
+    }
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Ok = Output;
+    type Error = ValueDataError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Output, ValueDataError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeStruct {
+    type Ok = Output;
+    type Error = ValueDataError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Output, ValueDataError> {
+        self.finish()
+    }
+}
+
+/// Serializes map keys, which must be strings, chars, integers or unit variants.
+struct MapKeySerializer;
+
+fn key_error() -> ValueDataError {
+    unsupported("a map key that is not a string or an integer")
+}
+
+macro_rules! serialize_key_to_string {
+    ($($method:ident($ty:ty)),*) => {$(
+        fn $method(self, v: $ty) -> Result<String, ValueDataError> {
+            Ok(v.to_string())
+        }
+    )*};
+}
+
+macro_rules! serialize_key_unsupported {
+    ($($method:ident($($ty:ty)?)),*) => {$(
+        fn $method(self, $(_: $ty)?) -> Result<String, ValueDataError> {
+            Err(key_error())
+        }
+    )*};
+}
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = ValueDataError;
+    type SerializeSeq = Impossible<String, ValueDataError>;
+    type SerializeTuple = Impossible<String, ValueDataError>;
+    type SerializeTupleStruct = Impossible<String, ValueDataError>;
+    type SerializeTupleVariant = Impossible<String, ValueDataError>;
+    type SerializeMap = Impossible<String, ValueDataError>;
+    type SerializeStruct = Impossible<String, ValueDataError>;
+    type SerializeStructVariant = Impossible<String, ValueDataError>;
+
+    serialize_key_to_string!(
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_char(char),
+        serialize_str(&str)
+    );
+
+    serialize_key_unsupported!(
+        serialize_bool(bool),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit()
+    );
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<String, ValueDataError> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<String, ValueDataError> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, ValueDataError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, ValueDataError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<String, ValueDataError> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, ValueDataError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, ValueDataError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, ValueDataError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, ValueDataError> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, ValueDataError> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, ValueDataError> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, ValueDataError> {
+        Err(key_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{to_json, to_value_data};
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    /// Bytes serialized as `BYTES` rather than as a sequence.
+    struct Raw(&'static [u8]);
+
+    impl Serialize for Raw {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    #[derive(Serialize)]
+    enum Kind {
+        Mint,
+        Burn(u64),
+        Split(u64, u64),
+        Swap { pool: &'static str },
+    }
+
+    #[derive(Serialize)]
+    struct Transfer {
+        sender: &'static str,
+        amount: u8,
+        ratio: f32,
+        big: u128,
+        memo: Option<&'static str>,
+        tags: Vec<char>,
+        balances: BTreeMap<u32, u64>,
+        raw: Raw,
+        kinds: (Kind, Kind, Kind, Kind),
+    }
+
+    fn json<T: Serialize>(value: &T) -> String {
+        to_json(&to_value_data(value).unwrap()).unwrap().to_string()
+    }
+
+    #[test]
+    fn serializes_each_kind_of_value() {
+        let transfer = Transfer {
+            sender: "0xa",
+            amount: 5,
+            ratio: 0.5,
+            big: u128::from(u64::MAX) + 1,
+            memo: None,
+            tags: vec!['a', 'b'],
+            balances: BTreeMap::from([(2, 20), (1, 10)]),
+            raw: Raw(&[0xab]),
+            kinds: (
+                Kind::Mint,
+                Kind::Burn(1),
+                Kind::Split(2, 3),
+                Kind::Swap { pool: "0xb" },
+            ),
+        };
+
+        assert_eq!(
+            json(&transfer),
+            concat!(
+                r#"{"$struct":"Transfer","$fields":{"sender":"0xa","amount":5,"ratio":0.5,"#,
+                r#""big":{"$u256":"0x10000000000000000"},"tags":["a","b"],"#,
+                r#""balances":{"1":10,"2":20},"raw":{"$bytes":"0xab"},"kinds":["Mint","#,
+                r#"{"Burn":1},{"Split":[2,3]},"#,
+                r#"{"Swap":{"$struct":"Swap","$fields":{"pool":"0xb"}}}]}}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn serializes_structs_as_struct_value_types() {
+        let value = to_value_data(&Kind::Swap { pool: "0xb" }).unwrap();
+        let swap = value.view().field("Swap").unwrap();
+
+        assert_eq!(swap.struct_name(), Some("Swap"));
+        assert!(matches!(value.value, ValueType::Map(_)));
+        assert!(matches!(swap.value(), ValueType::Struct(_)));
+    }
+
+    #[test]
+    fn rejects_values_without_a_value_data_representation() {
+        let unsupported = |value: Result<ValueData, ValueDataError>| match value {
+            Err(ValueDataError::Unsupported(reason)) => reason,
+            other => panic!("expected an unsupported value, got {other:?}"),
+        };
+
+        assert_eq!(
+            unsupported(to_value_data(&-1i32)),
+            "negative integer -1 can not be represented as value-data"
+        );
+        assert_eq!(
+            unsupported(to_value_data(&-1i128)),
+            "negative integer -1 can not be represented as value-data"
+        );
+        assert_eq!(
+            unsupported(to_value_data(&())),
+            "`()` can not be represented as value-data"
+        );
+        assert!(unsupported(to_value_data(&None::<u64>)).contains("`None`"));
+        assert!(unsupported(to_value_data(&vec![Some(1u64), None])).contains("`None`"));
+        assert!(matches!(
+            to_value_data(&BTreeMap::from([((1u64, 2u64), 3u64)])),
+            Err(ValueDataError::Unsupported(_))
+        ));
+        assert!(matches!(
+            to_value_data(&(0..300u64).map(|i| (i, i)).collect::<BTreeMap<_, _>>()),
+            Err(ValueDataError::IndexOverflow(_))
+        ));
+    }
+}
@@ -0,0 +1,601 @@
+use super::{de, IndexOverflow, ValueDataError, MAX_DEPTH};
+use crate::component::guest::types::{ValueData, ValueType};
+use crate::u256::U256;
+use serde::de::DeserializeOwned;
+
+/// Errors returned when navigating a [`ValueDataView`], with the path of the failing segment.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ViewError {
+    /// The path can not be parsed.
+    #[error("invalid path `{path}`: {reason}")]
+    InvalidPath { path: String, reason: String },
+    /// The `MAP` or `STRUCT` has no such field.
+    #[error("`{}`: no such field", display_path(.path))]
+    MissingField { path: String },
+    /// The `LIST` is shorter than the index.
+    #[error("`{}`: index out of bounds of a list of {len} items", display_path(.path))]
+    IndexOutOfBounds { path: String, len: usize },
+    /// The value is not of the expected value-type.
+    #[error("`{}`: expected {expected}, found {found}", display_path(.path))]
+    TypeMismatch {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// A container index points outside of the `data` of the value-data.
+    #[error("`{}`: value-data index {index} is out of bounds", display_path(.path))]
+    InvalidIndex { path: String, index: u64 },
+    /// The value has the expected value-type but can not be converted, e.g. an unknown enum
+    /// variant or an integer that does not fit.
+    #[error("`{}`: {reason}", display_path(.path))]
+    InvalidValue { path: String, reason: String },
+}
+
+pub(super) fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "<root>"
+    } else {
+        path
+    }
+}
+
+/// A segment of a path such as `pool.reserves[1].amount`.
+enum Segment<'p> {
+    Field(&'p str),
+    Index(usize),
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment<'_>>, ViewError> {
+    let invalid = |reason: &str| ViewError::InvalidPath {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let mut segments = Vec::new();
+    if path.is_empty() {
+        return Ok(segments);
+    }
+
+    for (position, part) in path.split('.').enumerate() {
+        let (name, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+
+        if name.is_empty() && (position > 0 || rest.is_empty()) {
+            return Err(invalid("empty field name"));
+        }
+        if !name.is_empty() {
+            segments.push(Segment::Field(name));
+        }
+
+        while !rest.is_empty() {
+            let end = rest.find(']').ok_or_else(|| invalid("unclosed `[`"))?;
+            let index = rest[1..end]
+                .parse()
+                .map_err(|_| invalid("list indexes must be unsigned integers"))?;
+
+            segments.push(Segment::Index(index));
+            rest = &rest[end + 1..];
+            if !rest.is_empty() && !rest.starts_with('[') {
+                return Err(invalid("expected `.` or `[` after `]`"));
+            }
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Returns the name of a value-type used in error messages.
+pub(crate) fn type_name(value: &ValueType) -> &'static str {
+    match value {
+        ValueType::Bool(_) => "BOOL",
+        ValueType::U64(_) => "U64",
+        ValueType::U256(_) => "U256",
+        ValueType::Float(_) => "FLOAT",
+        ValueType::String(_) => "STRING",
+        ValueType::Bytes(_) => "BYTES",
+        ValueType::List(_) => "LIST",
+        ValueType::Map(_) => "MAP",
+        ValueType::Struct(_) => "STRUCT",
+    }
+}
+
+/// A read-only view of a value nested in a value-data, resolving `MAP`, `LIST` and `STRUCT`
+/// indexes for you.
+///
+/// ```ignore
+/// let contents = event.contents.view();
+/// let amount = contents.get("pool.reserves[1].amount")?.as_u256()?;
+///
+/// for (key, value) in contents.get("balances")?.entries()? {
+///     log::info!("{key}: {}", value.as_u64()?);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ValueDataView<'a> {
+    value: &'a ValueType,
+    data: &'a [ValueType],
+    path: String,
+}
+
+impl ValueData {
+    /// Returns a view of the root of the value-data.
+    pub fn view(&self) -> ValueDataView<'_> {
+        ValueDataView::new(self)
+    }
+}
+
+impl<'a> ValueDataView<'a> {
+    /// Creates a view of the root of `value`.
+    pub fn new(value: &'a ValueData) -> Self {
+        ValueDataView {
+            value: &value.value,
+            data: value.data.as_deref().unwrap_or_default(),
+            path: String::new(),
+        }
+    }
+
+    /// Returns the path of the viewed value from the root, empty for the root itself.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the viewed value-type, whose indexes point into the root `data`.
+    pub fn value(&self) -> &'a ValueType {
+        self.value
+    }
+
+    /// Navigates to a nested value.
+    ///
+    /// Parameters:
+    /// - `path`: Field names separated by `.`, each optionally followed by `[index]` list accesses,
+    ///   e.g. `pool.reserves[1].amount`. Fields are looked up in both `MAP`s and `STRUCT`s.
+    ///
+    /// Returns:
+    /// The view of the nested value, or a `ViewError` naming the segment that could not be found.
+    pub fn get(&self, path: &str) -> Result<ValueDataView<'a>, ViewError> {
+        let mut view = self.clone();
+
+        for segment in parse_path(path)? {
+            view = match segment {
+                Segment::Field(name) => view.field(name)?,
+                Segment::Index(index) => view.index(index)?,
+            };
+        }
+
+        Ok(view)
+    }
+
+    /// Navigates to a field of a `MAP` or `STRUCT`.
+    pub fn field(&self, name: &str) -> Result<ValueDataView<'a>, ViewError> {
+        let index = match self.value {
+            ValueType::Map(elems) => elems
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, index)| u64::from(*index)),
+            ValueType::Struct((_, fields)) => fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, index)| *index),
+            other => return Err(self.mismatch("MAP or STRUCT", other)),
+        };
+
+        match index {
+            Some(index) => self.field_at(name, index),
+            None => Err(ViewError::MissingField {
+                path: self.field_path(name),
+            }),
+        }
+    }
+
+    /// Navigates to an element of a `LIST`.
+    pub fn index(&self, index: usize) -> Result<ValueDataView<'a>, ViewError> {
+        let path = format!("{}[{index}]", self.path);
+        let indexes = self.list_indexes()?;
+
+        match indexes.get(index) {
+            Some(nested) => self.nested(path, *nested),
+            None => Err(ViewError::IndexOutOfBounds {
+                path,
+                len: indexes.len(),
+            }),
+        }
+    }
+
+    /// Returns the type name of a `STRUCT`, `None` for other value-types.
+    pub fn struct_name(&self) -> Option<&'a str> {
+        match self.value {
+            ValueType::Struct((name, _)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries of a `MAP` or the fields of a `STRUCT`, in order.
+    pub fn entries(&self) -> Result<Vec<(&'a str, ValueDataView<'a>)>, ViewError> {
+        let entries: Vec<(&'a str, u64)> = match self.value {
+            ValueType::Map(elems) => elems
+                .iter()
+                .map(|(key, index)| (key.as_str(), u64::from(*index)))
+                .collect(),
+            ValueType::Struct((_, fields)) => fields
+                .iter()
+                .map(|(key, index)| (key.as_str(), *index))
+                .collect(),
+            other => return Err(self.mismatch("MAP or STRUCT", other)),
+        };
+
+        entries
+            .into_iter()
+            .map(|(key, index)| Ok((key, self.field_at(key, index)?)))
+            .collect()
+    }
+
+    /// Returns the elements of a `LIST`, in order.
+    pub fn elements(&self) -> Result<Vec<ValueDataView<'a>>, ViewError> {
+        self.list_indexes()?
+            .iter()
+            .enumerate()
+            .map(|(position, index)| self.nested(format!("{}[{position}]", self.path), *index))
+            .collect()
+    }
+
+    /// Returns the number of entries of a `MAP`, fields of a `STRUCT` or elements of a `LIST`.
+    pub fn len(&self) -> Result<usize, ViewError> {
+        match self.value {
+            ValueType::Map(elems) => Ok(elems.len()),
+            ValueType::Struct((_, fields)) => Ok(fields.len()),
+            ValueType::List(indexes) => Ok(indexes.len()),
+            other => Err(self.mismatch("MAP, STRUCT or LIST", other)),
+        }
+    }
+
+    /// Returns `true` if the container has no entries, fields or elements.
+    pub fn is_empty(&self) -> Result<bool, ViewError> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Reads a `BOOL`.
+    pub fn as_bool(&self) -> Result<bool, ViewError> {
+        match self.value {
+            ValueType::Bool(value) => Ok(*value),
+            other => Err(self.mismatch("BOOL", other)),
+        }
+    }
+
+    /// Reads a `U64`, or a `U256` that fits in 64 bits.
+    pub fn as_u64(&self) -> Result<u64, ViewError> {
+        match self.value {
+            ValueType::U64(value) => Ok(*value),
+            ValueType::U256(_) => self.as_u256().and_then(|value| {
+                u64::try_from(value).map_err(|_| self.mismatch("U64", self.value))
+            }),
+            other => Err(self.mismatch("U64", other)),
+        }
+    }
+
+    /// Reads a `U256` or a `U64`.
+    pub fn as_u256(&self) -> Result<U256, ViewError> {
+        U256::try_from(self.value).map_err(|_| self.mismatch("U256", self.value))
+    }
+
+    /// Reads a `FLOAT`.
+    pub fn as_f64(&self) -> Result<f64, ViewError> {
+        match self.value {
+            ValueType::Float(value) => Ok(*value),
+            other => Err(self.mismatch("FLOAT", other)),
+        }
+    }
+
+    /// Reads a `STRING`.
+    pub fn as_str(&self) -> Result<&'a str, ViewError> {
+        match self.value {
+            ValueType::String(value) => Ok(value),
+            other => Err(self.mismatch("STRING", other)),
+        }
+    }
+
+    /// Reads `BYTES`.
+    pub fn as_bytes(&self) -> Result<&'a [u8], ViewError> {
+        match self.value {
+            ValueType::Bytes(value) => Ok(value),
+            other => Err(self.mismatch("BYTES", other)),
+        }
+    }
+
+    /// Deserializes the viewed value into a Rust type, see [`super::from_value_data`].
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, ValueDataError> {
+        T::deserialize(de::Deserializer {
+            value: self.value,
+            data: self.data,
+            depth: 0,
+        })
+    }
+
+    /// Copies the viewed value and the values nested in it into a standalone value-data.
+    pub fn to_value_data(&self) -> Result<ValueData, ValueDataError> {
+        let mut arena = Vec::new();
+        let value = copy(self.value, self.data, &mut arena, 0)?;
+
+        Ok(ValueData {
+            data: Some(arena),
+            value,
+        })
+    }
+
+    fn list_indexes(&self) -> Result<&'a [u64], ViewError> {
+        match self.value {
+            ValueType::List(indexes) => Ok(indexes),
+            other => Err(self.mismatch("LIST", other)),
+        }
+    }
+
+    fn field_path(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{key}", self.path)
+        }
+    }
+
+    fn field_at(&self, key: &str, index: u64) -> Result<ValueDataView<'a>, ViewError> {
+        self.nested(self.field_path(key), index)
+    }
+
+    fn nested(&self, path: String, index: u64) -> Result<ValueDataView<'a>, ViewError> {
+        match super::resolve(self.data, index) {
+            Ok(value) => Ok(ValueDataView {
+                value,
+                data: self.data,
+                path,
+            }),
+            Err(_) => Err(ViewError::InvalidIndex { path, index }),
+        }
+    }
+
+    fn mismatch(&self, expected: &'static str, found: &ValueType) -> ViewError {
+        ViewError::TypeMismatch {
+            path: self.path.clone(),
+            expected,
+            found: type_name(found),
+        }
+    }
+}
+
+/// Copies `value` and the values nested in it from `data` to the end of `arena`.
+fn copy(
+    value: &ValueType,
+    data: &[ValueType],
+    arena: &mut Vec<ValueType>,
+    depth: usize,
+) -> Result<ValueType, ValueDataError> {
+    if depth > MAX_DEPTH {
+        return Err(super::too_deep());
+    }
+
+    let copy_all = |indexes: Vec<u64>, arena: &mut Vec<ValueType>| {
+        let base = super::reserve(arena, indexes.len());
+        for (offset, index) in indexes.into_iter().enumerate() {
+            arena[base + offset] = copy(super::resolve(data, index)?, data, arena, depth + 1)?;
+        }
+        Ok::<_, ValueDataError>(base)
+    };
+
+    Ok(match value {
+        ValueType::List(indexes) => {
+            let base = copy_all(indexes.clone(), arena)? as u64;
+            ValueType::List((base..base + indexes.len() as u64).collect())
+        }
+        ValueType::Map(elems) => {
+            let base = copy_all(
+                elems.iter().map(|(_, index)| u64::from(*index)).collect(),
+                arena,
+            )?;
+            let elems = elems
+                .iter()
+                .enumerate()
+                .map(|(offset, (key, _))| {
+                    let index = base + offset;
+                    u8::try_from(index)
+                        .map(|index| (key.clone(), index))
+                        .map_err(|_| IndexOverflow(index).into())
+                })
+                .collect::<Result<_, ValueDataError>>()?;
+            ValueType::Map(elems)
+        }
+        ValueType::Struct((name, fields)) => {
+            let base = copy_all(fields.iter().map(|(_, index)| *index).collect(), arena)?;
+            let fields = fields
+                .iter()
+                .enumerate()
+                .map(|(offset, (key, _))| (key.clone(), (base + offset) as u64))
+                .collect();
+            ValueType::Struct((name.clone(), fields))
+        }
+        other => other.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::from_json;
+    use super::*;
+    use serde_json::json;
+
+    fn pool() -> ValueData {
+        from_json(&json!({
+            "pool": {"$struct": "0x2::pool::Pool", "$fields": {
+                "reserves": [
+                    {"amount": 1, "coin": "0x2::sui::SUI"},
+                    {"amount": {"$u256": "0x2"}, "coin": "0x5::usdc::USDC"},
+                ],
+                "matrix": [[1, 2], [3]],
+                "paused": false,
+            }},
+            "fee": 0.3,
+            "digest": {"$bytes": "0x01"},
+        }))
+        .unwrap()
+    }
+
+    fn invalid_path(reason: &str, path: &str) -> ViewError {
+        ViewError::InvalidPath {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn navigates_paths() {
+        let pool = pool();
+        let root = pool.view();
+
+        let amount = root.get("pool.reserves[1].amount").unwrap();
+        assert_eq!(amount.path(), "pool.reserves[1].amount");
+        assert_eq!(amount.as_u64().unwrap(), 2);
+
+        let matrix = root.get("pool.matrix[0][1]").unwrap();
+        assert_eq!(matrix.path(), "pool.matrix[0][1]");
+        assert_eq!(matrix.as_u64().unwrap(), 2);
+
+        let reserves = root.get("pool.reserves").unwrap();
+        assert_eq!(
+            reserves.get("[0].coin").unwrap().as_str().unwrap(),
+            "0x2::sui::SUI"
+        );
+        assert_eq!(root.get("").unwrap().path(), "");
+        assert_eq!(root.get("fee").unwrap().as_f64().unwrap(), 0.3);
+        assert_eq!(root.get("digest").unwrap().as_bytes().unwrap(), [1]);
+        assert!(!root.get("pool.paused").unwrap().as_bool().unwrap());
+        assert_eq!(
+            root.get("pool").unwrap().struct_name(),
+            Some("0x2::pool::Pool")
+        );
+    }
+
+    #[test]
+    fn lists_entries_and_elements() {
+        let pool = pool();
+        let reserves = pool.view().get("pool.reserves").unwrap();
+
+        assert_eq!(reserves.len().unwrap(), 2);
+        let elements = reserves.elements().unwrap();
+        assert_eq!(elements[1].path(), "pool.reserves[1]");
+
+        let entries = elements[1].entries().unwrap();
+        let keys: Vec<_> = entries.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, ["amount", "coin"]);
+        assert_eq!(entries[1].1.path(), "pool.reserves[1].coin");
+        assert!(pool.view().get("pool.matrix[1]").unwrap().len().is_ok());
+    }
+
+    #[test]
+    fn reports_missing_fields_and_indexes() {
+        let pool = pool();
+        let root = pool.view();
+
+        assert_eq!(
+            root.get("pool.reserves[1].price").unwrap_err(),
+            ViewError::MissingField {
+                path: "pool.reserves[1].price".to_string()
+            }
+        );
+        assert_eq!(
+            root.get("pool.reserves[2].amount").unwrap_err(),
+            ViewError::IndexOutOfBounds {
+                path: "pool.reserves[2]".to_string(),
+                len: 2
+            }
+        );
+    }
+
+    #[test]
+    fn reports_type_mismatches() {
+        let pool = pool();
+        let root = pool.view();
+
+        assert_eq!(
+            root.get("fee.value").unwrap_err(),
+            ViewError::TypeMismatch {
+                path: "fee".to_string(),
+                expected: "MAP or STRUCT",
+                found: "FLOAT",
+            }
+        );
+        assert_eq!(
+            root.get("pool[0]").unwrap_err(),
+            ViewError::TypeMismatch {
+                path: "pool".to_string(),
+                expected: "LIST",
+                found: "STRUCT",
+            }
+        );
+        assert_eq!(
+            root.get("pool.reserves[0].coin")
+                .unwrap()
+                .as_u64()
+                .unwrap_err()
+                .to_string(),
+            "`pool.reserves[0].coin`: expected U64, found STRING"
+        );
+        assert_eq!(
+            root.as_str().unwrap_err().to_string(),
+            "`<root>`: expected STRING, found MAP"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        let pool = pool();
+        let root = pool.view();
+
+        for (path, reason) in [
+            (".pool", "empty field name"),
+            ("pool.", "empty field name"),
+            ("pool..reserves", "empty field name"),
+            ("pool.[0]", "empty field name"),
+            ("pool.reserves[0", "unclosed `[`"),
+            (
+                "pool.reserves[one]",
+                "list indexes must be unsigned integers",
+            ),
+            (
+                "pool.reserves[-1]",
+                "list indexes must be unsigned integers",
+            ),
+            ("pool.reserves[]", "list indexes must be unsigned integers"),
+            ("pool.reserves[0]amount", "expected `.` or `[` after `]`"),
+        ] {
+            assert_eq!(root.get(path).unwrap_err(), invalid_path(reason, path));
+        }
+    }
+
+    #[test]
+    fn reports_invalid_indexes() {
+        let value = ValueData {
+            data: Some(Vec::new()),
+            value: ValueType::Struct(("Pool".to_string(), vec![("fee".to_string(), 4)])),
+        };
+
+        assert_eq!(
+            value.view().get("fee").unwrap_err(),
+            ViewError::InvalidIndex {
+                path: "fee".to_string(),
+                index: 4
+            }
+        );
+    }
+
+    #[test]
+    fn copies_nested_values() {
+        let pool = pool();
+        let reserve = pool
+            .view()
+            .get("pool.reserves[1]")
+            .unwrap()
+            .to_value_data()
+            .unwrap();
+
+        assert_eq!(
+            super::super::to_json(&reserve).unwrap(),
+            json!({"amount": {"$u256": "0x2"}, "coin": "0x5::usdc::USDC"})
+        );
+    }
+}
@@ -1,65 +1,675 @@
 use crate::component::guest::mamoru_storage;
+use crate::component::guest::types::{ValueData, ValueType};
+use crate::mamoru_serialize::{type_name, IntoValueData};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Duration;
 
+pub mod collections;
+
+/// TTL given to the host for values that never expire, as documented on `mamoru-storage.set`
+/// in `wit/components.wit` along with the unit of TTLs, seconds.
+const PERSISTENT_TTL: u32 = u32::MAX;
+
+/// The longest finite TTL sent to the host, just below [`PERSISTENT_TTL`] (about 136 years).
+const MAX_TTL: u32 = PERSISTENT_TTL - 1;
+
+/// Converts a TTL into the unit expected by the host, whole seconds, `None` meaning that the
+/// value never expires. Every TTL sent to the host goes through this function.
+///
+/// Sub-second durations are rounded up, and a zero duration becomes one second, the shortest
+/// TTL of the host. Durations that do not fit are saturated to [`MAX_TTL`], so that they still
+/// expire.
+fn host_ttl(ttl: Option<Duration>) -> u32 {
+    match ttl {
+        Some(ttl) => {
+            let seconds = ttl
+                .as_secs()
+                .saturating_add(u64::from(ttl.subsec_nanos() > 0));
+            u32::try_from(seconds).map_or(MAX_TTL, |seconds| seconds.clamp(1, MAX_TTL))
+        }
+        None => PERSISTENT_TTL,
+    }
+}
+
+/// Connection id of the agent's own storage.
+const OWN_CONNECTION_ID: i32 = -1;
+
+/// Errors returned by [`KvStorage`] operations.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum StorageError {
+    /// The storage belongs to another agent and can only be read.
+    #[error("storage connection {0} belongs to an external agent and is read-only")]
+    ReadOnly(i32),
+    /// The key has no value.
+    #[error("no value stored for key `{0}`")]
+    NotFound(String),
+    /// The host refused to open the storage of an external agent.
+    #[error("the host rejected opening the storage of external agent `{agent_id}` (code {code})")]
+    HostRejected { agent_id: String, code: i32 },
+    /// The connection id is neither the own storage nor an opened external one.
+    #[error("invalid storage connection id {0}")]
+    InvalidConnectionId(i32),
+    /// The value could not be encoded by [`KvStorage::set`] or [`KvStorage::set_as`].
+    #[error("failed to encode the value of key `{key}`: {reason}")]
+    Encode { key: String, reason: String },
+    /// The stored value could not be decoded by [`KvStorage::get_as`].
+    #[error("failed to decode the value of key `{key}`: {reason}")]
+    Decode { key: String, reason: DecodeError },
+}
+
+/// Reasons a value read by [`KvStorage::get_as`] can not be decoded.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    /// The value was not written by `set_as`.
+    #[error("expected a BYTES value-data, found {0}")]
+    NotBytes(&'static str),
+    #[error("missing the MessagePack header")]
+    MissingHeader,
+    /// The value was written by a newer SDK.
+    #[error("unsupported format version {0}")]
+    UnsupportedVersion(u8),
+    /// The bytes do not decode into the requested type.
+    #[error("{0}")]
+    MessagePack(String),
+}
+
+/// Prefix of the values written by [`KvStorage::set_as`]: a magic followed by the format version.
+const MSGPACK_MAGIC: &[u8] = b"MP";
+const MSGPACK_VERSION: u8 = 1;
+
+/// A key-value storage: the agent's own one, or the read-only storage of an external agent.
 pub struct KvStorage {
     connection_id: i32,
+    /// TTL of the values written without one, `None` for values that never expire.
+    default_ttl: Option<Duration>,
 }
 
 impl KvStorage {
-    pub fn new(connection_id: i32) -> Self {
-        Self { connection_id }
+    /// Wraps a connection id, `-1` being the agent's own storage and any other non-negative id a
+    /// read-only external storage.
+    pub fn new(connection_id: i32) -> Result<Self, StorageError> {
+        match connection_id {
+            OWN_CONNECTION_ID => Ok(Self::open(None)),
+            id if id >= 0 => Ok(KvStorage {
+                connection_id,
+                default_ttl: None,
+            }),
+            id => Err(StorageError::InvalidConnectionId(id)),
+        }
+    }
+
+    fn open(default_ttl: Option<Duration>) -> Self {
+        KvStorage {
+            connection_id: OWN_CONNECTION_ID,
+            default_ttl,
+        }
+    }
+
+    fn open_external(external_agent_id: &str) -> Result<Self, StorageError> {
+        match mamoru_storage::open_external(external_agent_id) {
+            code if code < 0 => Err(StorageError::HostRejected {
+                agent_id: external_agent_id.to_string(),
+                code,
+            }),
+            connection_id => Ok(KvStorage {
+                connection_id,
+                default_ttl: None,
+            }),
+        }
+    }
+
+    /// Returns the TTL of the values written without one, `None` if they never expire.
+    pub fn default_ttl(&self) -> Option<Duration> {
+        self.default_ttl
     }
-    fn open() -> Self {
-        KvStorage { connection_id: -1 }
+
+    /// Returns `true` for the storage of an external agent.
+    pub fn is_read_only(&self) -> bool {
+        self.connection_id != OWN_CONNECTION_ID
     }
-    fn open_external(external_agent_id: String) -> Self {
-        let connection_id = mamoru_storage::open_external(&external_agent_id);
-        KvStorage { connection_id }
+
+    fn check_writable(&self) -> Result<(), StorageError> {
+        match self.is_read_only() {
+            true => Err(StorageError::ReadOnly(self.connection_id)),
+            false => Ok(()),
+        }
     }
 
     /// Checks if the key exists.
-    pub fn contains(&self, _key: String) -> bool {
-        false
+    pub fn contains(&self, key: &str) -> bool {
+        mamoru_storage::get(self.connection_id, key).is_some()
     }
 
     /// Returns keys with the given prefix
     ///
     /// Parameters:
-    /// - `prefix`: Prefix to use for filtering
+    /// - `prefix`: Prefix to use for filtering, an empty prefix returns every key.
     ///
-    /// Returns
-    pub fn keys(_prefix: String) -> Vec<String> {
-        Vec::new()
+    /// Returns:
+    /// The keys of the storage starting with `prefix`. Keys the host does not return as a
+    /// `STRING` or UTF-8 `BYTES` value-data are skipped.
+    pub fn keys(&self, prefix: &str) -> Vec<String> {
+        mamoru_storage::keys(self.connection_id, prefix)
+            .into_iter()
+            .filter_map(|key| match key.value {
+                ValueType::String(key) => Some(key),
+                ValueType::Bytes(key) => String::from_utf8(key).ok(),
+                _ => None,
+            })
+            .collect()
     }
 
     /// Get the value for a given `key`
-    pub fn get(&self, key: String) -> Option<mamoru_storage::ValueData> {
-        mamoru_storage::get(self.connection_id, key.as_str())
+    pub fn get(&self, key: &str) -> Option<mamoru_storage::ValueData> {
+        mamoru_storage::get(self.connection_id, key)
+    }
+
+    /// Get the value for a given `key`, failing with `StorageError::NotFound` if there is none.
+    pub fn get_required(&self, key: &str) -> Result<mamoru_storage::ValueData, StorageError> {
+        self.get(key)
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    /// Get the value for a given `key`, decoded from the MessagePack written by
+    /// [`KvStorage::set_as`].
+    ///
+    /// Returns:
+    /// `None` if the key has no value, or `StorageError::Decode` if the value was not written by
+    /// `set_as` or does not decode into `T`.
+    pub fn get_as<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, StorageError> {
+        let Some(value) = self.get(key) else {
+            return Ok(None);
+        };
+
+        decode_msgpack(&value)
+            .map(Some)
+            .map_err(|reason| StorageError::Decode {
+                key: key.to_string(),
+                reason,
+            })
     }
-    //TODO add default for ttl
-    /// Set a value with the given `key`
+
+    /// Set a value with the given `key`, encoded as MessagePack in a `BYTES` value-data, with the
+    /// default TTL of the storage.
+    ///
+    /// The bytes start with a format header, so that [`KvStorage::get_as`] can tell them apart
+    /// from values written by `set`.
+    pub fn set_as<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<(), StorageError> {
+        self.write(key, encode_msgpack(key, value)?, self.default_ttl)
+            .map(|_| ())
+    }
+
+    /// Same as [`KvStorage::set_as`], with the value expiring after `ttl`.
+    pub fn set_as_with_ttl<T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<(), StorageError> {
+        self.write(key, encode_msgpack(key, value)?, Some(ttl))
+            .map(|_| ())
+    }
+
+    /// Set a value with the given `key`, with the default TTL of the storage.
+    ///
+    /// Returns:
+    /// The previous value, `StorageError::ReadOnly` for the storage of an external agent, or
+    /// `StorageError::Encode` if the value can not be converted into value-data.
     pub fn set(
         &self,
-        key: String,
-        value: mamoru_storage::ValueData,
-        ttl: u32,
-    ) -> Option<mamoru_storage::ValueData> {
-        mamoru_storage::set(key.as_str(), &value, ttl)
+        key: &str,
+        value: impl IntoValueData,
+    ) -> Result<Option<mamoru_storage::ValueData>, StorageError> {
+        self.write(key, encode_value(key, value)?, self.default_ttl)
+    }
+
+    /// Set a value with the given `key`, expiring after `ttl`.
+    ///
+    /// Parameters:
+    /// - `ttl`: How long the value is kept, rounded up to whole seconds and at least one.
+    ///
+    /// Returns:
+    /// The previous value, `StorageError::ReadOnly` for the storage of an external agent, or
+    /// `StorageError::Encode` if the value can not be converted into value-data.
+    pub fn set_with_ttl(
+        &self,
+        key: &str,
+        value: impl IntoValueData,
+        ttl: Duration,
+    ) -> Result<Option<mamoru_storage::ValueData>, StorageError> {
+        self.write(key, encode_value(key, value)?, Some(ttl))
+    }
+
+    /// Set a value with the given `key` that never expires, whatever the default TTL.
+    ///
+    /// Returns:
+    /// The previous value, `StorageError::ReadOnly` for the storage of an external agent, or
+    /// `StorageError::Encode` if the value can not be converted into value-data.
+    pub fn set_persistent(
+        &self,
+        key: &str,
+        value: impl IntoValueData,
+    ) -> Result<Option<mamoru_storage::ValueData>, StorageError> {
+        self.write(key, encode_value(key, value)?, None)
+    }
+
+    fn write(
+        &self,
+        key: &str,
+        value: ValueData,
+        ttl: Option<Duration>,
+    ) -> Result<Option<ValueData>, StorageError> {
+        self.check_writable()?;
+
+        Ok(mamoru_storage::set(key, &value, host_ttl(ttl)))
+    }
+
+    /// Deletes the value of the given `key`, if any.
+    ///
+    /// Returns:
+    /// `StorageError::ReadOnly` for the storage of an external agent.
+    pub fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.check_writable()?;
+        mamoru_storage::delete(key);
+
+        Ok(())
+    }
+
+    /// Starts a batch of writes applied together by [`WriteBatch::commit`].
+    ///
+    /// Returns:
+    /// `StorageError::ReadOnly` for the storage of an external agent.
+    pub fn batch(&self) -> Result<WriteBatch<'_>, StorageError> {
+        self.check_writable()?;
+
+        Ok(WriteBatch {
+            storage: self,
+            writes: PendingWrites::default(),
+        })
+    }
+}
+
+/// Sets and deletes buffered in memory and written to the storage on [`WriteBatch::commit`].
+///
+/// A batch dropped without being committed is discarded, so an agent that fails or traps halfway
+/// through an update leaves the storage untouched. The commit itself is not atomic, see
+/// [`WriteBatch::commit`]:
+///
+/// ```ignore
+/// let mut batch = storage.batch()?;
+/// batch.delete(&pending_key).set_as(&confirmed_key, &position)?;
+/// batch.commit()?;
+/// ```
+#[must_use = "a batch is discarded unless committed"]
+pub struct WriteBatch<'s> {
+    storage: &'s KvStorage,
+    writes: PendingWrites,
+}
+
+enum Write {
+    Set(ValueData, Option<Duration>),
+    Delete,
+}
+
+/// The last write of each key of a [`WriteBatch`], in the order the keys were first written.
+#[derive(Default)]
+struct PendingWrites(Vec<(String, Write)>);
+
+impl PendingWrites {
+    /// Records a write, replacing a previous write of the key without moving it.
+    fn record(&mut self, key: &str, write: Write) {
+        match self.0.iter_mut().find(|(k, _)| k == key) {
+            Some((_, existing)) => *existing = write,
+            None => self.0.push((key.to_string(), write)),
+        }
+    }
+
+    /// Returns the pending write of a key: `None` if it was not written, `Some(None)` if it was
+    /// deleted.
+    fn get(&self, key: &str) -> Option<Option<&ValueData>> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, write)| match write {
+                Write::Set(value, _) => Some(value),
+                Write::Delete => None,
+            })
+    }
+}
+
+impl WriteBatch<'_> {
+    /// Buffers a value for the given `key` with the default TTL of the storage, replacing a
+    /// previous write of the key.
+    ///
+    /// Returns:
+    /// The batch, or `StorageError::Encode` if the value can not be converted into value-data.
+    pub fn set(&mut self, key: &str, value: impl IntoValueData) -> Result<&mut Self, StorageError> {
+        let value = encode_value(key, value)?;
+
+        Ok(self.write(key, Write::Set(value, self.storage.default_ttl)))
+    }
+
+    /// Same as [`WriteBatch::set`], with the value expiring after `ttl`.
+    pub fn set_with_ttl(
+        &mut self,
+        key: &str,
+        value: impl IntoValueData,
+        ttl: Duration,
+    ) -> Result<&mut Self, StorageError> {
+        let value = encode_value(key, value)?;
+
+        Ok(self.write(key, Write::Set(value, Some(ttl))))
+    }
+
+    /// Same as [`WriteBatch::set`], with a value that never expires.
+    pub fn set_persistent(
+        &mut self,
+        key: &str,
+        value: impl IntoValueData,
+    ) -> Result<&mut Self, StorageError> {
+        let value = encode_value(key, value)?;
+
+        Ok(self.write(key, Write::Set(value, None)))
+    }
+
+    /// Buffers a value for the given `key`, encoded as by [`KvStorage::set_as`].
+    pub fn set_as<T: Serialize + ?Sized>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<&mut Self, StorageError> {
+        let value = encode_msgpack(key, value)?;
+
+        Ok(self.write(key, Write::Set(value, self.storage.default_ttl)))
+    }
+
+    /// Same as [`WriteBatch::set_as`], with the value expiring after `ttl`.
+    pub fn set_as_with_ttl<T: Serialize + ?Sized>(
+        &mut self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<&mut Self, StorageError> {
+        let value = encode_msgpack(key, value)?;
+
+        Ok(self.write(key, Write::Set(value, Some(ttl))))
+    }
+
+    /// Buffers the deletion of the given `key`, replacing a previous write of the key.
+    pub fn delete(&mut self, key: &str) -> &mut Self {
+        self.write(key, Write::Delete)
+    }
+
+    /// Get the value of a given `key` as it will be after the commit.
+    pub fn get(&self, key: &str) -> Option<mamoru_storage::ValueData> {
+        match self.writes.get(key) {
+            Some(value) => value.cloned(),
+            None => self.storage.get(key),
+        }
+    }
+
+    /// Returns the number of keys written by the batch.
+    pub fn len(&self) -> usize {
+        self.writes.0.len()
+    }
+
+    /// Returns `true` if nothing was written to the batch.
+    pub fn is_empty(&self) -> bool {
+        self.writes.0.is_empty()
+    }
+
+    /// Writes every buffered set and delete to the storage, in the order their keys were first
+    /// written, without running any agent code in between.
+    ///
+    /// Each write is a separate host call, so the commit is not atomic: if one of them fails or
+    /// the host stops the agent, the writes before it stay applied and the ones after it are
+    /// lost.
+    pub fn commit(self) -> Result<(), StorageError> {
+        for (key, write) in self.writes.0 {
+            match write {
+                Write::Set(value, ttl) => {
+                    self.storage.write(&key, value, ttl)?;
+                }
+                Write::Delete => self.storage.delete(&key)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, key: &str, write: Write) -> &mut Self {
+        self.writes.record(key, write);
+        self
+    }
+}
+
+fn encode_value(key: &str, value: impl IntoValueData) -> Result<ValueData, StorageError> {
+    value
+        .try_into_value_data()
+        .map_err(|e| StorageError::Encode {
+            key: key.to_string(),
+            reason: e.to_string(),
+        })
+}
+
+fn encode_msgpack<T: Serialize + ?Sized>(key: &str, value: &T) -> Result<ValueData, StorageError> {
+    let mut bytes = [MSGPACK_MAGIC, &[MSGPACK_VERSION]].concat();
+    rmp_serde::encode::write_named(&mut bytes, value).map_err(|e| StorageError::Encode {
+        key: key.to_string(),
+        reason: e.to_string(),
+    })?;
+
+    Ok(ValueData {
+        data: None,
+        value: ValueType::Bytes(bytes),
+    })
+}
+
+fn decode_msgpack<T: DeserializeOwned>(value: &ValueData) -> Result<T, DecodeError> {
+    let bytes = match &value.value {
+        ValueType::Bytes(bytes) => bytes,
+        other => return Err(DecodeError::NotBytes(type_name(other))),
+    };
+    let Some(rest) = bytes.strip_prefix(MSGPACK_MAGIC) else {
+        return Err(DecodeError::MissingHeader);
+    };
+
+    match rest.split_first() {
+        Some((&MSGPACK_VERSION, payload)) => {
+            rmp_serde::from_slice(payload).map_err(|e| DecodeError::MessagePack(e.to_string()))
+        }
+        Some((&version, _)) => Err(DecodeError::UnsupportedVersion(version)),
+        None => Err(DecodeError::MissingHeader),
     }
 }
 
 /// Open a KV storage for the current agent
-pub fn open() -> KvStorage {
-    KvStorage::open()
+///
+/// Parameters:
+/// - `default_ttl`: TTL of the values written without one, such as by [`KvStorage::set`] and
+///   [`KvStorage::set_as`], rounded up to whole seconds and at least one. `None` for values that
+///   never expire.
+pub fn open(default_ttl: Option<Duration>) -> KvStorage {
+    KvStorage::open(default_ttl)
 }
 
 /// Opens a KV storage for the external agent id
-/// The storage is read-only
+/// The storage is read-only: `set` fails with `StorageError::ReadOnly`.
 ///
 /// Parameters:
 /// -`external_agent_id`: External agent identifier
 ///
 /// Returns:
-/// KVStorage for agent
-pub fn open_external(external_agent_id: String) -> KvStorage {
+/// KVStorage for agent, or `StorageError::HostRejected` if the host returned no connection.
+pub fn open_external(external_agent_id: &str) -> Result<KvStorage, StorageError> {
     KvStorage::open_external(external_agent_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Position {
+        pool: String,
+        amount: u64,
+    }
+
+    fn bytes(bytes: &[u8]) -> ValueData {
+        ValueData {
+            data: None,
+            value: ValueType::Bytes(bytes.to_vec()),
+        }
+    }
+
+    fn u64_value(value: u64) -> ValueData {
+        ValueData {
+            data: None,
+            value: ValueType::U64(value),
+        }
+    }
+
+    /// Returns the keys of the pending writes, with the value of a set or `None` for a delete.
+    fn pending(writes: &PendingWrites) -> Vec<(&str, Option<u64>)> {
+        writes
+            .0
+            .iter()
+            .map(|(key, write)| {
+                let value = match write {
+                    Write::Set(value, _) => Some(value.view().as_u64().unwrap()),
+                    Write::Delete => None,
+                };
+                (key.as_str(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_last_write_of_each_key() {
+        let mut writes = PendingWrites::default();
+        writes.record("b", Write::Set(u64_value(1), None));
+        writes.record("a", Write::Set(u64_value(2), None));
+        writes.record("b", Write::Set(u64_value(3), None));
+        writes.record("c", Write::Delete);
+        writes.record("a", Write::Delete);
+        writes.record("c", Write::Set(u64_value(4), None));
+
+        assert_eq!(
+            pending(&writes),
+            [("b", Some(3)), ("a", None), ("c", Some(4))]
+        );
+    }
+
+    #[test]
+    fn reads_pending_writes() {
+        let mut writes = PendingWrites::default();
+        writes.record("set", Write::Set(u64_value(1), None));
+        writes.record("deleted", Write::Set(u64_value(2), None));
+        writes.record("deleted", Write::Delete);
+
+        let value = writes.get("set").unwrap().unwrap();
+        assert_eq!(value.view().as_u64().unwrap(), 1);
+        assert!(writes.get("deleted").unwrap().is_none());
+        assert!(writes.get("untouched").is_none());
+    }
+
+    #[test]
+    fn buffers_batch_writes() {
+        let storage = KvStorage::new(-1).unwrap();
+        let mut batch = storage.batch().unwrap();
+        batch
+            .set("count", u64_value(1))
+            .unwrap()
+            .delete("stale")
+            .set_with_ttl("count", u64_value(2), Duration::from_secs(60))
+            .unwrap();
+
+        assert_eq!(batch.len(), 2);
+        assert_eq!(
+            pending(&batch.writes),
+            [("count", Some(2)), ("stale", None)]
+        );
+        assert_eq!(batch.get("count").unwrap().view().as_u64().unwrap(), 2);
+        assert!(batch.get("stale").is_none());
+        assert!(KvStorage::new(0).unwrap().batch().is_err());
+    }
+
+    #[test]
+    fn round_trips_msgpack_values() {
+        let position = Position {
+            pool: "0xa".to_string(),
+            amount: 42,
+        };
+        let value = encode_msgpack("position", &position).unwrap();
+
+        let ValueType::Bytes(encoded) = &value.value else {
+            panic!("expected BYTES, got {value:?}");
+        };
+        assert!(encoded.starts_with(b"MP\x01"));
+        assert_eq!(decode_msgpack::<Position>(&value), Ok(position));
+    }
+
+    #[test]
+    fn rejects_values_not_written_by_set_as() {
+        let number = ValueData {
+            data: None,
+            value: ValueType::U64(1),
+        };
+
+        assert_eq!(
+            decode_msgpack::<u64>(&number),
+            Err(DecodeError::NotBytes("U64"))
+        );
+        assert_eq!(
+            decode_msgpack::<u64>(&bytes(b"")),
+            Err(DecodeError::MissingHeader)
+        );
+        assert_eq!(
+            decode_msgpack::<u64>(&bytes(b"MP")),
+            Err(DecodeError::MissingHeader)
+        );
+        assert_eq!(
+            decode_msgpack::<u64>(&bytes(b"XX\x01\x2a")),
+            Err(DecodeError::MissingHeader)
+        );
+        assert_eq!(
+            decode_msgpack::<u64>(&bytes(b"MP\x02\x2a")),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn reports_msgpack_type_mismatches() {
+        let value = encode_msgpack("count", "not a number").unwrap();
+
+        assert!(matches!(
+            decode_msgpack::<u64>(&value),
+            Err(DecodeError::MessagePack(_))
+        ));
+        assert_eq!(decode_msgpack::<String>(&value).unwrap(), "not a number");
+    }
+
+    #[test]
+    fn converts_ttls_to_whole_seconds() {
+        assert_eq!(host_ttl(None), PERSISTENT_TTL);
+        assert_eq!(host_ttl(Some(Duration::ZERO)), 1);
+        assert_eq!(host_ttl(Some(Duration::from_millis(1))), 1);
+        assert_eq!(host_ttl(Some(Duration::from_millis(1500))), 2);
+        assert_eq!(host_ttl(Some(Duration::from_secs(7 * 24 * 3600))), 604_800);
+    }
+
+    #[test]
+    fn keeps_long_ttls_finite() {
+        let max = Duration::from_secs(u64::from(MAX_TTL));
+
+        assert_eq!(host_ttl(Some(max)), MAX_TTL);
+        assert_eq!(host_ttl(Some(max + Duration::from_millis(1))), MAX_TTL);
+        assert_eq!(
+            host_ttl(Some(Duration::from_secs(u64::from(u32::MAX)))),
+            MAX_TTL
+        );
+        assert_eq!(host_ttl(Some(Duration::MAX)), MAX_TTL);
+    }
+}
@@ -0,0 +1,614 @@
+//! Data structures kept in a [`KvStorage`] across runs, for detectors whose state spans blocks.
+//!
+//! Each structure owns the keys under its namespace, `{namespace}:...`, and every write refreshes
+//! the TTL of the keys it touches: the TTL given with `with_ttl`, or the default TTL of the
+//! storage. Values are encoded as by [`KvStorage::set_as`].
+//!
+//! ```ignore
+//! let storage = mamoru_storage::open(None);
+//! let window = Duration::from_secs(10 * 60);
+//! let withdrawals = SlidingWindowCounter::new(&storage, "withdrawals:pool-1", window, 60);
+//!
+//! let count = withdrawals.add(Duration::from_millis(tx.timestamp_ms), 1)?;
+//! if count > threshold {
+//!     // report
+//! }
+//! ```
+
+use super::{KvStorage, StorageError};
+use crate::mamoru_serialize::to_hex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// The keys of a data structure, all starting with `{namespace}:`.
+struct Namespace<'s> {
+    storage: &'s KvStorage,
+    prefix: String,
+    /// TTL of the written keys, `None` for the default TTL of the storage.
+    ttl: Option<Duration>,
+}
+
+impl<'s> Namespace<'s> {
+    fn new(storage: &'s KvStorage, namespace: &str) -> Self {
+        Namespace {
+            storage,
+            prefix: format!("{namespace}:"),
+            ttl: None,
+        }
+    }
+
+    fn key(&self, suffix: &str) -> String {
+        format!("{}{suffix}", self.prefix)
+    }
+
+    /// Returns the key of a Rust value, the hex of its MessagePack encoding.
+    fn value_key<T: Serialize + ?Sized>(&self, value: &T) -> Result<String, StorageError> {
+        let bytes = rmp_serde::to_vec_named(value).map_err(|e| StorageError::Encode {
+            key: self.prefix.clone(),
+            reason: e.to_string(),
+        })?;
+
+        Ok(self.key(&to_hex(&bytes)))
+    }
+
+    /// Returns the keys written by [`Namespace::value_key`].
+    fn value_keys(&self) -> Vec<String> {
+        self.filter_value_keys(self.storage.keys(&self.prefix))
+    }
+
+    /// Keeps the keys written by [`Namespace::value_key`], leaving out nested namespaces and any
+    /// key the host returns outside of the namespace.
+    fn filter_value_keys(&self, keys: Vec<String>) -> Vec<String> {
+        keys.into_iter()
+            .filter(|key| {
+                key.strip_prefix(&self.prefix)
+                    .and_then(|suffix| suffix.strip_prefix("0x"))
+                    .is_some_and(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            })
+            .collect()
+    }
+
+    fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, StorageError> {
+        self.storage.get_as(key)
+    }
+
+    fn set<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<(), StorageError> {
+        match self.ttl {
+            Some(ttl) => self.storage.set_as_with_ttl(key, value, ttl),
+            None => self.storage.set_as(key, value),
+        }
+    }
+}
+
+/// A counter stored under a single key.
+pub struct Counter<'s> {
+    namespace: Namespace<'s>,
+}
+
+impl<'s> Counter<'s> {
+    /// Creates a counter stored under `{namespace}:count`.
+    pub fn new(storage: &'s KvStorage, namespace: &str) -> Self {
+        Counter {
+            namespace: Namespace::new(storage, namespace),
+        }
+    }
+
+    /// Sets the TTL of the counter, refreshed on every write.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.namespace.ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the current value, `0` if the counter was never written or expired.
+    pub fn get(&self) -> Result<u64, StorageError> {
+        let value = self.namespace.get(&self.namespace.key("count"))?;
+
+        Ok(value.unwrap_or_default())
+    }
+
+    /// Adds `amount` to the counter, saturating at `u64::MAX`.
+    ///
+    /// Returns:
+    /// The new value.
+    pub fn increment_by(&self, amount: u64) -> Result<u64, StorageError> {
+        let value = self.get()?.saturating_add(amount);
+        self.namespace.set(&self.namespace.key("count"), &value)?;
+
+        Ok(value)
+    }
+
+    /// Adds one to the counter, returning the new value.
+    pub fn increment(&self) -> Result<u64, StorageError> {
+        self.increment_by(1)
+    }
+
+    /// Resets the counter to `0`.
+    pub fn reset(&self) -> Result<(), StorageError> {
+        self.namespace.storage.delete(&self.namespace.key("count"))
+    }
+}
+
+/// The buckets of a [`SlidingWindowCounter`], as `(bucket index, count)` pairs.
+#[derive(Default, Serialize, Deserialize)]
+struct Buckets(Vec<(u64, u64)>);
+
+impl Buckets {
+    /// Adds `amount` events to the bucket of index `bucket`.
+    fn add(&mut self, bucket: u64, amount: u64) {
+        match self.0.iter_mut().find(|(index, _)| *index == bucket) {
+            Some((_, count)) => *count = count.saturating_add(amount),
+            None => self.0.push((bucket, amount)),
+        }
+    }
+
+    fn total(&self) -> u64 {
+        self.0
+            .iter()
+            .fold(0u64, |total, (_, count)| total.saturating_add(*count))
+    }
+}
+
+/// Counts events over a sliding time window, such as "withdrawals from this pool in the last
+/// 10 minutes".
+///
+/// The window is split into buckets and events are counted in the bucket of their timestamp,
+/// so the count is exact up to the width of a bucket. Timestamps come from the caller, usually
+/// the time of the block or transaction, and should not go backwards.
+pub struct SlidingWindowCounter<'s> {
+    namespace: Namespace<'s>,
+    window: Duration,
+    bucket_width: Duration,
+}
+
+impl<'s> SlidingWindowCounter<'s> {
+    /// Creates a counter stored under `{namespace}:buckets`.
+    ///
+    /// Parameters:
+    /// - `window`: The duration events are counted for.
+    /// - `buckets`: The number of buckets the window is split into, at least 1.
+    ///
+    /// The buckets expire with the window, unless a longer TTL is set with `with_ttl`.
+    pub fn new(storage: &'s KvStorage, namespace: &str, window: Duration, buckets: u32) -> Self {
+        let bucket_width = (window / buckets.max(1)).max(Duration::from_millis(1));
+        let mut namespace = Namespace::new(storage, namespace);
+        namespace.ttl = Some(window);
+
+        SlidingWindowCounter {
+            namespace,
+            window,
+            bucket_width,
+        }
+    }
+
+    /// Sets the TTL of the buckets, refreshed on every write.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.namespace.ttl = Some(ttl);
+        self
+    }
+
+    /// Counts `amount` events at `timestamp`.
+    ///
+    /// Parameters:
+    /// - `timestamp`: The time of the events, e.g. since the Unix epoch.
+    /// - `amount`: The number of events.
+    ///
+    /// Returns:
+    /// The number of events in the window ending at `timestamp`, these ones included.
+    pub fn add(&self, timestamp: Duration, amount: u64) -> Result<u64, StorageError> {
+        let mut buckets = self.live_buckets(timestamp)?;
+        buckets.add(self.bucket(timestamp), amount);
+        self.namespace
+            .set(&self.namespace.key("buckets"), &buckets)?;
+
+        Ok(buckets.total())
+    }
+
+    /// Returns the number of events in the window ending at `timestamp`.
+    pub fn count(&self, timestamp: Duration) -> Result<u64, StorageError> {
+        Ok(self.live_buckets(timestamp)?.total())
+    }
+
+    /// Forgets every counted event.
+    pub fn reset(&self) -> Result<(), StorageError> {
+        self.namespace
+            .storage
+            .delete(&self.namespace.key("buckets"))
+    }
+
+    fn bucket(&self, timestamp: Duration) -> u64 {
+        (timestamp.as_nanos() / self.bucket_width.as_nanos()) as u64
+    }
+
+    /// Returns the stored buckets that still overlap the window ending at `timestamp`.
+    fn live_buckets(&self, timestamp: Duration) -> Result<Buckets, StorageError> {
+        let mut buckets: Buckets = self
+            .namespace
+            .get(&self.namespace.key("buckets"))?
+            .unwrap_or_default();
+        self.retain_live(&mut buckets, timestamp);
+
+        Ok(buckets)
+    }
+
+    /// Drops the buckets outside of the window ending at `timestamp`. Every bucket up to
+    /// `timestamp` is live while the window reaches back past zero.
+    fn retain_live(&self, buckets: &mut Buckets, timestamp: Duration) {
+        let oldest = timestamp
+            .checked_sub(self.window)
+            .map(|start| self.bucket(start));
+        let newest = self.bucket(timestamp);
+
+        buckets
+            .0
+            .retain(|(index, _)| oldest.is_none_or(|oldest| *index > oldest) && *index <= newest);
+    }
+}
+
+/// The position of the elements of a [`RingBuffer`].
+#[derive(Default, Serialize, Deserialize)]
+struct RingMeta {
+    /// The number of elements ever pushed, the next one going to `pushed % capacity`.
+    pushed: u64,
+    len: u64,
+}
+
+impl RingMeta {
+    /// Records a push to a buffer of `capacity` elements.
+    ///
+    /// Returns:
+    /// The slot of the pushed element, which held the evicted one once the buffer is full.
+    fn push(&mut self, capacity: u64) -> u64 {
+        let slot = self.pushed % capacity;
+        self.pushed += 1;
+        self.len = (self.len + 1).min(capacity);
+
+        slot
+    }
+
+    /// Returns the slots of the elements, from the oldest to the newest.
+    fn slots(&self, capacity: u64) -> impl Iterator<Item = u64> {
+        (self.pushed - self.len..self.pushed).map(move |position| position % capacity)
+    }
+}
+
+/// The last `capacity` values pushed, the oldest ones being evicted by new ones.
+///
+/// Each element is stored under its own key, `{namespace}:{slot}`, next to a `{namespace}:meta`
+/// key. A push writes both in a [`super::WriteBatch`].
+pub struct RingBuffer<'s, T> {
+    namespace: Namespace<'s>,
+    capacity: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<'s, T: Serialize + DeserializeOwned> RingBuffer<'s, T> {
+    /// Creates a ring buffer holding at most `capacity` elements, at least 1.
+    pub fn new(storage: &'s KvStorage, namespace: &str, capacity: u64) -> Self {
+        RingBuffer {
+            namespace: Namespace::new(storage, namespace),
+            capacity: capacity.max(1),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the TTL of the elements, refreshed on every push.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.namespace.ttl = Some(ttl);
+        self
+    }
+
+    /// Appends a value, evicting the oldest one if the buffer is full.
+    pub fn push(&self, value: &T) -> Result<(), StorageError> {
+        let mut meta = self.meta()?;
+        let slot = self.namespace.key(&meta.push(self.capacity).to_string());
+
+        let mut batch = self.namespace.storage.batch()?;
+        let meta_key = self.namespace.key("meta");
+        match self.namespace.ttl {
+            Some(ttl) => batch
+                .set_as_with_ttl(&slot, value, ttl)?
+                .set_as_with_ttl(&meta_key, &meta, ttl)?,
+            None => batch.set_as(&slot, value)?.set_as(&meta_key, &meta)?,
+        };
+        batch.commit()
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> Result<u64, StorageError> {
+        Ok(self.meta()?.len)
+    }
+
+    /// Returns `true` if no element was pushed or all of them expired.
+    pub fn is_empty(&self) -> Result<bool, StorageError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Returns the elements from the oldest to the newest, leaving out the expired ones.
+    pub fn to_vec(&self) -> Result<Vec<T>, StorageError> {
+        let meta = self.meta()?;
+        let mut values = Vec::with_capacity(meta.len as usize);
+
+        for slot in meta.slots(self.capacity) {
+            if let Some(value) = self.namespace.get(&self.namespace.key(&slot.to_string()))? {
+                values.push(value);
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Removes every element.
+    pub fn clear(&self) -> Result<(), StorageError> {
+        let meta = self.meta()?;
+        let mut batch = self.namespace.storage.batch()?;
+
+        for slot in meta.slots(self.capacity) {
+            batch.delete(&self.namespace.key(&slot.to_string()));
+        }
+        batch.delete(&self.namespace.key("meta"));
+        batch.commit()
+    }
+
+    fn meta(&self) -> Result<RingMeta, StorageError> {
+        let meta = self.namespace.get(&self.namespace.key("meta"))?;
+
+        Ok(meta.unwrap_or_default())
+    }
+}
+
+/// A set of values, each stored under its own key.
+///
+/// The key of a value is the hex of its MessagePack encoding, so values must serialize the same
+/// way every time, e.g. no `HashMap` inside.
+pub struct PersistentSet<'s, T> {
+    namespace: Namespace<'s>,
+    _marker: PhantomData<T>,
+}
+
+impl<'s, T: Serialize + DeserializeOwned> PersistentSet<'s, T> {
+    /// Creates a set stored under `{namespace}:{value key}` keys.
+    pub fn new(storage: &'s KvStorage, namespace: &str) -> Self {
+        PersistentSet {
+            namespace: Namespace::new(storage, namespace),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the TTL of the values, refreshed when a value is inserted again.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.namespace.ttl = Some(ttl);
+        self
+    }
+
+    /// Inserts a value.
+    ///
+    /// Returns:
+    /// `true` if the value was not in the set.
+    pub fn insert(&self, value: &T) -> Result<bool, StorageError> {
+        let key = self.namespace.value_key(value)?;
+        let inserted = !self.namespace.storage.contains(&key);
+        self.namespace.set(&key, value)?;
+
+        Ok(inserted)
+    }
+
+    /// Returns `true` if the value is in the set.
+    pub fn contains(&self, value: &T) -> Result<bool, StorageError> {
+        let key = self.namespace.value_key(value)?;
+
+        Ok(self.namespace.storage.contains(&key))
+    }
+
+    /// Removes a value.
+    ///
+    /// Returns:
+    /// `true` if the value was in the set.
+    pub fn remove(&self, value: &T) -> Result<bool, StorageError> {
+        let key = self.namespace.value_key(value)?;
+        let removed = self.namespace.storage.contains(&key);
+        self.namespace.storage.delete(&key)?;
+
+        Ok(removed)
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.namespace.value_keys().len()
+    }
+
+    /// Returns `true` if the set has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the values, in the order of their keys.
+    pub fn to_vec(&self) -> Result<Vec<T>, StorageError> {
+        let mut values = Vec::new();
+        for key in self.namespace.value_keys() {
+            values.extend(self.namespace.get(&key)?);
+        }
+
+        Ok(values)
+    }
+}
+
+/// A map of keys to values, each entry stored under its own storage key.
+///
+/// The storage key of an entry is the hex of the MessagePack encoding of its key, so keys must
+/// serialize the same way every time, e.g. no `HashMap` inside.
+pub struct PersistentMap<'s, K, V> {
+    namespace: Namespace<'s>,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<'s, K, V> PersistentMap<'s, K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// Creates a map stored under `{namespace}:{key}` keys.
+    pub fn new(storage: &'s KvStorage, namespace: &str) -> Self {
+        PersistentMap {
+            namespace: Namespace::new(storage, namespace),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the TTL of the entries, refreshed when an entry is written.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.namespace.ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the value of a key.
+    pub fn get(&self, key: &K) -> Result<Option<V>, StorageError> {
+        let entry: Option<(K, V)> = self.namespace.get(&self.namespace.value_key(key)?)?;
+
+        Ok(entry.map(|(_, value)| value))
+    }
+
+    /// Returns `true` if the key has a value.
+    pub fn contains_key(&self, key: &K) -> Result<bool, StorageError> {
+        let key = self.namespace.value_key(key)?;
+
+        Ok(self.namespace.storage.contains(&key))
+    }
+
+    /// Sets the value of a key.
+    pub fn insert(&self, key: &K, value: &V) -> Result<(), StorageError> {
+        let storage_key = self.namespace.value_key(key)?;
+
+        self.namespace.set(&storage_key, &(key, value))
+    }
+
+    /// Removes a key.
+    ///
+    /// Returns:
+    /// `true` if the key had a value.
+    pub fn remove(&self, key: &K) -> Result<bool, StorageError> {
+        let key = self.namespace.value_key(key)?;
+        let removed = self.namespace.storage.contains(&key);
+        self.namespace.storage.delete(&key)?;
+
+        Ok(removed)
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.namespace.value_keys().len()
+    }
+
+    /// Returns `true` if the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the entries, in the order of their storage keys.
+    pub fn entries(&self) -> Result<Vec<(K, V)>, StorageError> {
+        let mut entries = Vec::new();
+        for key in self.namespace.value_keys() {
+            entries.extend(self.namespace.get(&key)?);
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn storage() -> KvStorage {
+        KvStorage::new(-1).unwrap()
+    }
+
+    fn live(
+        counter: &SlidingWindowCounter,
+        buckets: &[(u64, u64)],
+        timestamp: Duration,
+    ) -> Vec<u64> {
+        let mut buckets = Buckets(buckets.to_vec());
+        counter.retain_live(&mut buckets, timestamp);
+
+        buckets.0.into_iter().map(|(index, _)| index).collect()
+    }
+
+    #[test]
+    fn counts_events_in_buckets() {
+        let storage = storage();
+        let counter = SlidingWindowCounter::new(&storage, "withdrawals", 60 * SECOND, 6);
+
+        assert_eq!(counter.bucket(Duration::ZERO), 0);
+        assert_eq!(counter.bucket(9 * SECOND), 0);
+        assert_eq!(counter.bucket(10 * SECOND), 1);
+        assert_eq!(counter.bucket(125 * SECOND), 12);
+
+        let mut buckets = Buckets::default();
+        buckets.add(1, 2);
+        buckets.add(3, 1);
+        buckets.add(1, u64::MAX);
+        assert_eq!(buckets.0, [(1, u64::MAX), (3, 1)]);
+        assert_eq!(buckets.total(), u64::MAX);
+    }
+
+    #[test]
+    fn keeps_the_buckets_of_the_window() {
+        let storage = storage();
+        let counter = SlidingWindowCounter::new(&storage, "withdrawals", 60 * SECOND, 6);
+        let buckets = [(0, 1), (5, 1), (6, 1), (7, 1), (12, 1), (13, 1)];
+
+        // The window ending in bucket 12 starts in bucket 6, which it only overlaps by its end.
+        assert_eq!(live(&counter, &buckets, 125 * SECOND), [7, 12]);
+        assert_eq!(live(&counter, &buckets, 70 * SECOND), [5, 6, 7]);
+    }
+
+    #[test]
+    fn keeps_the_first_bucket_while_the_window_reaches_past_zero() {
+        let storage = storage();
+        let counter = SlidingWindowCounter::new(&storage, "withdrawals", 60 * SECOND, 6);
+        let buckets = [(0, 1), (3, 1)];
+
+        assert_eq!(live(&counter, &buckets, Duration::ZERO), [0]);
+        assert_eq!(live(&counter, &buckets, 30 * SECOND), [0, 3]);
+        assert_eq!(live(&counter, &buckets, 59 * SECOND), [0, 3]);
+        assert_eq!(live(&counter, &buckets, 60 * SECOND), [3]);
+    }
+
+    #[test]
+    fn evicts_the_oldest_ring_elements() {
+        let mut meta = RingMeta::default();
+        assert_eq!(meta.slots(3).count(), 0);
+
+        assert_eq!([meta.push(3), meta.push(3)], [0, 1]);
+        assert_eq!(meta.len, 2);
+        assert_eq!(meta.slots(3).collect::<Vec<_>>(), [0, 1]);
+
+        assert_eq!([meta.push(3), meta.push(3), meta.push(3)], [2, 0, 1]);
+        assert_eq!(meta.len, 3);
+        assert_eq!(meta.pushed, 5);
+        assert_eq!(meta.slots(3).collect::<Vec<_>>(), [2, 0, 1]);
+    }
+
+    #[test]
+    fn filters_the_value_keys_of_a_namespace() {
+        let storage = storage();
+        let namespace = Namespace::new(&storage, "set");
+        let keys = [
+            "set:0x0a1b",
+            "set:meta",
+            "set:0xzz",
+            "set:nested:0x01",
+            "settle:0x01",
+            "other",
+        ];
+
+        assert_eq!(
+            namespace.filter_value_keys(keys.map(String::from).to_vec()),
+            ["set:0x0a1b"]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::component::guest::types::*;
+use crate::mamoru_bcs::{BcsError, MoveTypeLayout};
 use std::collections::BTreeMap;
 #[allow(dead_code)]
 /// Structure representing a transaction.
@@ -162,7 +163,7 @@ impl From<SuiTransaction> for Transaction {
             inner_sec,
             inner_id,
             inner_time,
-            digest: "".to_string(),
+            digest: sui_transaction.digest.clone(),
             success: false,
             gas_used: 0,
             gas_computation_cost: 0,
@@ -172,6 +173,11 @@ impl From<SuiTransaction> for Transaction {
 }
 
 impl Transaction {
+    /// Digest of the transaction, as reported by the host.
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
     /// Returns if it is a programmable transaction
     pub fn is_programmable(&self) -> bool {
         true
@@ -542,6 +548,20 @@ pub struct MoveObject {
     /// BCS bytes of a Move struct value
     contents: Vec<u8>,
 }
+
+impl MoveObject {
+    /// Decodes the BCS contents of the object.
+    ///
+    /// Parameters:
+    /// - `layout`: The layout of the object type.
+    ///
+    /// Returns:
+    /// The decoded contents, or a `BcsError` if they do not match the layout.
+    pub fn decode_contents(&self, layout: &MoveTypeLayout) -> Result<ValueData, BcsError> {
+        crate::mamoru_bcs::decode(&self.contents, layout)
+    }
+}
+
 /// Wraps the detailed type definition for a Move object, providing a single-layer abstraction.
 #[derive(Clone)]
 pub struct MoveObjectType(MoveObjectType_);
@@ -645,8 +665,19 @@ pub struct EventEnvelope {
     pub event_num: u64,
     /// Specific event type
     pub event: Event,
-    /// Move event's json value
-    pub parsed_json: String, //TODO Json serialized
+    /// Move event's json value, serialized. See [`EventEnvelope::parsed_json_value`] to read it.
+    pub parsed_json: String,
+}
+
+impl EventEnvelope {
+    /// Parses the Move event's json value.
+    ///
+    /// Returns:
+    /// The json value, or a `serde_json::Error` if `parsed_json` is not valid json. See
+    /// [`crate::mamoru_serialize::to_json`] to build it from the event contents instead.
+    pub fn parsed_json_value(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::from_str(&self.parsed_json)
+    }
 }
 
 /// Structure for an event within a transaction.
@@ -668,6 +699,17 @@ impl Event {
     pub fn get_parent_transaction(&self) -> Transaction {
         dummy_trans()
     }
+
+    /// Decodes the BCS contents of the event.
+    ///
+    /// Parameters:
+    /// - `layout`: The layout of the event type.
+    ///
+    /// Returns:
+    /// The decoded contents, or a `BcsError` if they do not match the layout.
+    pub fn decode_contents(&self, layout: &MoveTypeLayout) -> Result<ValueData, BcsError> {
+        crate::mamoru_bcs::decode(&self.contents, layout)
+    }
 }
 
 /// Enum representing argument types for a call within a transaction.
@@ -697,6 +739,23 @@ pub enum CallArg {
     Object(ObjectArg),
 }
 
+impl CallArg {
+    /// Decodes the BCS bytes of a pure argument, such as a transfer amount or recipient.
+    ///
+    /// Parameters:
+    /// - `layout`: The layout of the argument type.
+    ///
+    /// Returns:
+    /// `None` for object arguments, otherwise the decoded value or a `BcsError` if the bytes do
+    /// not match the layout.
+    pub fn decode_pure(&self, layout: &MoveTypeLayout) -> Option<Result<ValueData, BcsError>> {
+        match self {
+            CallArg::Pure(bytes) => Some(crate::mamoru_bcs::decode(bytes, layout)),
+            CallArg::Object(_) => None,
+        }
+    }
+}
+
 /// Struct representing a trace of a call.
 #[derive(Clone)]
 pub struct Calltrace {
@@ -816,6 +875,12 @@ impl SuiCtx {
     pub fn transaction(&self) -> Option<Transaction> {
         self.transaction.clone()
     }
+
+    /// Returns the digest of the transaction, if the context has one.
+    pub fn transaction_digest(&self) -> Option<&str> {
+        self.transaction.as_ref().map(Transaction::digest)
+    }
+
     /// Creates a new `SuiCtx` instance with no active transaction.
     ///
     /// Returns:
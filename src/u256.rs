@@ -0,0 +1,539 @@
+use crate::component::guest::types::{ValueData, ValueType};
+use crate::mamoru_serialize::U256_TOKEN;
+use mamoru_rust_sdk_parse::{strip_hex_prefix, U256DigitsError};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of bytes of the `U256` value-type encoding.
+const BYTES: usize = 32;
+
+/// Errors returned when parsing or converting a `U256`.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum U256Error {
+    #[error("empty number")]
+    Empty,
+    #[error("invalid digit in `{0}`")]
+    InvalidDigit(String),
+    #[error("number does not fit in 256 bits")]
+    Overflow,
+    #[error("`{value}` has more than {decimals} decimals")]
+    TooManyDecimals { value: String, decimals: u8 },
+    #[error("expected at most 32 bytes, got {0}")]
+    TooManyBytes(usize),
+    #[error("expected a U256 or U64 value-type, got {0}")]
+    InvalidValueType(String),
+}
+
+/// An unsigned 256-bit integer, as used for token amounts on EVM and Move chains.
+///
+/// It converts to and from the `U256(list<u8>)` value-type, encoded as 32 big-endian bytes.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U256(primitive_types::U256);
+
+impl U256 {
+    pub const ZERO: U256 = U256(primitive_types::U256::zero());
+    pub const ONE: U256 = U256(primitive_types::U256::one());
+    pub const MAX: U256 = U256(primitive_types::U256::MAX);
+
+    /// Parses a decimal string such as `1000000000000000000`.
+    pub fn from_dec_str(value: &str) -> Result<Self, U256Error> {
+        mamoru_rust_sdk_parse::check_decimal_u256(value).map_err(|e| digits_error(e, value))?;
+
+        primitive_types::U256::from_dec_str(value)
+            .map(U256)
+            .map_err(|_| U256Error::Overflow)
+    }
+
+    /// Parses a hexadecimal string, with or without the `0x` prefix.
+    pub fn from_hex_str(value: &str) -> Result<Self, U256Error> {
+        let digits = strip_hex_prefix(value).unwrap_or(value);
+        mamoru_rust_sdk_parse::check_hex_u256(digits).map_err(|e| digits_error(e, value))?;
+
+        primitive_types::U256::from_str_radix(digits.trim_start_matches('0'), 16)
+            .map(U256)
+            .map_err(|_| U256Error::InvalidDigit(value.to_string()))
+    }
+
+    /// Parses a decimal amount of tokens, e.g. `1.5` with 18 decimals is `1500000000000000000`.
+    ///
+    /// Parameters:
+    /// - `value`: The amount, with at most `decimals` fractional digits.
+    /// - `decimals`: The number of decimals of the token.
+    pub fn parse_units(value: &str, decimals: u8) -> Result<Self, U256Error> {
+        let (integer, fraction) = value.split_once('.').unwrap_or((value, ""));
+        let fraction = fraction.trim_end_matches('0');
+
+        if fraction.len() > decimals as usize {
+            return Err(U256Error::TooManyDecimals {
+                value: value.to_string(),
+                decimals,
+            });
+        }
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(U256Error::Empty);
+        }
+
+        let padding = "0".repeat(decimals as usize - fraction.len());
+        let digits = format!("{integer}{fraction}{padding}");
+        let digits = digits.trim_start_matches('0');
+
+        if digits.is_empty() {
+            return Ok(U256::ZERO);
+        }
+
+        Self::from_dec_str(digits).map_err(|e| match e {
+            U256Error::InvalidDigit(_) => U256Error::InvalidDigit(value.to_string()),
+            other => other,
+        })
+    }
+
+    /// Formats the value as a decimal amount of tokens, e.g. `1500000000000000000` with 18
+    /// decimals is `1.5`. Trailing zeros of the fractional part are removed.
+    pub fn format_units(&self, decimals: u8) -> String {
+        let digits = self.0.to_string();
+        let decimals = decimals as usize;
+
+        if decimals == 0 {
+            return digits;
+        }
+
+        let digits = format!("{digits:0>width$}", width = decimals + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - decimals);
+        let fraction = fraction.trim_end_matches('0');
+
+        if fraction.is_empty() {
+            integer.to_string()
+        } else {
+            format!("{integer}.{fraction}")
+        }
+    }
+
+    /// Creates a `U256` from at most 32 big-endian bytes.
+    pub fn from_be_slice(bytes: &[u8]) -> Result<Self, U256Error> {
+        if bytes.len() > BYTES {
+            return Err(U256Error::TooManyBytes(bytes.len()));
+        }
+
+        Ok(U256(primitive_types::U256::from_big_endian(bytes)))
+    }
+
+    /// Returns the 32 big-endian bytes of the value.
+    pub fn to_be_bytes(&self) -> [u8; BYTES] {
+        let mut bytes = [0; BYTES];
+        self.0.to_big_endian(&mut bytes);
+        bytes
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn checked_add(self, other: U256) -> Option<U256> {
+        self.0.checked_add(other.0).map(U256)
+    }
+
+    pub fn checked_sub(self, other: U256) -> Option<U256> {
+        self.0.checked_sub(other.0).map(U256)
+    }
+
+    pub fn checked_mul(self, other: U256) -> Option<U256> {
+        self.0.checked_mul(other.0).map(U256)
+    }
+
+    pub fn checked_div(self, other: U256) -> Option<U256> {
+        self.0.checked_div(other.0).map(U256)
+    }
+
+    pub fn checked_rem(self, other: U256) -> Option<U256> {
+        self.0.checked_rem(other.0).map(U256)
+    }
+
+    pub fn checked_pow(self, exponent: u32) -> Option<U256> {
+        self.0.checked_pow(exponent.into()).map(U256)
+    }
+
+    pub fn saturating_add(self, other: U256) -> U256 {
+        U256(self.0.saturating_add(other.0))
+    }
+
+    pub fn saturating_sub(self, other: U256) -> U256 {
+        U256(self.0.saturating_sub(other.0))
+    }
+
+    pub fn saturating_mul(self, other: U256) -> U256 {
+        U256(self.0.saturating_mul(other.0))
+    }
+
+    /// Returns the absolute difference between two values.
+    pub fn abs_diff(self, other: U256) -> U256 {
+        match self.cmp(&other) {
+            Ordering::Less => U256(other.0 - self.0),
+            _ => U256(self.0 - other.0),
+        }
+    }
+}
+
+fn digits_error(error: U256DigitsError, value: &str) -> U256Error {
+    match error {
+        U256DigitsError::Empty => U256Error::Empty,
+        U256DigitsError::InvalidDigit => U256Error::InvalidDigit(value.to_string()),
+        U256DigitsError::Overflow => U256Error::Overflow,
+    }
+}
+
+/// Parses `0x` prefixed values as hexadecimal and anything else as decimal.
+impl FromStr for U256 {
+    type Err = U256Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match strip_hex_prefix(value) {
+            Some(_) => Self::from_hex_str(value),
+            None => Self::from_dec_str(value),
+        }
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::LowerHex for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+macro_rules! impl_from_unsigned {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for U256 {
+            fn from(value: $ty) -> Self {
+                U256(value.into())
+            }
+        }
+    )*};
+}
+
+impl_from_unsigned!(u8, u16, u32, u64, u128);
+
+impl TryFrom<U256> for u64 {
+    type Error = U256Error;
+
+    fn try_from(value: U256) -> Result<Self, Self::Error> {
+        u64::try_from(value.0).map_err(|_| U256Error::Overflow)
+    }
+}
+
+impl TryFrom<U256> for u128 {
+    type Error = U256Error;
+
+    fn try_from(value: U256) -> Result<Self, Self::Error> {
+        u128::try_from(value.0).map_err(|_| U256Error::Overflow)
+    }
+}
+
+impl From<U256> for ValueType {
+    fn from(value: U256) -> Self {
+        ValueType::U256(value.to_be_bytes().to_vec())
+    }
+}
+
+impl From<U256> for ValueData {
+    fn from(value: U256) -> Self {
+        ValueData {
+            data: None,
+            value: value.into(),
+        }
+    }
+}
+
+/// Reads a `U256` or `U64` value-type.
+impl TryFrom<&ValueType> for U256 {
+    type Error = U256Error;
+
+    fn try_from(value: &ValueType) -> Result<Self, Self::Error> {
+        match value {
+            ValueType::U256(bytes) => Self::from_be_slice(bytes),
+            ValueType::U64(value) => Ok((*value).into()),
+            other => Err(U256Error::InvalidValueType(format!("{other:?}"))),
+        }
+    }
+}
+
+impl TryFrom<&ValueData> for U256 {
+    type Error = U256Error;
+
+    fn try_from(value: &ValueData) -> Result<Self, Self::Error> {
+        U256::try_from(&value.value)
+    }
+}
+
+/// Serialized as a decimal string by human-readable formats such as JSON, and as a `U256`
+/// value-type by [`crate::mamoru_serialize::to_value_data`].
+impl Serialize for U256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_newtype_struct(U256_TOKEN, &BigEndianBytes(self.to_be_bytes()))
+        }
+    }
+}
+
+struct BigEndianBytes([u8; BYTES]);
+
+impl Serialize for BigEndianBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+/// Accepts decimal or `0x` hex strings, integers, and big-endian bytes.
+impl<'de> Deserialize<'de> for U256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(U256Visitor)
+        } else {
+            deserializer.deserialize_newtype_struct(U256_TOKEN, U256Visitor)
+        }
+    }
+}
+
+struct U256Visitor;
+
+impl<'de> Visitor<'de> for U256Visitor {
+    type Value = U256;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a 256-bit unsigned integer")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<U256, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<U256, E> {
+        Ok(value.into())
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<U256, E> {
+        Ok(value.into())
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<U256, E> {
+        value.parse().map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<U256, E> {
+        U256::from_be_slice(value).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_DEC: &str =
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+
+    #[test]
+    fn parses_decimal_and_hex() {
+        assert_eq!("0".parse::<U256>(), Ok(U256::ZERO));
+        assert_eq!("1000".parse::<U256>(), Ok(U256::from(1000u64)));
+        assert_eq!("0x3e8".parse::<U256>(), Ok(U256::from(1000u64)));
+        assert_eq!("0X3E8".parse::<U256>(), Ok(U256::from(1000u64)));
+        assert_eq!(U256::from_hex_str("3e8"), Ok(U256::from(1000u64)));
+        assert_eq!(MAX_DEC.parse::<U256>(), Ok(U256::MAX));
+        assert_eq!(
+            format!("0x{}", "f".repeat(64)).parse::<U256>(),
+            Ok(U256::MAX)
+        );
+        assert_eq!(
+            format!("0x{}1", "0".repeat(70)).parse::<U256>(),
+            Ok(U256::ONE)
+        );
+        assert_eq!(
+            format!("{}1", "0".repeat(90)).parse::<U256>(),
+            Ok(U256::ONE)
+        );
+        assert_eq!("0x000".parse::<U256>(), Ok(U256::ZERO));
+    }
+
+    #[test]
+    fn rejects_invalid_numbers() {
+        assert_eq!("".parse::<U256>(), Err(U256Error::Empty));
+        assert_eq!("0x".parse::<U256>(), Err(U256Error::Empty));
+        assert_eq!(
+            "-1".parse::<U256>(),
+            Err(U256Error::InvalidDigit("-1".to_string()))
+        );
+        assert_eq!(
+            "0xzz".parse::<U256>(),
+            Err(U256Error::InvalidDigit("0xzz".to_string()))
+        );
+        assert_eq!(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+                .parse::<U256>(),
+            Err(U256Error::Overflow)
+        );
+        assert_eq!(
+            format!("0x1{}", "0".repeat(64)).parse::<U256>(),
+            Err(U256Error::Overflow)
+        );
+    }
+
+    #[test]
+    fn formats_values() {
+        assert_eq!(U256::MAX.to_string(), MAX_DEC);
+        assert_eq!(format!("{:x}", U256::from(255u64)), "ff");
+        assert_eq!(format!("{:?}", U256::from(7u64)), "7");
+    }
+
+    #[test]
+    fn checks_arithmetic() {
+        let two = U256::from(2u64);
+
+        assert_eq!(U256::MAX.checked_add(U256::ONE), None);
+        assert_eq!(U256::MAX.checked_add(U256::ZERO), Some(U256::MAX));
+        assert_eq!(U256::ZERO.checked_sub(U256::ONE), None);
+        assert_eq!(U256::MAX.checked_mul(two), None);
+        assert_eq!(U256::ONE.checked_div(U256::ZERO), None);
+        assert_eq!(U256::ONE.checked_rem(U256::ZERO), None);
+        assert_eq!(
+            two.checked_pow(255),
+            U256::from_hex_str(&format!("8{}", "0".repeat(63))).ok()
+        );
+        assert_eq!(two.checked_pow(256), None);
+        assert_eq!(
+            U256::from(7u64).checked_rem(U256::from(3u64)),
+            Some(U256::ONE)
+        );
+
+        assert_eq!(U256::MAX.saturating_add(U256::ONE), U256::MAX);
+        assert_eq!(U256::ZERO.saturating_sub(U256::ONE), U256::ZERO);
+        assert_eq!(U256::MAX.saturating_mul(two), U256::MAX);
+        assert_eq!(U256::ZERO.abs_diff(U256::MAX), U256::MAX);
+        assert_eq!(U256::from(3u64).abs_diff(U256::from(5u64)), two);
+    }
+
+    #[test]
+    fn converts_bytes_and_integers() {
+        assert_eq!(U256::from_be_slice(&[]), Ok(U256::ZERO));
+        assert_eq!(U256::from_be_slice(&[1, 0]), Ok(U256::from(256u64)));
+        assert_eq!(U256::from_be_slice(&[0xff; 32]), Ok(U256::MAX));
+        assert_eq!(
+            U256::from_be_slice(&[0; 33]),
+            Err(U256Error::TooManyBytes(33))
+        );
+        assert_eq!(U256::MAX.to_be_bytes(), [0xff; 32]);
+
+        assert_eq!(u64::try_from(U256::from(u64::MAX)), Ok(u64::MAX));
+        assert_eq!(
+            u64::try_from(U256::from(u128::from(u64::MAX) + 1)),
+            Err(U256Error::Overflow)
+        );
+        assert_eq!(u128::try_from(U256::MAX), Err(U256Error::Overflow));
+        assert_eq!(U256::try_from(&ValueType::U64(5)), Ok(U256::from(5u64)));
+        assert_eq!(U256::try_from(&ValueType::from(U256::MAX)), Ok(U256::MAX));
+        assert!(matches!(
+            U256::try_from(&ValueType::Bool(true)),
+            Err(U256Error::InvalidValueType(_))
+        ));
+    }
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(
+            U256::parse_units("1.5", 18),
+            Ok(U256::from(1_500_000_000_000_000_000u64))
+        );
+        assert_eq!(U256::parse_units("1", 0), Ok(U256::ONE));
+        assert_eq!(U256::parse_units(".5", 1), Ok(U256::from(5u64)));
+        assert_eq!(U256::parse_units("2.", 2), Ok(U256::from(200u64)));
+        assert_eq!(U256::parse_units("0.000", 0), Ok(U256::ZERO));
+        assert_eq!(U256::parse_units("1.2300", 2), Ok(U256::from(123u64)));
+        assert_eq!(U256::parse_units(MAX_DEC, 0), Ok(U256::MAX));
+        assert_eq!(U256::parse_units("0", 200), Ok(U256::ZERO));
+        assert_eq!(
+            U256::parse_units(&format!("0.{}", "0".repeat(199)), 200),
+            Ok(U256::ZERO)
+        );
+        assert_eq!(
+            U256::parse_units(&format!("0.{}1", "0".repeat(199)), 200),
+            Ok(U256::ONE)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_units() {
+        assert_eq!(
+            U256::parse_units("1.234", 2),
+            Err(U256Error::TooManyDecimals {
+                value: "1.234".to_string(),
+                decimals: 2
+            })
+        );
+        assert_eq!(
+            U256::parse_units("0.5", 0),
+            Err(U256Error::TooManyDecimals {
+                value: "0.5".to_string(),
+                decimals: 0
+            })
+        );
+        assert_eq!(U256::parse_units(".", 18), Err(U256Error::Empty));
+        assert_eq!(U256::parse_units("", 18), Err(U256Error::Empty));
+        assert_eq!(
+            U256::parse_units("1.2.3", 18),
+            Err(U256Error::InvalidDigit("1.2.3".to_string()))
+        );
+        assert_eq!(
+            U256::parse_units("-1", 18),
+            Err(U256Error::InvalidDigit("-1".to_string()))
+        );
+        // 10^78 is above U256::MAX.
+        assert_eq!(U256::parse_units("1", 78), Err(U256Error::Overflow));
+        assert_eq!(U256::parse_units("0.5", 200), Err(U256Error::Overflow));
+        assert_eq!(
+            U256::parse_units(&format!("{MAX_DEC}.0"), 1),
+            Err(U256Error::Overflow)
+        );
+    }
+
+    #[test]
+    fn formats_units() {
+        let amount = U256::from(1_500_000_000_000_000_000u64);
+
+        assert_eq!(amount.format_units(18), "1.5");
+        assert_eq!(amount.format_units(0), "1500000000000000000");
+        assert_eq!(U256::ZERO.format_units(18), "0");
+        assert_eq!(U256::ONE.format_units(3), "0.001");
+        assert_eq!(U256::from(1000u64).format_units(3), "1");
+        assert_eq!(
+            U256::MAX.format_units(77),
+            "1.15792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+        assert_eq!(U256::MAX.format_units(80), format!("0.00{MAX_DEC}"));
+        assert_eq!(
+            U256::ONE.format_units(255),
+            format!("0.{}1", "0".repeat(254))
+        );
+    }
+
+    #[test]
+    fn round_trips_units() {
+        for (value, decimals) in [("1.5", 18), ("0.001", 3), ("123", 0), (MAX_DEC, 0)] {
+            let amount = U256::parse_units(value, decimals).unwrap();
+            assert_eq!(amount.format_units(decimals), value);
+        }
+    }
+}
@@ -0,0 +1,5 @@
+#[test]
+fn rejects_unsupported_contexts_at_build_time() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/agent/*.rs");
+}
@@ -0,0 +1,162 @@
+use mamoru_rust_sdk::mamoru::{AgentParameters, FromParameter, ParameterError, ParameterSpec};
+use mamoru_rust_sdk::u256::U256;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, AgentParameters)]
+struct Params {
+    #[param(default = 10, min = 1, max = 100)]
+    max_withdrawals: u64,
+    #[param(key = "pool", default = "0x2")]
+    pool_address: String,
+    #[param(default = "10m")]
+    window: Duration,
+    watched_tokens: Vec<String>,
+    threshold: Option<f64>,
+    #[param(default = "0x10")]
+    amount: U256,
+}
+
+fn config(entries: &[(&str, &str)]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn load<T: AgentParameters>(entries: &[(&str, &str)]) -> Result<T, Vec<ParameterError>> {
+    let config = config(entries);
+    T::load_from(&|key| config.get(key).cloned())
+}
+
+#[test]
+fn loads_defaults() {
+    let params: Params = load(&[("watched_tokens", "SUI, USDC,")]).unwrap();
+
+    assert_eq!(
+        params,
+        Params {
+            max_withdrawals: 10,
+            pool_address: "0x2".to_string(),
+            window: Duration::from_secs(600),
+            watched_tokens: vec!["SUI".to_string(), "USDC".to_string()],
+            threshold: None,
+            amount: U256::from(16u64),
+        }
+    );
+}
+
+#[test]
+fn loads_set_values() {
+    let params: Params = load(&[
+        ("max_withdrawals", "0x20"),
+        ("pool", "0x5"),
+        ("window", "1h30m"),
+        ("watched_tokens", "SUI"),
+        ("threshold", "0.5"),
+        ("amount", "1000"),
+    ])
+    .unwrap();
+
+    assert_eq!(params.max_withdrawals, 32);
+    assert_eq!(params.pool_address, "0x5");
+    assert_eq!(params.window, Duration::from_secs(90 * 60));
+    assert_eq!(params.threshold, Some(0.5));
+    assert_eq!(params.amount, U256::from(1000u64));
+}
+
+#[test]
+fn collects_every_error() {
+    let errors = load::<Params>(&[("max_withdrawals", "101"), ("window", "10w")]).unwrap_err();
+    let keys: Vec<_> = errors.iter().map(ParameterError::key).collect();
+
+    assert_eq!(keys, ["max_withdrawals", "window", "watched_tokens"]);
+    assert!(matches!(errors[0], ParameterError::OutOfRange { .. }));
+    assert!(matches!(errors[1], ParameterError::Malformed { .. }));
+    assert!(matches!(errors[2], ParameterError::Missing { .. }));
+}
+
+#[test]
+fn validates_configurations() {
+    let valid = config(&[("watched_tokens", "SUI"), ("threshold", " ")]);
+    let invalid = config(&[("watched_tokens", " ")]);
+
+    assert_eq!(Params::validate(&valid), Ok(()));
+    assert_eq!(
+        Params::validate(&invalid).unwrap_err(),
+        [ParameterError::Missing {
+            key: "watched_tokens".to_string()
+        }]
+    );
+}
+
+#[test]
+fn describes_the_schema() {
+    let schema = Params::schema();
+
+    assert_eq!(
+        schema[0],
+        ParameterSpec {
+            key: "max_withdrawals",
+            type_name: "u64",
+            required: false,
+            default: Some("10"),
+            min: Some("1"),
+            max: Some("100"),
+        }
+    );
+    assert_eq!(schema[1].key, "pool");
+    assert!(schema[3].required);
+    assert_eq!(schema[3].type_name, "Vec<String>");
+    assert!(!schema[4].required);
+    assert!(Params::schema_json().starts_with(r#"[{"key":"max_withdrawals","type":"u64""#));
+}
+
+#[derive(Debug)]
+struct Even(u64);
+
+impl FromParameter for Even {
+    fn from_parameter(key: &str, value: Option<&str>) -> Result<Self, ParameterError> {
+        let value = u64::from_parameter(key, value)?;
+        if value % 2 != 0 {
+            return Err(ParameterError::Malformed {
+                key: key.to_string(),
+                value: value.to_string(),
+                reason: "expected an even number".to_string(),
+            });
+        }
+
+        Ok(Even(value))
+    }
+}
+
+#[derive(Debug, AgentParameters)]
+struct CustomParams {
+    #[param(default = 3)]
+    even: Even,
+}
+
+#[test]
+fn reports_invalid_declared_defaults() {
+    let errors = load::<CustomParams>(&[]).unwrap_err();
+    assert_eq!(
+        errors,
+        [ParameterError::InvalidDeclaration {
+            key: "even".to_string(),
+            value: "3".to_string(),
+            reason: "expected an even number".to_string(),
+        }]
+    );
+
+    let params: CustomParams = load(&[("even", "4")]).unwrap();
+    assert_eq!(params.even.0, 4);
+
+    let errors = load::<CustomParams>(&[("even", "5")]).unwrap_err();
+    assert!(matches!(errors[0], ParameterError::Malformed { .. }));
+}
+
+#[test]
+fn rejects_invalid_declarations_at_build_time() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/agent_parameters/*.rs");
+}
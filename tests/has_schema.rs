@@ -0,0 +1,76 @@
+use mamoru_rust_sdk::mamoru_serialize::{FromValueData, HasSchema, IntoValueData, ValueSchema};
+use serde::Deserialize;
+
+fn field_names<T: HasSchema>() -> Vec<(String, bool)> {
+    match T::schema() {
+        ValueSchema::Record(record) => record
+            .fields
+            .into_iter()
+            .map(|field| (field.name, field.required))
+            .collect(),
+        other => panic!("expected a record schema, got {other:?}"),
+    }
+}
+
+#[derive(Deserialize, HasSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct Transfer {
+    gas_used: u64,
+    #[serde(rename = "from")]
+    sender_address: String,
+    #[serde(default)]
+    token_kind: Option<String>,
+    #[serde(skip)]
+    cached: u64,
+}
+
+#[test]
+fn follows_serde_renames() {
+    assert_eq!(
+        field_names::<Transfer>(),
+        [
+            ("gasUsed".to_string(), true),
+            ("from".to_string(), true),
+            ("tokenKind".to_string(), false),
+        ]
+    );
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all(serialize = "snake_case", deserialize = "SCREAMING-KEBAB-CASE"))]
+#[derive(HasSchema)]
+#[allow(dead_code)]
+struct Limits {
+    max_gas: u64,
+}
+
+#[test]
+fn follows_the_deserialize_rename_rule() {
+    assert_eq!(field_names::<Limits>(), [("MAX-GAS".to_string(), true)]);
+}
+
+#[derive(IntoValueData, FromValueData, HasSchema)]
+#[value(rename = "coin")]
+#[allow(dead_code)]
+struct Coin {
+    #[value(rename = "type")]
+    coin_type: String,
+    balance: u64,
+}
+
+#[test]
+fn follows_value_renames() {
+    assert_eq!(
+        field_names::<Coin>(),
+        [("type".to_string(), true), ("balance".to_string(), true)]
+    );
+
+    let data = Coin {
+        coin_type: "0x2::sui::SUI".to_string(),
+        balance: 10,
+    }
+    .try_into_value_data()
+    .unwrap();
+    assert!(Coin::schema().validate(&data).is_ok());
+}
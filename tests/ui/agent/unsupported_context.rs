@@ -0,0 +1,10 @@
+use mamoru_rust_sdk::mamoru::{agent, AgentError};
+
+struct EvmCtx;
+
+#[agent]
+fn run(_ctx: EvmCtx) -> Result<(), AgentError> {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,41 @@
+use mamoru_rust_sdk::mamoru::AgentParameters;
+use mamoru_rust_sdk::u256::U256;
+use std::time::Duration;
+
+#[derive(AgentParameters)]
+struct Integer {
+    #[param(default = 300)]
+    limit: u8,
+}
+
+#[derive(AgentParameters)]
+struct Bound {
+    #[param(min = -1)]
+    limit: u32,
+}
+
+#[derive(AgentParameters)]
+struct Flag {
+    #[param(default = "yes")]
+    enabled: bool,
+}
+
+#[derive(AgentParameters)]
+struct Window {
+    #[param(default = "10w")]
+    window: Duration,
+}
+
+#[derive(AgentParameters)]
+struct Amount {
+    #[param(default = "0xzz")]
+    amount: U256,
+}
+
+#[derive(AgentParameters)]
+struct Ratios {
+    #[param(default = "0.5, half")]
+    ratios: Vec<f64>,
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+use mamoru_rust_sdk::mamoru_serialize::{FromValueData, IntoValueData};
+
+#[derive(IntoValueData)]
+enum DefaultTag {
+    Transfer { variant: String },
+}
+
+#[derive(FromValueData)]
+#[value(tag = "kind")]
+enum CustomTag {
+    Transfer {
+        #[value(rename = "kind")]
+        transfer_kind: String,
+    },
+}
+
+#[derive(IntoValueData)]
+struct Hints {
+    #[value(u256, bytes)]
+    amount: u64,
+}
+
+#[derive(IntoValueData)]
+struct Tagged {
+    #[value(tag = "kind")]
+    amount: u64,
+}
+
+fn main() {}
@@ -0,0 +1,169 @@
+use mamoru_rust_sdk::component::guest::types::ValueData;
+use mamoru_rust_sdk::mamoru_serialize::{
+    from_json, to_json, FromValueData, IntoValueData, ViewError,
+};
+use serde_json::json;
+use std::fmt::Debug;
+
+fn round_trip<T>(value: T, expected: serde_json::Value)
+where
+    T: IntoValueData + FromValueData + Clone + PartialEq + Debug,
+{
+    let data = value.clone().try_into_value_data().unwrap();
+
+    assert_eq!(to_json(&data).unwrap(), expected);
+    assert_eq!(T::from_value_data(&data).unwrap(), value);
+}
+
+#[derive(Clone, Debug, PartialEq, IntoValueData, FromValueData)]
+#[value(rename = "0x2::pool::Event", tag = "kind")]
+enum PoolEvent {
+    Paused,
+    #[value(rename = "swap")]
+    Swap {
+        pool: String,
+        #[value(u256)]
+        amount: u128,
+        memo: Option<String>,
+    },
+    Deposit(#[value(bytes)] Vec<u8>, u64),
+}
+
+#[derive(Clone, Debug, PartialEq, IntoValueData, FromValueData)]
+struct Transfer {
+    #[value(u256)]
+    amount: u64,
+    #[value(bytes)]
+    digest: [u8; 4],
+    #[value(u256)]
+    fee: Option<u128>,
+    #[value(bytes)]
+    memo: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, PartialEq, IntoValueData, FromValueData)]
+enum Status {
+    Active { since: u64 },
+}
+
+#[test]
+fn round_trips_unit_variants() {
+    round_trip(
+        PoolEvent::Paused,
+        json!({"$struct": "0x2::pool::Event", "$fields": {"kind": "Paused"}}),
+    );
+}
+
+#[test]
+fn round_trips_struct_variants() {
+    round_trip(
+        PoolEvent::Swap {
+            pool: "0xa".to_string(),
+            amount: u128::MAX,
+            memo: None,
+        },
+        json!({"$struct": "0x2::pool::Event", "$fields": {
+            "kind": "swap",
+            "pool": "0xa",
+            "amount": {"$u256": "0xffffffffffffffffffffffffffffffff"},
+        }}),
+    );
+    round_trip(
+        PoolEvent::Deposit(vec![1, 2], 3),
+        json!({"$struct": "0x2::pool::Event", "$fields": {
+            "kind": "Deposit",
+            "0": {"$bytes": "0x0102"},
+            "1": 3,
+        }}),
+    );
+}
+
+#[test]
+fn tags_variants_with_variant_by_default() {
+    round_trip(
+        Status::Active { since: 5 },
+        json!({"$struct": "Status", "$fields": {"variant": "Active", "since": 5}}),
+    );
+}
+
+#[test]
+fn rejects_unknown_variants() {
+    let data = from_json(&json!({"kind": "Burned"})).unwrap();
+
+    assert_eq!(
+        PoolEvent::from_value_data(&data).unwrap_err(),
+        ViewError::InvalidValue {
+            path: "kind".to_string(),
+            reason: "unknown variant `Burned`".to_string(),
+        }
+    );
+    assert!(matches!(
+        PoolEvent::from_value_data(&from_json(&json!({})).unwrap()),
+        Err(ViewError::MissingField { path }) if path == "kind"
+    ));
+}
+
+#[test]
+fn round_trips_hinted_fields() {
+    round_trip(
+        Transfer {
+            amount: 7,
+            digest: [1, 2, 3, 4],
+            fee: Some(1),
+            memo: Some(b"gm".to_vec()),
+        },
+        json!({"$struct": "Transfer", "$fields": {
+            "amount": {"$u256": "0x7"},
+            "digest": {"$bytes": "0x01020304"},
+            "fee": {"$u256": "0x1"},
+            "memo": {"$bytes": "0x676d"},
+        }}),
+    );
+    round_trip(
+        Transfer {
+            amount: 0,
+            digest: [0; 4],
+            fee: None,
+            memo: None,
+        },
+        json!({"$struct": "Transfer", "$fields": {
+            "amount": {"$u256": "0x0"},
+            "digest": {"$bytes": "0x00000000"},
+        }}),
+    );
+}
+
+#[test]
+fn rejects_hinted_values_that_do_not_fit() {
+    let read = |value| Transfer::from_value_data(&from_json(&value).unwrap());
+
+    assert!(matches!(
+        read(json!({"amount": {"$u256": "0x10000000000000000"}, "digest": {"$bytes": "0x01020304"}})),
+        Err(ViewError::InvalidValue { path, .. }) if path == "amount"
+    ));
+    assert!(matches!(
+        read(json!({"amount": 1, "digest": {"$bytes": "0x0102"}})),
+        Err(ViewError::InvalidValue { path, reason })
+            if path == "digest" && reason.starts_with("2 bytes do not fit")
+    ));
+    assert!(matches!(
+        read(json!({"amount": 1, "digest": [1, 2, 3, 4]})),
+        Err(ViewError::TypeMismatch { path, expected: "BYTES", .. }) if path == "digest"
+    ));
+}
+
+#[test]
+fn converts_with_try_from() {
+    let data = ValueData::try_from(Status::Active { since: 1 }).unwrap();
+
+    assert_eq!(
+        Status::from_value_data(&data).unwrap(),
+        Status::Active { since: 1 }
+    );
+}
+
+#[test]
+fn rejects_invalid_declarations_at_build_time() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/value_data/*.rs");
+}